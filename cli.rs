@@ -0,0 +1,401 @@
+use colorful::Colorful;
+use std::fmt::Display;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+
+use snooze_core::assertions;
+use snooze_core::collection::{self, Workspace};
+use snooze_core::environment::Environments;
+use snooze_core::executor::Executor;
+use snooze_core::report;
+use snooze_core::runner::{self, RunEvent, RunOptions, RunStep, StepResult};
+use snooze_core::settings::Settings;
+
+const USAGE: &str = "usage: Snooze run [options] [folder or request]
+
+runs a folder of saved requests (the whole collection by default) without
+the gui and exits with 1 if any request fails or assertion doesn't hold
+
+options:
+  --workspace <dir>     workspace to load (default: $SNOOZE_WORKSPACE or ./snooze-workspace)
+  --env <name>          environment to use instead of the active one
+  --var <name=value>    set a variable, overriding the environment (repeatable)
+  --data <file>         csv or json file, running everything once per row
+  --concurrency <n>     requests sent at once (default: 1)
+  --bail                stop at the first failure
+  --junit <file>        write a JUnit XML report
+  --json <file>         write a JSON report
+  --no-color            plain output, also when NO_COLOR is set or not on a terminal";
+
+/// what `Snooze run` was asked to do
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    target: Option<String>,
+    workspace: Option<PathBuf>,
+    environment: Option<String>,
+    variables: Vec<(String, String)>,
+    options: RunOptions,
+    junit: Option<PathBuf>,
+    json: Option<PathBuf>,
+    no_color: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+        match arg.as_str() {
+            "--workspace" => parsed.workspace = Some(value(arg)?.into()),
+            "--env" => parsed.environment = Some(value(arg)?),
+            "--var" => {
+                let pair = value(arg)?;
+                let (name, val) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("--var expects name=value, got {:?}", pair))?;
+                parsed.variables.push((name.to_owned(), val.to_owned()));
+            }
+            "--data" => parsed.options.data_file = value(arg)?,
+            "--concurrency" => {
+                let n = value(arg)?;
+                parsed.options.concurrency =
+                    n.parse().ok().filter(|n| *n > 0).ok_or_else(|| {
+                        format!("--concurrency expects a positive number, got {:?}", n)
+                    })?;
+            }
+            "--bail" => parsed.options.stop_on_failure = true,
+            "--junit" => parsed.junit = Some(value(arg)?.into()),
+            "--json" => parsed.json = Some(value(arg)?.into()),
+            "--no-color" => parsed.no_color = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            target if parsed.target.is_none() => parsed.target = Some(target.to_owned()),
+            extra => return Err(format!("unexpected argument {:?}", extra)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// colors output when it goes to a terminal and wasn't turned off
+struct Painter {
+    enabled: bool,
+}
+
+impl Painter {
+    fn paint<D: Display>(&self, text: &str, color: impl Fn(&str) -> D) -> String {
+        if self.enabled {
+            color(text).to_string()
+        } else {
+            text.to_owned()
+        }
+    }
+
+    fn green(&self, text: &str) -> String {
+        self.paint(text, |t| t.green())
+    }
+
+    fn red(&self, text: &str) -> String {
+        self.paint(text, |t| t.red())
+    }
+
+    fn yellow(&self, text: &str) -> String {
+        self.paint(text, |t| t.yellow())
+    }
+
+    fn dim(&self, text: &str) -> String {
+        self.paint(text, |t| t.dim())
+    }
+
+    fn bold(&self, text: &str) -> String {
+        self.paint(text, |t| t.bold())
+    }
+}
+
+/// `Snooze run`, returning the process exit code: 0 when everything passed,
+/// 1 when something failed and 2 when the run couldn't start
+pub fn main(args: &[String]) -> i32 {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return 0;
+    }
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+    let painter = Painter {
+        enabled: !args.no_color
+            && std::env::var_os("NO_COLOR").is_none()
+            && std::io::stdout().is_terminal(),
+    };
+    match run(&args, &painter) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("{}", painter.red(&e));
+            2
+        }
+    }
+}
+
+/// loads everything, runs it and prints results as they arrive. `Ok(true)`
+/// when every step passed
+fn run(args: &Args, painter: &Painter) -> Result<bool, String> {
+    let root = args
+        .workspace
+        .clone()
+        .unwrap_or_else(collection::default_workspace_dir);
+    let workspace = Workspace::open(&root)
+        .map_err(|e| format!("failed to open workspace {}: {}", root.display(), e))?;
+    let target = resolve_target(&workspace, args.target.as_deref())?;
+    let (suite, steps) = load_target(&workspace, &target)?;
+    if steps.is_empty() {
+        return Err(format!("no requests in {}", target.display()));
+    }
+
+    let mut environments = Environments::load(&workspace.environments_path())
+        .map_err(|e| format!("failed to load environments: {}", e))?;
+    if let Some(name) = &args.environment {
+        if !environments
+            .environments
+            .iter()
+            .any(|env| &env.name == name)
+        {
+            return Err(format!("no environment named {:?}", name));
+        }
+        environments.active = Some(name.clone());
+    }
+    let mut variables = environments.active_variables();
+    variables.extend(args.variables.iter().cloned());
+    let settings = Settings::load(&workspace.settings_path())
+        .map_err(|e| format!("failed to load settings: {}", e))?;
+    let data = match args.options.data_file.trim() {
+        "" => Vec::new(),
+        file => runner::load_data(Path::new(file))?,
+    };
+
+    let iterations = data.len().max(1);
+    println!(
+        "{} {} ({} request(s){}{})",
+        painter.bold("running"),
+        suite,
+        steps.len(),
+        if iterations > 1 {
+            format!(", {} iterations", iterations)
+        } else {
+            String::new()
+        },
+        environments
+            .active
+            .as_ref()
+            .map(|env| format!(", environment {}", env))
+            .unwrap_or_default()
+    );
+    let executor =
+        Executor::new().map_err(|e| format!("failed to start the request runtime: {}", e))?;
+    let (tx, rx) = channel();
+    let _abort = executor.run(
+        steps,
+        variables,
+        data,
+        args.options.clone(),
+        &settings.timeouts,
+        tx,
+    );
+    let mut results = Vec::new();
+    let mut stopped = false;
+    let mut iteration = None;
+    for event in rx {
+        match event {
+            RunEvent::Step(result) => {
+                if iterations > 1 && iteration != Some(result.iteration) {
+                    iteration = Some(result.iteration);
+                    println!(
+                        "{}",
+                        painter.dim(&format!("iteration {}", result.iteration + 1))
+                    );
+                }
+                print_step(painter, &result);
+                results.push(result);
+            }
+            RunEvent::Finished { stopped: s } => {
+                stopped = s;
+                break;
+            }
+        }
+    }
+
+    let passed = results.iter().filter(|r| r.passed()).count();
+    let failed = results.len() - passed;
+    let duration: std::time::Duration = results.iter().map(|r| r.duration).sum();
+    println!();
+    let mut summary = vec![painter.green(&format!("{} passed", passed))];
+    if failed > 0 {
+        summary.push(painter.red(&format!("{} failed", failed)));
+    }
+    summary.push(format!("{} ms", duration.as_millis()));
+    println!("{}", summary.join(", "));
+    if stopped {
+        println!(
+            "{}",
+            painter.yellow("stopped after the first failure (--bail)")
+        );
+    }
+
+    if let Some(path) = &args.junit {
+        fs::write(path, report::junit(&suite, &results, iterations))
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        println!(
+            "{}",
+            painter.dim(&format!("junit report: {}", path.display()))
+        );
+    }
+    if let Some(path) = &args.json {
+        let mut text = serde_json::to_string_pretty(&report::json(&suite, &results, iterations))
+            .map_err(|e| format!("failed to build the json report: {}", e))?;
+        text.push('\n');
+        fs::write(path, text).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        println!(
+            "{}",
+            painter.dim(&format!("json report: {}", path.display()))
+        );
+    }
+    Ok(failed == 0 && !stopped)
+}
+
+/// the folder or request `target` names, relative to the collections folder
+/// or as a path of its own; the whole collection when there's none
+fn resolve_target(workspace: &Workspace, target: Option<&str>) -> Result<PathBuf, String> {
+    let collections = workspace.collections_dir();
+    let Some(target) = target else {
+        return Ok(collections);
+    };
+    let target = target.trim_matches('/');
+    let candidates = [
+        collections.join(target),
+        collections.join(format!("{}.json", target)),
+        PathBuf::from(target),
+    ];
+    candidates
+        .into_iter()
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("no folder or request named {:?}", target))
+}
+
+/// a name for the report and the steps to run from `target`
+fn load_target(workspace: &Workspace, target: &Path) -> Result<(String, Vec<RunStep>), String> {
+    let collections = workspace.collections_dir();
+    let suite = match target.strip_prefix(&collections) {
+        Ok(relative) if relative.as_os_str().is_empty() => "collections".to_owned(),
+        Ok(relative) => relative.to_string_lossy().into_owned(),
+        Err(_) => target.display().to_string(),
+    };
+    if target.is_dir() {
+        return Ok((suite, runner::load_steps(target)?));
+    }
+    let request = Workspace::load_request(target)
+        .map_err(|e| format!("failed to load {}: {}", target.display(), e))?;
+    let step = RunStep {
+        name: collection::request_name(target),
+        request,
+    };
+    Ok((suite.trim_end_matches(".json").to_owned(), vec![step]))
+}
+
+fn print_step(painter: &Painter, result: &StepResult) {
+    let mark = if result.passed() {
+        painter.green("✔")
+    } else {
+        painter.red("✘")
+    };
+    let status = match (result.status, &result.error) {
+        (Some(status), _) if status >= 400 => painter.yellow(&status.to_string()),
+        (Some(status), _) => status.to_string(),
+        (None, _) => painter.red("err"),
+    };
+    let tests = if result.assertions.is_empty() {
+        String::new()
+    } else {
+        let passed = assertions::passed(&result.assertions);
+        let text = format!("tests {}/{}", passed, result.assertions.len());
+        if passed == result.assertions.len() {
+            painter.green(&text)
+        } else {
+            painter.red(&text)
+        }
+    };
+    println!(
+        "  {} {:<7} {}  {}  {}  {}",
+        mark,
+        format!("{:?}", result.method),
+        result.name,
+        status,
+        painter.dim(&format!("{} ms", result.duration.as_millis())),
+        tests
+    );
+    if let Some(error) = &result.error {
+        println!("      {}", painter.red(error));
+    }
+    for assertion in result.assertions.iter().filter(|a| !a.passed) {
+        println!(
+            "      {} {}: {}",
+            painter.red("✘"),
+            assertion.description,
+            assertion.message
+        );
+    }
+    for line in result.console.iter().filter(|line| line.error) {
+        println!("      {}", painter.red(&line.text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Result<Args, String> {
+        parse_args(&list.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_options() {
+        let parsed = args(&[
+            "api/users",
+            "--env",
+            "staging",
+            "--var",
+            "token=a=b",
+            "--concurrency",
+            "4",
+            "--bail",
+            "--junit",
+            "out.xml",
+        ])
+        .unwrap();
+        assert_eq!(parsed.target.as_deref(), Some("api/users"));
+        assert_eq!(parsed.environment.as_deref(), Some("staging"));
+        assert_eq!(
+            parsed.variables,
+            vec![("token".to_owned(), "a=b".to_owned())]
+        );
+        assert_eq!(parsed.options.concurrency, 4);
+        assert!(parsed.options.stop_on_failure);
+        assert_eq!(parsed.junit, Some(PathBuf::from("out.xml")));
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        assert!(args(&["--concurrency", "0"]).is_err());
+        assert!(args(&["--env"]).unwrap_err().contains("needs a value"));
+        assert!(args(&["--frobnicate"]).is_err());
+        assert!(args(&["a", "b"]).is_err());
+        assert!(args(&["--var", "novalue"]).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+use egui_code_editor::Syntax;
+use std::collections::BTreeSet;
+
+pub fn graphql_syntax() -> Syntax {
+    Syntax {
+        language: "graphql",
+        case_sensitive: true,
+        comment: "#",
+        // graphql only has line comments
+        comment_multiline: ["", ""],
+        hyperlinks: BTreeSet::new(),
+        keywords: BTreeSet::from([
+            "query",
+            "mutation",
+            "subscription",
+            "fragment",
+            "on",
+            "true",
+            "false",
+            "null",
+        ]),
+        // the built-in scalars
+        types: BTreeSet::from(["Int", "Float", "String", "Boolean", "ID"]),
+        special: BTreeSet::from(["__typename", "__schema", "__type"]),
+    }
+}
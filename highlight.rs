@@ -0,0 +1,120 @@
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Stroke};
+use std::collections::HashMap;
+
+use snooze_core::environment;
+
+const DEFINED_COLOR: Color32 = Color32::from_rgb(152, 230, 121);
+const UNDEFINED_COLOR: Color32 = Color32::from_rgb(255, 108, 117);
+const TAG_COLOR: Color32 = Color32::from_rgb(57, 186, 230);
+const ATTRIBUTE_COLOR: Color32 = Color32::from_rgb(255, 180, 84);
+const VALUE_COLOR: Color32 = Color32::from_rgb(170, 217, 76);
+const COMMENT_COLOR: Color32 = Color32::from_rgb(128, 128, 128);
+
+/// lays out `text` with `{{name}}` placeholders colored by whether the
+/// active environment defines them
+pub fn variable_layout_job(
+    text: &str,
+    font_id: FontId,
+    color: Color32,
+    vars: &HashMap<String, String>,
+) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let plain = TextFormat::simple(font_id.clone(), color);
+    let mut last = 0;
+    for (range, name) in environment::variable_spans(text) {
+        job.append(&text[last..range.start], 0.0, plain.clone());
+        let format = if vars.contains_key(name) {
+            TextFormat::simple(font_id.clone(), DEFINED_COLOR)
+        } else {
+            TextFormat {
+                underline: Stroke::new(1.0, UNDEFINED_COLOR),
+                ..TextFormat::simple(font_id.clone(), UNDEFINED_COLOR)
+            }
+        };
+        job.append(&text[range.clone()], 0.0, format);
+        last = range.end;
+    }
+    job.append(&text[last..], 0.0, plain);
+    job
+}
+
+/// lays out xml or html with tags, attribute names, attribute values and
+/// comments colored
+pub fn markup_layout_job(text: &str, font_id: FontId, color: Color32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            job.append(rest, 0.0, TextFormat::simple(font_id.clone(), color));
+            break;
+        };
+        job.append(
+            &rest[..start],
+            0.0,
+            TextFormat::simple(font_id.clone(), color),
+        );
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(rest.len(), |i| i + 3);
+            job.append(
+                &rest[..end],
+                0.0,
+                TextFormat::simple(font_id.clone(), COMMENT_COLOR),
+            );
+            rest = &rest[end..];
+            continue;
+        }
+        // the tag runs to the first `>` outside quotes
+        let mut quote = None;
+        let end = rest
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| match (c, quote) {
+                ('"' | '\'', None) => {
+                    quote = Some(c);
+                    false
+                }
+                (c, Some(q)) if c == q => {
+                    quote = None;
+                    false
+                }
+                (c, q) => c == '>' && q.is_none(),
+            })
+            .map_or(rest.len(), |(i, _)| i + 1);
+        append_tag(&mut job, &rest[..end], &font_id, color);
+        rest = &rest[end..];
+    }
+    job
+}
+
+fn append_tag(job: &mut LayoutJob, tag: &str, font_id: &FontId, color: Color32) {
+    let mut named = false;
+    let mut i = 0;
+    while i < tag.len() {
+        let c = tag[i..].chars().next().unwrap_or_default();
+        let (end, color) = match c {
+            '"' | '\'' => {
+                let end = tag[i + 1..].find(c).map_or(tag.len(), |j| i + 1 + j + 1);
+                (end, VALUE_COLOR)
+            }
+            '<' | '>' | '/' | '?' | '!' => (i + 1, TAG_COLOR),
+            c if c.is_whitespace() || c == '=' => (i + c.len_utf8(), color),
+            _ => {
+                let end = tag[i..]
+                    .find(|c: char| c.is_whitespace() || "=>/\"'".contains(c))
+                    .map_or(tag.len(), |j| i + j);
+                // the first name is the element, the rest are attributes
+                let color = if named { ATTRIBUTE_COLOR } else { TAG_COLOR };
+                named = true;
+                (end.max(i + c.len_utf8()), color)
+            }
+        };
+        job.append(
+            &tag[i..end],
+            0.0,
+            TextFormat::simple(font_id.clone(), color),
+        );
+        i = end;
+    }
+}
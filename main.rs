@@ -1,83 +1,2614 @@
-use colorful::{Color, Colorful};
+use snooze_core::{
+    assertions, auth, body, codegen, collection, curl, environment, executor, graphql, history,
+    json_filter, json_format, oauth, response, runner, script, settings, HttpMethod,
+};
+use assertions::{Assertion, AssertionKind, AssertionResult};
+use auth::{ApiKeyLocation, Auth, AuthKind};
+use body::{BinaryFile, BodyMode, FormPart, PartKind, RequestBody, UploadProgress};
+use collection::{CollectionNode, EditorTab, SavedRequest, Workspace};
+use environment::{Environment, Environments};
+use oauth::{ClientAuthentication, GrantType, OAuth2Config, TokenCache};
+use history::{History, HistoryEntry, HistoryFilter, HistoryWriter, StatusFilter};
+use executor::{Executor, ResolvedRequest};
+use graphql::{GraphqlBody, Schema};
+use runner::{CollectionRun, RunOptions, StepResult};
+use response::{HttpResponse, RequestError, RequestResult};
+use script::{ConsoleLine, Scripts};
+use json_format::JsonError;
+use settings::{Settings, Timeouts};
+use tabs::RequestTab;
+use viewer::{ViewCache, Viewer};
 use egui::{Color32, RichText};
-use egui_code_editor::{CodeEditor, ColorTheme, Syntax, Token, TokenType};
+use egui_code_editor::{CodeEditor, ColorTheme};
+use egui_json_tree::pointer::{JsonPointer, JsonPointerSegment};
+use egui_json_tree::render::{DefaultRender, RenderContext};
 use egui_json_tree::{DefaultExpand, JsonTree};
 use eframe::egui;
 use log::{error, info};
+mod cli;
+mod graphql_syntax;
+mod highlight;
 mod json_syntax;
-use reqwest;
-use serde_json::Value;
+mod rhai_syntax;
+mod tabs;
+mod viewer;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
-use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::time::Duration;
 use url::Url;
 
-#[derive(Debug, PartialEq, Clone)]
-enum HttpMethod {
-    GET,
-    POST,
-    PUT,
-    DELETE,
-    PATCH,
-    HEAD,
-    OPTIONS,
+/// the color a method is drawn in, in the tab strip, history and runner
+fn method_color(method: &HttpMethod) -> Color32 {
+    match method {
+        HttpMethod::GET => Color32::from_rgb(97, 175, 255),
+        HttpMethod::POST => Color32::from_rgb(152, 230, 121),
+        HttpMethod::PUT => Color32::from_rgb(198, 120, 255),
+        HttpMethod::DELETE => Color32::from_rgb(255, 108, 117),
+        HttpMethod::PATCH => Color32::from_rgb(255, 192, 123),
+        HttpMethod::HEAD => Color32::from_rgb(86, 182, 230),
+        HttpMethod::OPTIONS => Color32::from_rgb(152, 195, 121),
+    }
+}
+
+#[derive(PartialEq)]
+enum ResponseTab {
+    Body,
+    Headers,
+    Console,
+    Tests,
+}
+
+/// what a running request is for
+enum PendingKind {
+    /// sent from the editor; recorded in history and shown in its tab
+    Send,
+    /// a graphql introspection query whose result becomes the tab's schema
+    Introspection,
+}
+
+/// a request handed to the executor that hasn't reported back yet
+struct PendingRequest {
+    abort: tokio::task::AbortHandle,
+    kind: PendingKind,
+    /// id of the tab that sent it
+    tab: u64,
+    progress: Arc<UploadProgress>,
+}
+
+struct SnoozeApp {
+    tabs: Vec<RequestTab>,
+    active_tab: usize,
+    next_tab_id: u64,
+    executor: Executor,
+    /// every request still running, by id
+    pending: HashMap<u64, PendingRequest>,
+    next_request_id: u64,
+    tx: Option<Sender<RequestResult>>,
+    rx: Option<Receiver<RequestResult>>,
+    divider: f32,
+    workspace: Option<Workspace>,
+    save_name: String,
+    save_folder: PathBuf,
+    new_folder_name: String,
+    collection_status: String,
+    environments: Environments,
+    show_environments: bool,
+    editing_environment: usize,
+    new_environment_name: String,
+    environment_status: String,
+    history: History,
+    /// `None` without a workspace to save into
+    history_writer: Option<HistoryWriter>,
+    history_filter: HistoryFilter,
+    show_history: bool,
+    history_status: String,
+    show_curl_import: bool,
+    show_schema: bool,
+    schema_search: String,
+    curl_input: String,
+    curl_messages: Vec<String>,
+    settings: Settings,
+    show_settings: bool,
+    settings_status: String,
+    show_runner: bool,
+    run_folder: PathBuf,
+    run_options: RunOptions,
+    run: Option<CollectionRun>,
+    run_status: String,
+}
+
+impl Default for SnoozeApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SnoozeApp {
+    fn new() -> Self {
+        let (tx, rx) = channel();
+        let (workspace, collection_status) =
+            match Workspace::open(collection::default_workspace_dir()) {
+                Ok(workspace) => (Some(workspace), String::new()),
+                Err(e) => {
+                    error!("failed to open workspace: {}", e);
+                    (None, format!("failed to open workspace: {}", e))
+                }
+            };
+        let save_folder = workspace
+            .as_ref()
+            .map(|w| w.collections_dir())
+            .unwrap_or_default();
+        let run_folder = save_folder.clone();
+        let (environments, environment_status) = match workspace.as_ref() {
+            Some(w) => match Environments::load(&w.environments_path()) {
+                Ok(environments) => (environments, String::new()),
+                Err(e) => {
+                    error!("failed to load environments: {}", e);
+                    (
+                        Environments::default(),
+                        format!("failed to load environments: {}", e),
+                    )
+                }
+            },
+            None => (Environments::default(), String::new()),
+        };
+        let (history, history_status) = match workspace.as_ref() {
+            Some(w) => match History::load(&w.history_path()) {
+                Ok(history) => (history, String::new()),
+                Err(e) => {
+                    error!("failed to load history: {}", e);
+                    (History::default(), format!("failed to load history: {}", e))
+                }
+            },
+            None => (History::default(), String::new()),
+        };
+        let (history_writer, history_status) = match workspace.as_ref() {
+            Some(w) => match HistoryWriter::new(w.history_path()) {
+                Ok(writer) => (Some(writer), history_status),
+                Err(e) => {
+                    error!("failed to start the history writer: {}", e);
+                    (None, format!("history won't be saved: {}", e))
+                }
+            },
+            None => (None, history_status),
+        };
+        let (settings, settings_status) = match workspace.as_ref() {
+            Some(w) => match Settings::load(&w.settings_path()) {
+                Ok(settings) => (settings, String::new()),
+                Err(e) => {
+                    error!("failed to load settings: {}", e);
+                    (
+                        Settings::default(),
+                        format!("failed to load settings: {}", e),
+                    )
+                }
+            },
+            None => (Settings::default(), String::new()),
+        };
+        Self {
+            tabs: vec![RequestTab::new(0)],
+            active_tab: 0,
+            next_tab_id: 1,
+            executor: Executor::new().expect("failed to start the request runtime"),
+            pending: HashMap::new(),
+            next_request_id: 0,
+            tx: Some(tx),
+            rx: Some(rx),
+            divider: 250.0,
+            workspace,
+            save_name: "".to_owned(),
+            save_folder,
+            new_folder_name: "".to_owned(),
+            collection_status,
+            environments,
+            show_environments: false,
+            editing_environment: 0,
+            new_environment_name: "".to_owned(),
+            environment_status,
+            history,
+            history_writer,
+            history_filter: HistoryFilter::default(),
+            show_history: false,
+            history_status,
+            show_curl_import: false,
+            show_schema: false,
+            schema_search: String::new(),
+            curl_input: "".to_owned(),
+            curl_messages: Vec::new(),
+            settings,
+            show_settings: false,
+            settings_status,
+            show_runner: false,
+            run_folder,
+            run_options: RunOptions::default(),
+            run: None,
+            run_status: String::new(),
+        }
+    }
+
+    fn tab(&self) -> &RequestTab {
+        &self.tabs[self.active_tab]
+    }
+
+    fn tab_mut(&mut self) -> &mut RequestTab {
+        &mut self.tabs[self.active_tab]
+    }
+
+    fn select_tab(&mut self, index: usize) {
+        self.active_tab = index;
+        if let Some(path) = self.tab().path.clone() {
+            self.save_name = collection::request_name(&path);
+            if let Some(parent) = path.parent() {
+                self.save_folder = parent.to_path_buf();
+            }
+        }
+    }
+
+    fn open_tab(&mut self, make: impl FnOnce(u64) -> RequestTab) {
+        self.insert_tab(self.tabs.len(), make);
+    }
+
+    /// adds a tab at `index` built with a fresh id, and switches to it
+    fn insert_tab(&mut self, index: usize, make: impl FnOnce(u64) -> RequestTab) {
+        let id = self.next_tab_id;
+        self.next_tab_id += 1;
+        self.tabs.insert(index, make(id));
+        self.select_tab(index);
+    }
+
+    /// closes the tab at `index`, aborting whatever it was waiting on; the
+    /// last tab is replaced by a blank one
+    fn close_tab(&mut self, index: usize) {
+        let tab = self.tabs.remove(index);
+        info!("closing tab {}", tab.title());
+        for id in tab.in_flight.into_iter().chain(tab.introspecting) {
+            if let Some(pending) = self.pending.remove(&id) {
+                pending.abort.abort();
+            }
+        }
+        if self.tabs.is_empty() {
+            self.open_tab(RequestTab::new);
+        } else if self.active_tab > index || self.active_tab == self.tabs.len() {
+            self.select_tab(self.active_tab - 1);
+        } else {
+            self.select_tab(self.active_tab);
+        }
+    }
+
+    fn move_tab(&mut self, from: usize, to: usize) {
+        if from == to || to >= self.tabs.len() {
+            return;
+        }
+        let active_id = self.tab().id;
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        self.active_tab = self
+            .tabs
+            .iter()
+            .position(|t| t.id == active_id)
+            .unwrap_or_default();
+    }
+
+    /// opens `path`, switching to its tab if it's already open and reusing the
+    /// current tab if it's still blank
+    fn load_request(&mut self, path: PathBuf) {
+        if let Some(index) = self
+            .tabs
+            .iter()
+            .position(|t| t.path.as_ref() == Some(&path))
+        {
+            self.select_tab(index);
+            return;
+        }
+        match Workspace::load_request(&path) {
+            Ok(saved) => {
+                info!("loaded request from {}", path.display());
+                if self.tab().is_blank() {
+                    let id = self.tab().id;
+                    *self.tab_mut() = RequestTab::from_saved(id, saved, Some(path));
+                    self.select_tab(self.active_tab);
+                } else {
+                    self.open_tab(|id| RequestTab::from_saved(id, saved, Some(path)));
+                }
+                self.collection_status.clear();
+            }
+            Err(e) => {
+                error!("failed to load {}: {}", path.display(), e);
+                self.collection_status = format!("failed to load {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn save_request_to(&mut self, path: PathBuf) {
+        match Workspace::save_request(&path, &self.tab().request) {
+            Ok(()) => {
+                info!("saved request to {}", path.display());
+                self.save_name = collection::request_name(&path);
+                self.tab_mut().mark_saved(&path);
+                self.collection_status.clear();
+                self.refresh_workspace();
+            }
+            Err(e) => {
+                error!("failed to save {}: {}", path.display(), e);
+                self.collection_status = format!("failed to save {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn refresh_workspace(&mut self) {
+        if let Some(workspace) = self.workspace.as_mut() {
+            if let Err(e) = workspace.refresh() {
+                error!("failed to read workspace: {}", e);
+                self.collection_status = format!("failed to read workspace: {}", e);
+            }
+        }
+    }
+
+    fn collections_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("collections");
+        ui.separator();
+
+        let Some(workspace) = self.workspace.as_ref() else {
+            ui.colored_label(Color32::from_rgb(255, 108, 117), &self.collection_status);
+            return;
+        };
+        let folders = workspace.folders();
+        let tree = workspace.tree.clone();
+        ui.label(
+            RichText::new(workspace.root.display().to_string())
+                .monospace()
+                .small(),
+        );
+
+        ui.horizontal(|ui| {
+            let can_save = self.tab().path.is_some();
+            if ui
+                .add_enabled(can_save, egui::Button::new("save"))
+                .clicked()
+            {
+                if let Some(path) = self.tab().path.clone() {
+                    self.save_request_to(path);
+                }
+            }
+            if ui.button("reload").clicked() {
+                info!("reloading workspace");
+                self.refresh_workspace();
+            }
+            if ui.selectable_label(self.show_runner, "runner").clicked() {
+                self.show_runner = !self.show_runner;
+            }
+        });
+
+        let folder_label = folders
+            .iter()
+            .find(|(_, path)| *path == self.save_folder)
+            .map(|(label, _)| label.clone())
+            .unwrap_or_else(|| "/".to_owned());
+        egui::ComboBox::from_id_salt("save_folder")
+            .selected_text(folder_label)
+            .width(ui.available_width())
+            .show_ui(ui, |ui| {
+                for (label, path) in folders.iter() {
+                    ui.selectable_value(&mut self.save_folder, path.clone(), label);
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.save_name)
+                    .desired_width(ui.available_width() - 70.0)
+                    .hint_text("request name"),
+            );
+            if ui.button("save as").clicked() && !self.save_name.trim().is_empty() {
+                let path = Workspace::request_path(&self.save_folder, &self.save_name);
+                self.save_request_to(path);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_folder_name)
+                    .desired_width(ui.available_width() - 70.0)
+                    .hint_text("folder name"),
+            );
+            if ui.button("+ folder").clicked() && !self.new_folder_name.trim().is_empty() {
+                match Workspace::create_folder(&self.save_folder, &self.new_folder_name) {
+                    Ok(path) => {
+                        info!("created folder {}", path.display());
+                        self.save_folder = path;
+                        self.new_folder_name.clear();
+                        self.refresh_workspace();
+                    }
+                    Err(e) => {
+                        error!("failed to create folder: {}", e);
+                        self.collection_status = format!("failed to create folder: {}", e);
+                    }
+                }
+            }
+        });
+
+        if !self.collection_status.is_empty() {
+            ui.colored_label(Color32::from_rgb(255, 108, 117), &self.collection_status);
+        }
+        ui.separator();
+
+        let mut to_load = None;
+        let mut to_delete = None;
+        let mut to_run = None;
+        egui::ScrollArea::vertical()
+            .id_salt("collections_scroll")
+            .show(ui, |ui| {
+                self.collection_tree(ui, &tree, &mut to_load, &mut to_delete, &mut to_run);
+            });
+        if let Some(path) = to_run {
+            self.run_folder = path;
+            self.show_runner = true;
+        }
+        if let Some(path) = to_load {
+            self.load_request(path);
+        }
+        if let Some(path) = to_delete {
+            match Workspace::delete(&path) {
+                Ok(()) => {
+                    info!("deleted {}", path.display());
+                    for tab in self.tabs.iter_mut() {
+                        if tab.path.as_ref().is_some_and(|p| p.starts_with(&path)) {
+                            tab.detach();
+                        }
+                    }
+                    if self.save_folder.starts_with(&path) {
+                        if let Some(workspace) = self.workspace.as_ref() {
+                            self.save_folder = workspace.collections_dir();
+                        }
+                    }
+                    self.refresh_workspace();
+                }
+                Err(e) => {
+                    error!("failed to delete {}: {}", path.display(), e);
+                    self.collection_status = format!("failed to delete {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    /// the editor fields with the active environment's variables substituted;
+    /// unknown placeholders are left as-is and reported in `unresolved`
+    fn resolve_request(&self, unresolved: &mut Vec<String>) -> ResolvedRequest {
+        let vars = self.environments.active_variables();
+        ResolvedRequest::resolve(&self.tab().request, &vars, unresolved)
+    }
+
+    /// code for the current request in `language`, with variables resolved
+    fn snippet(&self, language: codegen::Language) -> String {
+        let request = self.resolve_request(&mut Vec::new());
+        let method = format!("{:?}", request.method);
+        let mut headers = request.headers_with_auth();
+        let has_content_type = |headers: &[(String, String)]| {
+            headers
+                .iter()
+                .any(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        };
+        let mut file = None;
+        let (body, multipart) = match &request.body {
+            _ if !request.method.has_body() => (String::new(), &[][..]),
+            RequestBody::Raw(text) => (text.clone(), &[][..]),
+            RequestBody::Graphql(_) => {
+                if !has_content_type(&headers) {
+                    headers.push(("Content-Type".to_owned(), "application/json".to_owned()));
+                }
+                (request.body.summary(), &[][..])
+            }
+            RequestBody::File(binary) => {
+                if !has_content_type(&headers) {
+                    headers.push(("Content-Type".to_owned(), binary.content_type()));
+                }
+                file = Some(binary.path.as_str());
+                (String::new(), &[][..])
+            }
+            RequestBody::Form(pairs) => {
+                if !has_content_type(&headers) {
+                    headers.push((
+                        "Content-Type".to_owned(),
+                        "application/x-www-form-urlencoded".to_owned(),
+                    ));
+                }
+                (body::form_urlencode(pairs), &[][..])
+            }
+            RequestBody::Multipart(parts) => {
+                // the target library picks the boundary and sets the header
+                headers.retain(|(k, _)| !k.eq_ignore_ascii_case("content-type"));
+                (String::new(), &parts[..])
+            }
+        };
+        codegen::generate(
+            language,
+            &codegen::SnippetRequest {
+                method: &method,
+                url: &request.url,
+                queries: &request.queries_with_auth(),
+                headers: &headers,
+                body: &body,
+                multipart,
+                file,
+            },
+        )
+    }
+
+    /// resolves the current tab against the active environment and hands it
+    /// to the executor
+    fn send_request(&mut self) {
+        self.tab_mut().notice.clear();
+        // a tab shows one response, so whatever it's still waiting for is dropped
+        if let Some(previous) = self.tab_mut().in_flight.take() {
+            if let Some(pending) = self.pending.remove(&previous) {
+                info!("aborting request {} for a newer one", previous);
+                pending.abort.abort();
+            }
+        }
+        // resolve {{variables}} from the active environment before anything is sent
+        let mut unresolved = Vec::new();
+        let request = self.resolve_request(&mut unresolved);
+        // a pre-request script may still set them, the worker checks again after it
+        if !unresolved.is_empty() && !request.scripts.has_pre_request() {
+            let names: Vec<String> = unresolved
+                .iter()
+                .map(|n| format!("{{{{{}}}}}", n))
+                .collect();
+            error!("unresolved variables: {}", names.join(", "));
+            let active = self.environments.active.clone();
+            let tab = self.tab_mut();
+            tab.response_text = format!(
+                "not sent, unresolved variables: {}\nactive environment: {}",
+                names.join(", "),
+                active.as_deref().unwrap_or("none")
+            );
+            tab.clear_response();
+        } else if let Some(e) = self.json_body_error(&request) {
+            error!("not sending a json body that doesn't parse: {}", e);
+            let tab = self.tab_mut();
+            tab.response_text = format!(
+                "not sent, the content type is json but the body doesn't parse\n{}\n\nthis check can be turned off in settings",
+                e
+            );
+            tab.clear_response();
+        } else if Url::parse(&request.url).is_err() {
+            error!("invalid url: {}", request.url);
+            let tab = self.tab_mut();
+            tab.response_text = format!("invalid url: {}", request.url);
+            tab.clear_response();
+        } else {
+            info!("{:?} {}", request.method, request.url);
+            let timeouts = self.tab().request.timeouts.or(&self.settings.timeouts);
+            self.next_request_id += 1;
+            let id = self.next_request_id;
+            let tab_id = self.tab().id;
+            if let Some(tx) = self.tx.clone() {
+                let progress = Arc::new(UploadProgress::default());
+                let abort = self
+                    .executor
+                    .spawn(id, request, timeouts, progress.clone(), tx);
+                self.pending.insert(
+                    id,
+                    PendingRequest {
+                        abort,
+                        kind: PendingKind::Send,
+                        tab: tab_id,
+                        progress,
+                    },
+                );
+                self.tab_mut().in_flight = Some(id);
+            }
+            let tab = self.tab_mut();
+            tab.response_text = format!("{:?} {}", tab.request.method, tab.request.url);
+            tab.clear_response();
+        }
+    }
+
+    /// why a raw body labelled as json shouldn't be sent, when that's checked
+    fn json_body_error(&self, request: &ResolvedRequest) -> Option<JsonError> {
+        let RequestBody::Raw(text) = &request.body else {
+            return None;
+        };
+        if !self.settings.check_json_body
+            || text.trim().is_empty()
+            || !has_json_content_type(&request.headers)
+        {
+            return None;
+        }
+        json_format::validate(text).err()
+    }
+
+    /// sends the introspection query to the current tab's url, with its headers
+    /// and auth, so the schema can be browsed and used for completion
+    fn fetch_schema(&mut self) {
+        let mut unresolved = Vec::new();
+        let mut request = self.resolve_request(&mut unresolved);
+        if Url::parse(&request.url).is_err() {
+            self.tab_mut().schema_status = format!("invalid url: {}", request.url);
+            return;
+        }
+        info!("fetching graphql schema from {}", request.url);
+        request.method = HttpMethod::POST;
+        request.scripts = Scripts::default();
+        request.body = RequestBody::Graphql(GraphqlBody {
+            query: graphql::INTROSPECTION_QUERY.to_owned(),
+            ..Default::default()
+        });
+        let timeouts = self.tab().request.timeouts.or(&self.settings.timeouts);
+        self.next_request_id += 1;
+        let id = self.next_request_id;
+        let tab_id = self.tab().id;
+        if let Some(tx) = self.tx.clone() {
+            let progress = Arc::new(UploadProgress::default());
+            let abort = self
+                .executor
+                .spawn(id, request, timeouts, progress.clone(), tx);
+            self.pending.insert(
+                id,
+                PendingRequest {
+                    abort,
+                    kind: PendingKind::Introspection,
+                    tab: tab_id,
+                    progress,
+                },
+            );
+            let tab = self.tab_mut();
+            tab.introspecting = Some(id);
+            tab.schema_status = "fetching schema…".to_owned();
+        }
+    }
+
+    /// aborts the request the current tab is waiting on and forgets about its result
+    fn cancel_request(&mut self) {
+        let Some(id) = self.tab_mut().in_flight.take() else {
+            return;
+        };
+        if let Some(pending) = self.pending.remove(&id) {
+            info!("cancelling request {}", id);
+            pending.abort.abort();
+            let tab = self.tab_mut();
+            tab.response_text = "request cancelled".to_owned();
+            tab.clear_response();
+        }
+    }
+
+    fn save_settings(&mut self) {
+        let Some(workspace) = self.workspace.as_ref() else {
+            return;
+        };
+        match self.settings.save(&workspace.settings_path()) {
+            Ok(()) => {
+                info!("saved settings");
+                self.settings_status.clear();
+            }
+            Err(e) => {
+                error!("failed to save settings: {}", e);
+                self.settings_status = format!("failed to save settings: {}", e);
+            }
+        }
+    }
+
+    /// loads the run folder's requests and the data file and starts sending
+    /// them with the active environment
+    fn start_run(&mut self) {
+        let steps = match runner::load_steps(&self.run_folder) {
+            Ok(steps) if steps.is_empty() => {
+                self.run_status = "no requests in this folder".to_owned();
+                return;
+            }
+            Ok(steps) => steps,
+            Err(e) => {
+                error!("{}", e);
+                self.run_status = e;
+                return;
+            }
+        };
+        let data_file = self.run_options.data_file.trim();
+        let data = if data_file.is_empty() {
+            Vec::new()
+        } else {
+            match runner::load_data(std::path::Path::new(data_file)) {
+                Ok(data) => data,
+                Err(e) => {
+                    error!("{}", e);
+                    self.run_status = e;
+                    return;
+                }
+            }
+        };
+        info!(
+            "running {} requests from {} for {} iteration(s)",
+            steps.len(),
+            self.run_folder.display(),
+            data.len().max(1)
+        );
+        self.run_status.clear();
+        let (tx, rx) = channel();
+        let count = steps.len();
+        let iterations = data.len().max(1);
+        let abort = self.executor.run(
+            steps,
+            self.environments.active_variables(),
+            data,
+            self.run_options.clone(),
+            &self.settings.timeouts,
+            tx,
+        );
+        self.run = Some(CollectionRun::new(
+            self.run_folder.clone(),
+            count,
+            iterations,
+            abort,
+            rx,
+        ));
+    }
+
+    /// picks a folder and run options, runs it and shows how each step went
+    fn runner_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_runner;
+        let folders = self
+            .workspace
+            .as_ref()
+            .map(|w| w.folders())
+            .unwrap_or_default();
+        let mut start = false;
+        egui::Window::new("collection runner")
+            .open(&mut open)
+            .default_width(560.0)
+            .default_height(480.0)
+            .show(ctx, |ui| {
+                let running = self.run.as_ref().is_some_and(|run| !run.finished);
+                ui.add_enabled_ui(!running, |ui| {
+                    egui::Grid::new("runner_options")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("folder:");
+                            let folder_label = folders
+                                .iter()
+                                .find(|(_, path)| *path == self.run_folder)
+                                .map(|(label, _)| label.clone())
+                                .unwrap_or_else(|| "/".to_owned());
+                            egui::ComboBox::from_id_salt("run_folder")
+                                .selected_text(folder_label)
+                                .width(260.0)
+                                .show_ui(ui, |ui| {
+                                    for (label, path) in folders.iter() {
+                                        ui.selectable_value(
+                                            &mut self.run_folder,
+                                            path.clone(),
+                                            label,
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+                            ui.label("concurrency:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.run_options.concurrency)
+                                    .range(1..=16),
+                            )
+                            .on_hover_text("requests sent at once");
+                            ui.end_row();
+                            ui.label("on failure:");
+                            ui.checkbox(&mut self.run_options.stop_on_failure, "stop the run");
+                            ui.end_row();
+                            ui.label("data file:");
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.run_options.data_file)
+                                        .desired_width(260.0)
+                                        .hint_text("csv or json, one iteration per row"),
+                                );
+                                if ui.button("browse…").clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter("data", &["csv", "json"])
+                                        .pick_file()
+                                    {
+                                        self.run_options.data_file = path.display().to_string();
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        });
+                });
+                ui.horizontal(|ui| {
+                    if running {
+                        if ui.button("stop").clicked() {
+                            if let Some(run) = self.run.as_mut() {
+                                run.cancel();
+                            }
+                        }
+                        ui.spinner();
+                    } else if ui.button("run").clicked() {
+                        start = true;
+                    }
+                    if !self.run_status.is_empty() {
+                        ui.colored_label(Color32::from_rgb(255, 108, 117), &self.run_status);
+                    }
+                });
+                if let Some(run) = &self.run {
+                    ui.separator();
+                    run_summary(ui, run);
+                }
+            });
+        self.show_runner = open;
+        if start {
+            self.start_run();
+        }
+    }
+
+    /// the active tab's introspected schema, root types first
+    fn schema_window(&mut self, ctx: &egui::Context) {
+        let Some(schema) = self.tabs[self.active_tab].schema.as_ref() else {
+            return;
+        };
+        let mut open = self.show_schema;
+        let search = &mut self.schema_search;
+        egui::Window::new("graphql schema")
+            .open(&mut open)
+            .default_width(440.0)
+            .default_height(520.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("search:");
+                    ui.text_edit_singleline(search);
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .id_salt("schema_scroll")
+                    .show(ui, |ui| schema_browser(ui, schema, search));
+            });
+        self.show_schema = open;
+    }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        egui::Window::new("settings")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new("global timeouts").strong());
+                if timeouts_editor(
+                    ui,
+                    "global_timeouts",
+                    &mut self.settings.timeouts,
+                    "no limit",
+                ) {
+                    self.save_settings();
+                }
+                ui.separator();
+                ui.label(RichText::new("json bodies").strong());
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("format indent:");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.settings.json_indent).range(1..=8))
+                        .changed();
+                });
+                changed |= ui
+                    .checkbox(
+                        &mut self.settings.check_json_body,
+                        "don't send a json body that doesn't parse",
+                    )
+                    .changed();
+                if changed {
+                    self.save_settings();
+                }
+                if !self.settings_status.is_empty() {
+                    ui.colored_label(Color32::from_rgb(255, 108, 117), &self.settings_status);
+                }
+            });
+        self.show_settings = open;
+    }
+
+    /// queues the history to be written; `poll_history_writer` reports how it went
+    fn save_history(&mut self) {
+        if let Some(writer) = self.history_writer.as_ref() {
+            writer.save(&self.history);
+        }
+    }
+
+    fn poll_history_writer(&mut self) {
+        match self.history_writer.as_ref().and_then(HistoryWriter::poll) {
+            Some(Ok(())) => self.history_status.clear(),
+            Some(Err(e)) => {
+                error!("failed to save history: {}", e);
+                self.history_status = format!("failed to save history: {}", e);
+            }
+            None => {}
+        }
+    }
+
+    fn restore_history_entry(&mut self, entry: &HistoryEntry) {
+        info!("restoring {:?} {} from history", entry.method, entry.url);
+        let (url, queries) = history::split_queries(&entry.url);
+        let tab = self.tab_mut();
+        tab.request.method = entry.method.clone();
+        tab.request.url = url;
+        tab.request.queries = queries;
+        tab.request.headers = entry.headers.clone();
+        if tab.request.queries.is_empty() {
+            tab.request.queries.push(("".to_owned(), "".to_owned()));
+        }
+        if tab.request.headers.is_empty() {
+            tab.request.headers.push(("".to_owned(), "".to_owned()));
+        }
+        entry.restore_body(&mut tab.request);
+        // history doesn't record these, and the tab's own have nothing to do
+        // with the restored request, so they'd be sent along with it
+        let blank = SavedRequest::default();
+        let mut cleared = Vec::new();
+        if tab.request.auth != blank.auth {
+            cleared.push("auth");
+        }
+        if tab.request.scripts != blank.scripts {
+            cleared.push("scripts");
+        }
+        if tab.request.assertions != blank.assertions {
+            cleared.push("tests");
+        }
+        if tab.request.timeouts != blank.timeouts {
+            cleared.push("timeouts");
+        }
+        tab.request.auth = blank.auth;
+        tab.request.scripts = blank.scripts;
+        tab.request.assertions = blank.assertions;
+        tab.request.timeouts = blank.timeouts;
+        tab.notice = match cleared.as_slice() {
+            [] => String::new(),
+            cleared => format!(
+                "history doesn't keep {}, so this tab's were cleared",
+                cleared.join(", ")
+            ),
+        };
+        tab.response_text = entry.response_body.clone();
+        tab.parsed_json = serde_json::from_str(&tab.response_text).ok();
+        // history keeps the status and body, not the full response
+        tab.last_response = entry.status.map(|status| HttpResponse {
+            status,
+            reason: reqwest::StatusCode::from_u16(status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or_default()
+                .to_owned(),
+            body: entry.response_body.clone().into_bytes(),
+            ..Default::default()
+        });
+        tab.last_error = None;
+        tab.view_cache = ViewCache::default();
+        tab.last_duration = Some(Duration::from_millis(entry.duration_ms));
+        tab.assertion_results = entry.assertions.clone();
+        // a restored entry is no longer the saved request it may have come from
+        tab.detach();
+    }
+
+    fn history_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("history");
+        ui.separator();
+
+        ui.add(
+            egui::TextEdit::singleline(&mut self.history_filter.search)
+                .desired_width(ui.available_width())
+                .hint_text("search url, body or response"),
+        );
+        ui.horizontal(|ui| {
+            let method_label = self
+                .history_filter
+                .method
+                .as_ref()
+                .map(|m| format!("{:?}", m))
+                .unwrap_or_else(|| "any method".to_owned());
+            egui::ComboBox::from_id_salt("history_method")
+                .selected_text(method_label)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.history_filter.method, None, "any method");
+                    for method in HttpMethod::ALL.iter() {
+                        ui.selectable_value(
+                            &mut self.history_filter.method,
+                            Some(method.clone()),
+                            format!("{:?}", method),
+                        );
+                    }
+                });
+            egui::ComboBox::from_id_salt("history_status")
+                .selected_text(self.history_filter.status.label())
+                .show_ui(ui, |ui| {
+                    for status in StatusFilter::ALL {
+                        ui.selectable_value(
+                            &mut self.history_filter.status,
+                            status,
+                            status.label(),
+                        );
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.history_filter.host)
+                    .desired_width(ui.available_width() - 60.0)
+                    .hint_text("host"),
+            );
+            if ui.button("clear").clicked() {
+                info!("clearing history");
+                self.history.entries.clear();
+                self.save_history();
+            }
+        });
+        if !self.history_status.is_empty() {
+            ui.colored_label(Color32::from_rgb(255, 108, 117), &self.history_status);
+        }
+        ui.separator();
+
+        let mut to_restore = None;
+        let mut to_resend = None;
+        egui::ScrollArea::vertical()
+            .id_salt("history_scroll")
+            .show(ui, |ui| {
+                for (i, entry) in self.history.entries.iter().enumerate().rev() {
+                    if !self.history_filter.matches(entry) {
+                        continue;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new(format!("{:?}", entry.method))
+                                .monospace()
+                                .color(method_color(&entry.method)),
+                        );
+                        let status = entry
+                            .status
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| "err".to_owned());
+                        ui.label(RichText::new(status).monospace());
+                        ui.label(format!("{} ms", entry.duration_ms));
+                        if !entry.assertions.is_empty() {
+                            let passed = assertions::passed(&entry.assertions);
+                            let total = entry.assertions.len();
+                            ui.label(
+                                RichText::new(format!("tests {}/{}", passed, total))
+                                    .monospace()
+                                    .color(tests_color(passed, total)),
+                            );
+                        }
+                        if ui.small_button("restore").clicked() {
+                            to_restore = Some(i);
+                        }
+                        if ui.small_button("resend").clicked() {
+                            to_resend = Some(i);
+                        }
+                    });
+                    ui.add(
+                        egui::Label::new(RichText::new(&entry.url).monospace().small()).truncate(),
+                    );
+                    ui.separator();
+                }
+            });
+        if let Some(i) = to_restore.or(to_resend) {
+            let entry = self.history.entries[i].clone();
+            self.restore_history_entry(&entry);
+            if to_resend.is_some() {
+                self.send_request();
+            }
+        }
+    }
+
+    fn curl_import_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_curl_import;
+        egui::Window::new("paste curl")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.curl_input)
+                        .hint_text("curl https://example.com -H 'accept: application/json'")
+                        .desired_rows(8)
+                        .desired_width(f32::INFINITY)
+                        .font(egui::TextStyle::Monospace),
+                );
+                if ui.button("import").clicked() {
+                    match curl::parse_curl(&self.curl_input) {
+                        Ok(import) => {
+                            info!("imported curl command: {:?} {}", import.method, import.url);
+                            let tab = &mut self.tabs[self.active_tab];
+                            tab.request.method = import.method;
+                            tab.request.url = import.url;
+                            tab.request.headers = import.headers;
+                            tab.request.queries = import.queries;
+                            tab.request.body = import.body;
+                            tab.request.body_mode = import.body_mode;
+                            if !import.multipart.is_empty() {
+                                tab.request.multipart = import.multipart;
+                            }
+                            if let Some(path) = import.file {
+                                tab.request.binary = BinaryFile {
+                                    path,
+                                    content_type: String::new(),
+                                };
+                            }
+                            if tab.request.headers.is_empty() {
+                                tab.request.headers.push(("".to_owned(), "".to_owned()));
+                            }
+                            if tab.request.queries.is_empty() {
+                                tab.request.queries.push(("".to_owned(), "".to_owned()));
+                            }
+                            tab.detach();
+                            self.curl_messages = import.warnings;
+                            if self.curl_messages.is_empty() {
+                                self.curl_input.clear();
+                                self.show_curl_import = false;
+                            }
+                        }
+                        Err(e) => {
+                            error!("failed to parse curl command: {}", e);
+                            self.curl_messages = vec![format!("could not import: {}", e)];
+                        }
+                    }
+                }
+                for message in self.curl_messages.iter() {
+                    ui.colored_label(Color32::from_rgb(255, 192, 123), message);
+                }
+            });
+        self.show_curl_import &= open;
+    }
+
+    fn save_environments(&mut self) {
+        let Some(workspace) = self.workspace.as_ref() else {
+            return;
+        };
+        match self.environments.save(&workspace.environments_path()) {
+            Ok(()) => {
+                info!("saved environments");
+                self.environment_status.clear();
+            }
+            Err(e) => {
+                error!("failed to save environments: {}", e);
+                self.environment_status = format!("failed to save environments: {}", e);
+            }
+        }
+    }
+
+    /// stores variables set by a request's scripts in the active environment
+    fn set_script_variables(&mut self, variables: &[(String, String)]) -> Result<(), String> {
+        if variables.is_empty() {
+            return Ok(());
+        }
+        let active = self.environments.active.clone();
+        let Some(environment) = self
+            .environments
+            .environments
+            .iter_mut()
+            .find(|env| Some(&env.name) == active.as_ref())
+        else {
+            let names: Vec<&str> = variables.iter().map(|(k, _)| k.as_str()).collect();
+            info!(
+                "no active environment for script variables {}",
+                names.join(", ")
+            );
+            return Err(format!(
+                "no active environment, so {} wasn't kept",
+                names.join(", ")
+            ));
+        };
+        for (name, value) in variables {
+            info!("script set variable {}", name);
+            match environment.variables.iter_mut().find(|(k, _)| k == name) {
+                Some(row) => row.1 = value.clone(),
+                None => environment.variables.push((name.clone(), value.clone())),
+            }
+        }
+        self.save_environments();
+        Ok(())
+    }
+
+    fn environment_selector(&mut self, ui: &mut egui::Ui) {
+        let selected = self.environments.active.clone();
+        let mut changed = false;
+        egui::ComboBox::from_id_salt("active_environment")
+            .selected_text(selected.as_deref().unwrap_or("no environment"))
+            .show_ui(ui, |ui| {
+                changed |= ui
+                    .selectable_value(&mut self.environments.active, None, "no environment")
+                    .changed();
+                for env in self.environments.environments.iter() {
+                    changed |= ui
+                        .selectable_value(
+                            &mut self.environments.active,
+                            Some(env.name.clone()),
+                            &env.name,
+                        )
+                        .changed();
+                }
+            });
+        if changed {
+            info!("active environment: {:?}", self.environments.active);
+            self.save_environments();
+        }
+        if ui.button("environments").clicked() {
+            self.show_environments = !self.show_environments;
+        }
+    }
+
+    fn environments_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_environments;
+        egui::Window::new("environments")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (i, env) in self.environments.environments.iter().enumerate() {
+                        if ui
+                            .selectable_label(self.editing_environment == i, &env.name)
+                            .clicked()
+                        {
+                            self.editing_environment = i;
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.new_environment_name)
+                            .hint_text("environment name"),
+                    );
+                    let name = self.new_environment_name.trim().to_owned();
+                    let exists = self
+                        .environments
+                        .environments
+                        .iter()
+                        .any(|e| e.name == name);
+                    if ui
+                        .add_enabled(!name.is_empty() && !exists, egui::Button::new("+ add"))
+                        .clicked()
+                    {
+                        info!("adding environment {}", name);
+                        self.environments.environments.push(Environment {
+                            name,
+                            variables: vec![("".to_owned(), "".to_owned())],
+                        });
+                        self.editing_environment = self.environments.environments.len() - 1;
+                        self.new_environment_name.clear();
+                    }
+                });
+                ui.separator();
+
+                let mut remove = false;
+                if let Some(env) = self
+                    .environments
+                    .environments
+                    .get_mut(self.editing_environment)
+                {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(&env.name).strong());
+                        if ui.button("delete environment").clicked() {
+                            remove = true;
+                        }
+                    });
+                    key_value_editor(
+                        ui,
+                        "environment_scroll",
+                        &mut env.variables,
+                        "=",
+                        "variable",
+                        None,
+                    );
+                }
+                if remove {
+                    let env = self
+                        .environments
+                        .environments
+                        .remove(self.editing_environment);
+                    info!("removed environment {}", env.name);
+                    if self.environments.active.as_ref() == Some(&env.name) {
+                        self.environments.active = None;
+                    }
+                    self.editing_environment = 0;
+                }
+
+                ui.separator();
+                if ui.button("save environments").clicked() {
+                    self.save_environments();
+                }
+                if !self.environment_status.is_empty() {
+                    ui.colored_label(Color32::from_rgb(255, 108, 117), &self.environment_status);
+                }
+            });
+        self.show_environments = open;
+    }
+
+    /// one entry per open tab: click to switch, drag to reorder, right-click
+    /// for more. unsaved tabs get a dot, tabs with a request running a spinner
+    fn tab_strip(&mut self, ui: &mut egui::Ui) {
+        let mut select = None;
+        let mut close = None;
+        let mut duplicate = None;
+        let mut moved = None;
+        egui::ScrollArea::horizontal()
+            .id_salt("tab_strip")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let count = self.tabs.len();
+                    for (i, tab) in self.tabs.iter().enumerate() {
+                        let method = format!("{:?}", tab.request.method);
+                        let mut title = tab.title();
+                        if tab.is_dirty() {
+                            title.push_str(" ●");
+                        }
+                        let mut job = egui::text::LayoutJob::default();
+                        let font_id = egui::TextStyle::Button.resolve(ui.style());
+                        job.append(
+                            &method,
+                            0.0,
+                            egui::TextFormat::simple(
+                                font_id.clone(),
+                                method_color(&tab.request.method),
+                            ),
+                        );
+                        job.append(
+                            &title,
+                            6.0,
+                            egui::TextFormat::simple(font_id, ui.visuals().text_color()),
+                        );
+                        let response = ui
+                            .selectable_label(i == self.active_tab, job)
+                            .interact(egui::Sense::drag());
+                        if response.clicked() {
+                            select = Some(i);
+                        }
+                        response.dnd_set_drag_payload(i);
+                        if let Some(from) = response.dnd_release_payload::<usize>() {
+                            moved = Some((*from, i));
+                        }
+                        response.context_menu(|ui| {
+                            if ui.button("duplicate").clicked() {
+                                duplicate = Some(i);
+                                ui.close_menu();
+                            }
+                            if ui
+                                .add_enabled(i > 0, egui::Button::new("move left"))
+                                .clicked()
+                            {
+                                moved = Some((i, i - 1));
+                                ui.close_menu();
+                            }
+                            if ui
+                                .add_enabled(i + 1 < count, egui::Button::new("move right"))
+                                .clicked()
+                            {
+                                moved = Some((i, i + 1));
+                                ui.close_menu();
+                            }
+                            if ui.button("close").clicked() {
+                                close = Some(i);
+                                ui.close_menu();
+                            }
+                        });
+                        if tab.in_flight.is_some() {
+                            ui.spinner();
+                        }
+                        if ui.small_button("×").on_hover_text("close tab").clicked() {
+                            close = Some(i);
+                        }
+                        ui.separator();
+                    }
+                    if ui.button("+").on_hover_text("new tab").clicked() {
+                        info!("opening a new tab");
+                        self.open_tab(RequestTab::new);
+                    }
+                });
+            });
+        if let Some(i) = select {
+            self.select_tab(i);
+        }
+        if let Some(i) = duplicate {
+            info!("duplicating tab {}", self.tabs[i].title());
+            // the copy isn't tied to the original's file
+            let request = self.tabs[i].request.clone();
+            self.insert_tab(i + 1, |id| RequestTab::from_saved(id, request, None));
+        }
+        if let Some((from, to)) = moved {
+            self.move_tab(from, to);
+        }
+        if let Some(i) = close {
+            self.close_tab(i);
+        }
+    }
+
+    fn collection_tree(
+        &self,
+        ui: &mut egui::Ui,
+        nodes: &[CollectionNode],
+        to_load: &mut Option<PathBuf>,
+        to_delete: &mut Option<PathBuf>,
+        to_run: &mut Option<PathBuf>,
+    ) {
+        for node in nodes {
+            match node {
+                CollectionNode::Folder {
+                    name,
+                    path,
+                    children,
+                } => {
+                    let header = egui::CollapsingHeader::new(name)
+                        .id_salt(path)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            self.collection_tree(ui, children, to_load, to_delete, to_run);
+                        });
+                    header.header_response.context_menu(|ui| {
+                        if ui.button("run folder…").clicked() {
+                            *to_run = Some(path.clone());
+                            ui.close_menu();
+                        }
+                        if ui.button("delete folder").clicked() {
+                            *to_delete = Some(path.clone());
+                            ui.close_menu();
+                        }
+                    });
+                }
+                CollectionNode::Request { name, path } => {
+                    let is_current = self.tab().path.as_ref() == Some(path);
+                    let response = ui.selectable_label(is_current, name);
+                    if response.clicked() {
+                        *to_load = Some(path.clone());
+                    }
+                    response.context_menu(|ui| {
+                        if ui.button("delete").clicked() {
+                            *to_delete = Some(path.clone());
+                            ui.close_menu();
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// editable key/value rows with a delete button per row; when `vars` is given,
+/// `{{name}}` placeholders are highlighted against it
+fn key_value_editor(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    rows: &mut Vec<(String, String)>,
+    separator: &str,
+    item: &str,
+    vars: Option<&HashMap<String, String>>,
+) {
+    let mut to_remove = None;
+    egui::ScrollArea::horizontal()
+        .id_salt(id_salt)
+        .show(ui, |ui| {
+            for (i, row) in rows.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.set_max_width(ui.available_width());
+                    let delete_btn_width = 24.0;
+                    let spacing = 8.0;
+                    let field_width =
+                        (ui.available_width() - delete_btn_width - spacing * 2.0) / 2.0;
+                    if ui
+                        .add(
+                            egui::Button::new("×")
+                                .fill(Color32::from_rgb(255, 88, 88))
+                                .min_size(egui::vec2(delete_btn_width, 18.0)),
+                        )
+                        .clicked()
+                    {
+                        info!("removing {} at index {}", item, i);
+                        to_remove = Some(i);
+                    }
+                    variable_text_edit(ui, &mut row.0, field_width, "key", vars);
+                    ui.label(separator);
+                    variable_text_edit(ui, &mut row.1, field_width, "value", vars);
+                });
+            }
+        });
+    if let Some(i) = to_remove {
+        rows.remove(i);
+    }
+    ui.add_space(4.0);
+    if ui.button(format!("+ add {}", item)).clicked() {
+        info!("adding a new {}", item);
+        rows.push(("".to_owned(), "".to_owned()));
+    }
+}
+
+/// rows of multipart fields: name, text or file, the value or path, and an
+/// optional content type
+fn multipart_editor(ui: &mut egui::Ui, parts: &mut Vec<FormPart>, vars: &HashMap<String, String>) {
+    let mut to_remove = None;
+    egui::ScrollArea::horizontal()
+        .id_salt("multipart_scroll")
+        .show(ui, |ui| {
+            for (i, part) in parts.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.set_max_width(ui.available_width());
+                    if ui
+                        .add(
+                            egui::Button::new("×")
+                                .fill(Color32::from_rgb(255, 88, 88))
+                                .min_size(egui::vec2(24.0, 18.0)),
+                        )
+                        .clicked()
+                    {
+                        info!("removing form part at index {}", i);
+                        to_remove = Some(i);
+                    }
+                    variable_text_edit(ui, &mut part.name, 120.0, "name", Some(vars));
+                    egui::ComboBox::from_id_salt(("part_kind", i))
+                        .width(56.0)
+                        .selected_text(match part.kind {
+                            PartKind::Text => "text",
+                            PartKind::File => "file",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut part.kind, PartKind::Text, "text");
+                            ui.selectable_value(&mut part.kind, PartKind::File, "file");
+                        });
+                    let browse_width = if part.kind == PartKind::File {
+                        64.0
+                    } else {
+                        0.0
+                    };
+                    let value_width = (ui.available_width() - browse_width - 140.0).max(80.0);
+                    match part.kind {
+                        PartKind::Text => {
+                            variable_text_edit(
+                                ui,
+                                &mut part.value,
+                                value_width,
+                                "value",
+                                Some(vars),
+                            );
+                        }
+                        PartKind::File => {
+                            variable_text_edit(
+                                ui,
+                                &mut part.value,
+                                value_width,
+                                "path",
+                                Some(vars),
+                            );
+                            if ui.button("browse…").clicked() {
+                                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                                    info!("picked {} for form part {}", path.display(), part.name);
+                                    part.value = path.display().to_string();
+                                }
+                            }
+                        }
+                    }
+                    variable_text_edit(
+                        ui,
+                        &mut part.content_type,
+                        120.0,
+                        "content type",
+                        Some(vars),
+                    );
+                });
+            }
+        });
+    if let Some(i) = to_remove {
+        parts.remove(i);
+    }
+    ui.add_space(4.0);
+    if ui.button("+ add part").clicked() {
+        info!("adding a new form part");
+        parts.push(FormPart::default());
+    }
+}
+
+/// the right-click menu of a response tree node. `filtered` says the tree
+/// shows a filter's result rather than the whole body, so paths are relative
+/// to that. picking "filter to this node" sets `filter_to`
+fn json_node_menu(
+    ui: &mut egui::Ui,
+    context: &RenderContext<'_, '_, serde_json::Value>,
+    filtered: bool,
+    filter_to: &mut Option<String>,
+) {
+    let mut path = Vec::new();
+    collect_path(context.pointer(), &mut path);
+    if filtered {
+        ui.label(RichText::new("paths are within the filtered result").weak());
+    }
+    let copy = |ui: &mut egui::Ui, label: &str, text: String| {
+        if ui.button(label).clicked() {
+            ui.ctx().copy_text(text);
+            ui.close_menu();
+        }
+    };
+    copy(ui, "copy jsonpath", json_filter::to_jsonpath(&path));
+    copy(ui, "copy js accessor", json_filter::to_js(&path));
+    if let Some(key) = path.last() {
+        let key = match key {
+            json_filter::PathSegment::Key(key) => key.clone(),
+            json_filter::PathSegment::Index(i) => i.to_string(),
+        };
+        copy(ui, "copy key", key);
+    }
+    copy(
+        ui,
+        "copy value",
+        serde_json::to_string_pretty(context.value()).unwrap_or_default(),
+    );
+    ui.separator();
+    let filter_button = ui
+        .add_enabled(!filtered, egui::Button::new("filter to this node"))
+        .on_disabled_hover_text("clear the filter to pick from the whole body");
+    if filter_button.clicked() {
+        *filter_to = Some(json_filter::to_jsonpath(&path));
+        ui.close_menu();
+    }
+}
+
+/// the segments of `pointer`, root first
+fn collect_path(pointer: JsonPointer, path: &mut Vec<json_filter::PathSegment>) {
+    if let Some(parent) = pointer.parent() {
+        collect_path(parent, path);
+    }
+    match pointer.last() {
+        Some(JsonPointerSegment::Key(key)) => {
+            path.push(json_filter::PathSegment::Key(key.to_string()))
+        }
+        Some(JsonPointerSegment::Index(i)) => path.push(json_filter::PathSegment::Index(*i)),
+        None => {}
+    }
+}
+
+/// asks where to save `contents`, suggesting `name`, and writes it there.
+/// returns a status line, empty when the dialog was cancelled
+fn save_response_file(name: &str, contents: &[u8]) -> String {
+    let Some(path) = rfd::FileDialog::new().set_file_name(name).save_file() else {
+        return String::new();
+    };
+    match std::fs::write(&path, contents) {
+        Ok(()) => {
+            info!("saved response to {}", path.display());
+            format!("saved to {}", path.display())
+        }
+        Err(e) => {
+            error!("failed to save response to {}: {}", path.display(), e);
+            format!("failed to save {}: {}", path.display(), e)
+        }
+    }
+}
+
+/// whether a `Content-Type` header says json
+fn has_json_content_type(headers: &[(String, String)]) -> bool {
+    headers.iter().any(|(k, v)| {
+        k.eq_ignore_ascii_case("content-type") && v.to_ascii_lowercase().contains("json")
+    })
+}
+
+/// format and minify buttons for a json body, disabled while it doesn't parse
+fn json_body_actions(ui: &mut egui::Ui, body: &mut String, indent: usize) {
+    let check = json_format::validate(body);
+    let disabled_hint = match &check {
+        Ok(()) => String::new(),
+        Err(e) => format!("the body doesn't parse: {}", e),
+    };
+    ui.horizontal(|ui| {
+        let format = ui
+            .add_enabled(check.is_ok(), egui::Button::new("format"))
+            .on_disabled_hover_text(&disabled_hint);
+        if format.clicked() {
+            if let Ok(pretty) = json_format::pretty(body, indent) {
+                *body = pretty;
+            }
+        }
+        let minify = ui
+            .add_enabled(check.is_ok(), egui::Button::new("minify"))
+            .on_disabled_hover_text(&disabled_hint);
+        if minify.clicked() {
+            if let Ok(minified) = json_format::minify(body) {
+                *body = minified;
+            }
+        }
+    });
+}
+
+/// shades the line of `error` in an editor and underlines its column
+fn mark_json_error(ui: &egui::Ui, output: &egui::text_edit::TextEditOutput, error: &JsonError) {
+    let text = output.galley.text();
+    let mut index = 0;
+    for (number, line) in text.split('\n').enumerate() {
+        if number + 1 < error.line {
+            index += line.chars().count() + 1;
+            continue;
+        }
+        // serde counts columns in bytes, the galley in chars
+        index += match error.column {
+            0 => line.chars().count(),
+            column => line
+                .char_indices()
+                .take_while(|(i, _)| *i < column - 1)
+                .count(),
+        };
+        break;
+    }
+    let cursor = output
+        .galley
+        .pos_from_ccursor(egui::text::CCursor::new(index))
+        .translate(output.galley_pos.to_vec2());
+    let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+    let color = Color32::from_rgb(255, 108, 117);
+    painter.rect_filled(
+        egui::Rect::from_x_y_ranges(output.text_clip_rect.x_range(), cursor.y_range()),
+        0.0,
+        color.gamma_multiply(0.15),
+    );
+    painter.line_segment(
+        [
+            cursor.left_bottom(),
+            cursor.left_bottom() + egui::vec2(8.0, 0.0),
+        ],
+        egui::Stroke::new(2.0, color),
+    );
+}
+
+/// underlines the placeholders in an editor's text that `vars` doesn't define,
+/// for editors like the code editor that do their own layout
+fn mark_undefined_variables(
+    ui: &egui::Ui,
+    output: &egui::text_edit::TextEditOutput,
+    vars: &HashMap<String, String>,
+) {
+    let text = output.galley.text();
+    let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+    let color = Color32::from_rgb(255, 108, 117);
+    for range in environment::undefined_spans(text, vars) {
+        // spans are in bytes, the galley counts chars
+        let start = text[..range.start].chars().count();
+        let end = start + text[range].chars().count();
+        let offset = output.galley_pos.to_vec2();
+        let from = output
+            .galley
+            .pos_from_ccursor(egui::text::CCursor::new(start))
+            .translate(offset);
+        let to = output
+            .galley
+            .pos_from_ccursor(egui::text::CCursor::new(end))
+            .translate(offset);
+        let rect = egui::Rect::from_min_max(from.min, to.max);
+        painter.rect_filled(rect, 2.0, color.gamma_multiply(0.15));
+        painter.line_segment(
+            [rect.left_bottom(), rect.right_bottom()],
+            egui::Stroke::new(1.0, color),
+        );
+    }
+}
+
+/// how many completions are offered at once
+const MAX_COMPLETIONS: usize = 12;
+
+/// query and variables editors for a graphql body, with completion from the
+/// tab's schema once it's fetched. returns whether a fetch was asked for
+fn graphql_editor(ui: &mut egui::Ui, tab: &mut RequestTab, show_schema: &mut bool) -> bool {
+    let mut fetch = false;
+    ui.horizontal(|ui| {
+        ui.label("operation:");
+        ui.add(
+            egui::TextEdit::singleline(&mut tab.request.graphql.operation_name)
+                .desired_width(160.0)
+                .hint_text("optional"),
+        );
+        if tab.introspecting.is_some() {
+            ui.spinner();
+        } else if ui.button("fetch schema").clicked() {
+            fetch = true;
+        }
+        if tab.schema.is_some() && ui.selectable_label(*show_schema, "browse schema").clicked() {
+            *show_schema = !*show_schema;
+        }
+        if !tab.schema_status.is_empty() {
+            ui.label(RichText::new(&tab.schema_status).weak());
+        }
+    });
+    ui.label("query:");
+    let output = CodeEditor::default()
+        .id_source(format!("graphql_query_{}", tab.id))
+        .with_rows(10)
+        .with_fontsize(14.0)
+        .with_theme(ColorTheme::AYU_DARK)
+        .with_syntax(graphql_syntax::graphql_syntax())
+        .with_numlines(true)
+        .vscroll(true)
+        .show(ui, &mut tab.request.graphql.query);
+    if let Some(schema) = &tab.schema {
+        graphql_completions(ui, &output, schema, &mut tab.request.graphql.query);
+    }
+    ui.label("variables:");
+    CodeEditor::default()
+        .id_source(format!("graphql_variables_{}", tab.id))
+        .with_rows(5)
+        .with_fontsize(14.0)
+        .with_theme(ColorTheme::AYU_DARK)
+        .with_syntax(json_syntax::json_syntax())
+        .with_numlines(true)
+        .vscroll(true)
+        .show(ui, &mut tab.request.graphql.variables);
+    if let Err(e) = tab.request.graphql.to_json() {
+        ui.colored_label(Color32::from_rgb(255, 108, 117), e);
+    }
+    fetch
+}
+
+/// a row of suggestions for the word at the query editor's cursor; clicking
+/// one puts it in place of the word
+fn graphql_completions(
+    ui: &mut egui::Ui,
+    output: &egui::text_edit::TextEditOutput,
+    schema: &Schema,
+    query: &mut String,
+) {
+    // clicking a suggestion takes focus from the editor before the click
+    // lands, so they stay up for a frame after it's lost
+    let focus_id = output.response.id.with("had_focus");
+    let had_focus = ui.data(|d| d.get_temp::<bool>(focus_id)).unwrap_or(false);
+    let has_focus = output.response.has_focus();
+    ui.data_mut(|d| d.insert_temp(focus_id, has_focus));
+    if !has_focus && !had_focus {
+        return;
+    }
+    let Some(range) = output.state.cursor.char_range() else {
+        return;
+    };
+    let cursor = range.primary.index;
+    let (start, items) = graphql::completions(schema, query, cursor);
+    if items.is_empty() {
+        return;
+    }
+    ui.horizontal_wrapped(|ui| {
+        ui.label(RichText::new("complete:").weak());
+        for item in items.iter().take(MAX_COMPLETIONS) {
+            let button = ui
+                .button(RichText::new(&item.label).monospace())
+                .on_hover_text(&item.detail);
+            if button.clicked() {
+                let byte = |i: usize| query.char_indices().nth(i).map_or(query.len(), |(b, _)| b);
+                let range = byte(start)..byte(cursor);
+                query.replace_range(range, &item.label);
+                let end = start + item.label.chars().count();
+                let mut state = output.state.clone();
+                state
+                    .cursor
+                    .set_char_range(Some(egui::text::CCursorRange::one(
+                        egui::text::CCursor::new(end),
+                    )));
+                state.store(ui.ctx(), output.response.id);
+                ui.memory_mut(|m| m.request_focus(output.response.id));
+            }
+        }
+        if items.len() > MAX_COMPLETIONS {
+            ui.label(RichText::new(format!("+{} more", items.len() - MAX_COMPLETIONS)).weak());
+        }
+    });
+}
+
+/// every type in `schema` as a collapsible entry listing its fields, input
+/// fields or values. a search keeps types whose name or members match
+fn schema_browser(ui: &mut egui::Ui, schema: &Schema, search: &str) {
+    let search = search.trim().to_lowercase();
+    let matches = |name: &str| name.to_lowercase().contains(&search);
+    let roots: Vec<&str> = ["query", "mutation", "subscription"]
+        .into_iter()
+        .filter_map(|op| schema.root(op))
+        .collect();
+    let ordered = roots.iter().filter_map(|name| schema.get(name)).chain(
+        schema
+            .user_types()
+            .filter(|t| !roots.contains(&t.name.as_str())),
+    );
+    for ty in ordered {
+        let member_matches = ty.fields().iter().any(|f| matches(&f.name))
+            || ty.input_fields().iter().any(|f| matches(&f.name))
+            || ty.enum_values().iter().any(|v| matches(&v.name));
+        if !search.is_empty() && !matches(&ty.name) && !member_matches {
+            continue;
+        }
+        let title = RichText::new(format!("{} {}", ty.kind.to_lowercase(), ty.name)).monospace();
+        let title = if roots.contains(&ty.name.as_str()) {
+            title.strong()
+        } else {
+            title
+        };
+        egui::CollapsingHeader::new(title)
+            .id_salt(("schema_type", &ty.name))
+            .open((!search.is_empty() && member_matches).then_some(true))
+            .show(ui, |ui| {
+                if let Some(description) = &ty.description {
+                    ui.label(RichText::new(description).weak());
+                }
+                for field in ty.fields() {
+                    let label = ui.label(RichText::new(field.signature()).monospace());
+                    if let Some(description) = &field.description {
+                        label.on_hover_text(description);
+                    }
+                }
+                for input in ty.input_fields() {
+                    let mut text = format!("{}: {}", input.name, input.ty);
+                    if let Some(default) = &input.default_value {
+                        text.push_str(&format!(" = {}", default));
+                    }
+                    let label = ui.label(RichText::new(text).monospace());
+                    if let Some(description) = &input.description {
+                        label.on_hover_text(description);
+                    }
+                }
+                for value in ty.enum_values() {
+                    let label = ui.label(RichText::new(&value.name).monospace());
+                    if let Some(description) = &value.description {
+                        label.on_hover_text(description);
+                    }
+                }
+                let possible: Vec<&str> = ty.possible_types().collect();
+                if !possible.is_empty() {
+                    ui.label(
+                        RichText::new(format!("possible types: {}", possible.join(", "))).weak(),
+                    );
+                }
+            });
+    }
+}
+
+/// the file sent as the body, its size, and the content type to send it as
+fn binary_file_editor(ui: &mut egui::Ui, file: &mut BinaryFile, vars: &HashMap<String, String>) {
+    egui::Grid::new("binary_file_grid")
+        .num_columns(2)
+        .spacing([8.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("file:");
+            ui.horizontal(|ui| {
+                variable_text_edit(ui, &mut file.path, 360.0, "path", Some(vars));
+                if ui.button("browse…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        info!("picked {} as the request body", path.display());
+                        file.path = path.display().to_string();
+                    }
+                }
+            });
+            ui.end_row();
+
+            ui.label("content type:");
+            let guessed = file.guessed_content_type();
+            ui.add(
+                egui::TextEdit::singleline(&mut file.content_type)
+                    .desired_width(360.0)
+                    .hint_text(guessed),
+            );
+            ui.end_row();
+        });
+    let path = environment::substitute(&file.path, vars, &mut Vec::new());
+    if !path.is_empty() {
+        // only the size is read here, the contents are streamed when sending
+        match std::fs::metadata(&path) {
+            Ok(meta) if meta.is_file() => {
+                ui.label(format!(
+                    "size: {}",
+                    response::format_size(meta.len() as usize)
+                ));
+            }
+            Ok(_) => {
+                ui.colored_label(Color32::from_rgb(255, 108, 117), "not a file");
+            }
+            Err(e) => {
+                ui.colored_label(Color32::from_rgb(255, 108, 117), e.to_string());
+            }
+        }
+    }
+}
+
+fn variable_text_edit(
+    ui: &mut egui::Ui,
+    text: &mut String,
+    width: f32,
+    hint: &str,
+    vars: Option<&HashMap<String, String>>,
+) -> egui::Response {
+    let edit = egui::TextEdit::singleline(text)
+        .desired_width(width)
+        .clip_text(true)
+        .hint_text(hint);
+    match vars {
+        Some(vars) => {
+            let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                let font_id = egui::TextStyle::Body.resolve(ui.style());
+                let mut job =
+                    highlight::variable_layout_job(text, font_id, ui.visuals().text_color(), vars);
+                job.wrap.max_width = wrap_width;
+                ui.fonts(|f| f.layout_job(job))
+            };
+            ui.add(edit.layouter(&mut layouter))
+        }
+        None => ui.add(edit),
+    }
+}
+
+/// mode picker and fields for `auth`, followed by a preview of what it adds
+/// to the request with variables resolved
+fn auth_editor(
+    ui: &mut egui::Ui,
+    auth: &mut Auth,
+    vars: &HashMap<String, String>,
+    tokens: &TokenCache,
+) {
+    egui::ComboBox::from_id_salt("auth_kind")
+        .selected_text(auth.kind.label())
+        .show_ui(ui, |ui| {
+            for kind in AuthKind::ALL {
+                ui.selectable_value(&mut auth.kind, kind, kind.label());
+            }
+        });
+    let width = ui.available_width() - 100.0;
+    egui::Grid::new("auth_fields")
+        .num_columns(2)
+        .show(ui, |ui| match auth.kind {
+            AuthKind::None => {}
+            AuthKind::Basic | AuthKind::Digest => {
+                ui.label("username");
+                variable_text_edit(ui, &mut auth.username, width, "username", Some(vars));
+                ui.end_row();
+                ui.label("password");
+                password_edit(ui, &mut auth.password, width);
+                ui.end_row();
+            }
+            AuthKind::Bearer => {
+                ui.label("token");
+                variable_text_edit(ui, &mut auth.token, width, "token", Some(vars));
+                ui.end_row();
+            }
+            AuthKind::ApiKey => {
+                ui.label("key");
+                variable_text_edit(ui, &mut auth.api_key_name, width, "X-API-Key", Some(vars));
+                ui.end_row();
+                ui.label("value");
+                variable_text_edit(ui, &mut auth.api_key_value, width, "value", Some(vars));
+                ui.end_row();
+                ui.label("add to");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut auth.api_key_location, ApiKeyLocation::Header, "header");
+                    ui.radio_value(
+                        &mut auth.api_key_location,
+                        ApiKeyLocation::Query,
+                        "query params",
+                    );
+                });
+                ui.end_row();
+            }
+            AuthKind::OAuth2 => oauth2_fields(ui, &mut auth.oauth2, width, vars),
+            AuthKind::SigV4 => {
+                let config = &mut auth.sigv4;
+                ui.label("access key");
+                variable_text_edit(ui, &mut config.access_key, width, "AKIA…", Some(vars));
+                ui.end_row();
+                ui.label("secret key");
+                password_edit(ui, &mut config.secret_key, width);
+                ui.end_row();
+                ui.label("session token");
+                variable_text_edit(ui, &mut config.session_token, width, "optional", Some(vars));
+                ui.end_row();
+                ui.label("region");
+                variable_text_edit(ui, &mut config.region, width, "us-east-1", Some(vars));
+                ui.end_row();
+                ui.label("service");
+                variable_text_edit(ui, &mut config.service, width, "execute-api", Some(vars));
+                ui.end_row();
+                ui.label("payload");
+                ui.checkbox(&mut config.unsigned_payload, "unsigned");
+                ui.end_row();
+            }
+        });
+
+    if auth.kind == AuthKind::None {
+        return;
+    }
+    ui.separator();
+    ui.label(RichText::new("generated").strong());
+    let resolved = auth.map_fields(|field| environment::substitute(field, vars, &mut Vec::new()));
+    let mut lines: Vec<String> = resolved
+        .headers()
+        .into_iter()
+        .map(|(k, v)| format!("{}: {}", k, v))
+        .collect();
+    lines.extend(
+        resolved
+            .queries()
+            .into_iter()
+            .map(|(k, v)| format!("?{}={}", k, v)),
+    );
+    if auth.kind == AuthKind::Digest {
+        lines.push("Authorization: Digest … (computed from the server's 401 challenge)".to_owned());
+    }
+    if auth.kind == AuthKind::SigV4 {
+        for name in resolved.sigv4.extra_header_names() {
+            lines.push(format!("{}: … (set when sent)", name));
+        }
+        lines.push("Authorization: AWS4-HMAC-SHA256 … (signed when sent)".to_owned());
+    }
+    let cached = tokens.get(&resolved.oauth2);
+    if auth.kind == AuthKind::OAuth2 {
+        match cached.as_ref().filter(|t| t.is_fresh()) {
+            Some(token) => lines.push(format!("Authorization: Bearer {}", token.access_token)),
+            None => lines.push("Authorization: Bearer … (token fetched on send)".to_owned()),
+        }
+    }
+    for line in lines {
+        ui.add(egui::Label::new(RichText::new(line).monospace()).wrap());
+    }
+
+    if let (AuthKind::OAuth2, Some(token)) = (auth.kind, cached) {
+        ui.horizontal(|ui| {
+            let status = match token.expires_at {
+                Some(at) if token.is_fresh() => format!(
+                    "cached token expires in {} s",
+                    at.saturating_duration_since(std::time::Instant::now())
+                        .as_secs()
+                ),
+                Some(_) if token.refresh_token.is_some() => {
+                    "cached token is expiring, it will be refreshed on send".to_owned()
+                }
+                Some(_) => "cached token is expiring, a new one is fetched on send".to_owned(),
+                None => "cached token has no expiry".to_owned(),
+            };
+            ui.label(RichText::new(status).weak());
+            if ui.button("clear token").clicked() {
+                info!("clearing cached oauth2 token");
+                tokens.clear(&resolved.oauth2);
+            }
+        });
+    }
 }
 
-impl Default for HttpMethod {
-    fn default() -> Self {
-        Self::GET
+fn oauth2_fields(
+    ui: &mut egui::Ui,
+    config: &mut OAuth2Config,
+    width: f32,
+    vars: &HashMap<String, String>,
+) {
+    ui.label("grant");
+    egui::ComboBox::from_id_salt("oauth2_grant")
+        .selected_text(config.grant.label())
+        .show_ui(ui, |ui| {
+            for grant in GrantType::ALL {
+                ui.selectable_value(&mut config.grant, grant, grant.label());
+            }
+        });
+    ui.end_row();
+    if config.grant == GrantType::AuthorizationCode {
+        ui.label("auth url");
+        variable_text_edit(
+            ui,
+            &mut config.auth_url,
+            width,
+            "https://…/authorize",
+            Some(vars),
+        );
+        ui.end_row();
+    }
+    ui.label("token url");
+    variable_text_edit(
+        ui,
+        &mut config.token_url,
+        width,
+        "https://…/token",
+        Some(vars),
+    );
+    ui.end_row();
+    ui.label("client id");
+    variable_text_edit(ui, &mut config.client_id, width, "client id", Some(vars));
+    ui.end_row();
+    ui.label("client secret");
+    password_edit(ui, &mut config.client_secret, width);
+    ui.end_row();
+    ui.label("send client as");
+    ui.horizontal(|ui| {
+        ui.radio_value(
+            &mut config.client_authentication,
+            ClientAuthentication::Body,
+            "body params",
+        );
+        ui.radio_value(
+            &mut config.client_authentication,
+            ClientAuthentication::BasicHeader,
+            "basic auth header",
+        );
+    });
+    ui.end_row();
+    ui.label("scope");
+    variable_text_edit(ui, &mut config.scope, width, "space separated", Some(vars));
+    ui.end_row();
+    match config.grant {
+        GrantType::Password => {
+            ui.label("username");
+            variable_text_edit(ui, &mut config.username, width, "username", Some(vars));
+            ui.end_row();
+            ui.label("password");
+            password_edit(ui, &mut config.password, width);
+            ui.end_row();
+        }
+        GrantType::RefreshToken => {
+            ui.label("refresh token");
+            variable_text_edit(
+                ui,
+                &mut config.refresh_token,
+                width,
+                "refresh token",
+                Some(vars),
+            );
+            ui.end_row();
+        }
+        GrantType::AuthorizationCode => {
+            ui.label("redirect port");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut config.redirect_port));
+                ui.label(
+                    RichText::new(format!(
+                        "redirects to http://127.0.0.1:{}/callback",
+                        if config.redirect_port == 0 {
+                            "<any>".to_owned()
+                        } else {
+                            config.redirect_port.to_string()
+                        }
+                    ))
+                    .weak(),
+                );
+            });
+            ui.end_row();
+        }
+        GrantType::ClientCredentials => {}
     }
 }
 
-#[derive(PartialEq)]
-enum EditorTab {
-    Headers,
-    Body,
-    Query,
+/// editors for the pre-request and post-response scripts, with a summary of
+/// what scripts can use
+fn scripts_editor(ui: &mut egui::Ui, tab_id: u64, scripts: &mut Scripts) {
+    egui::CollapsingHeader::new("script reference")
+        .id_salt(("script_reference", tab_id))
+        .show(ui, |ui| {
+            for line in SCRIPT_REFERENCE {
+                ui.label(RichText::new(*line).monospace().small());
+            }
+        });
+    egui::ScrollArea::vertical()
+        .id_salt("scripts_scroll")
+        .show(ui, |ui| {
+            for (label, id, script) in [
+                (
+                    "pre-request",
+                    "pre_request_script",
+                    &mut scripts.pre_request,
+                ),
+                (
+                    "post-response",
+                    "post_response_script",
+                    &mut scripts.post_response,
+                ),
+            ] {
+                ui.label(label);
+                CodeEditor::default()
+                    .id_source(format!("{}_{}", id, tab_id))
+                    .with_rows(8)
+                    .with_fontsize(14.0)
+                    .with_theme(ColorTheme::AYU_DARK)
+                    .with_syntax(rhai_syntax::rhai_syntax())
+                    .with_numlines(true)
+                    .vscroll(false)
+                    .show(ui, script);
+                ui.add_space(6.0);
+            }
+        });
 }
 
-struct SnoozeApp {
-    url: String,
-    selected_method: HttpMethod,
-    response_text: String,
-    parsed_json: Option<Value>,
-    last_duration: Option<Duration>,
-    in_flight: bool,
-    tx: Option<Sender<(String, Duration)>>,
-    rx: Option<Receiver<(String, Duration)>>,
-    headers: Vec<(String, String)>,
-    request_body: String,
-    queries: Vec<(String, String)>,
-    divider: f32,
-    selected_tab: EditorTab,
-    search_input: String, // added for json search
+/// what scripts can call, shown above the script editors
+const SCRIPT_REFERENCE: &[&str] = &[
+    "pre-request:   request.method / url / body, request.header(name),",
+    "               set_header(name, value), remove_header(name),",
+    "               query(name), set_query(name, value), remove_query(name)",
+    "post-response: response.status / reason / body / headers,",
+    "               response.header(name), response.json()",
+    "both:          variable(name), set_variable(name, value), print(x),",
+    "               timestamp(), timestamp_ms(), uuid(), sha256(s),",
+    "               hmac_sha256(key, s), base64_encode(s), base64_decode(s),",
+    "               parse_json(s)",
+];
+
+/// the console tab selector, shown once scripts have printed something
+fn console_tab_label(ui: &mut egui::Ui, response_tab: &mut ResponseTab, console: &[ConsoleLine]) {
+    if console.is_empty() {
+        return;
+    }
+    let errors = console.iter().any(|line| line.error);
+    let label = RichText::new(format!("console ({})", console.len()));
+    let label = if errors {
+        label.color(Color32::from_rgb(255, 108, 117))
+    } else {
+        label
+    };
+    if ui
+        .selectable_label(*response_tab == ResponseTab::Console, label)
+        .clicked()
+    {
+        *response_tab = ResponseTab::Console;
+    }
 }
 
-impl Default for SnoozeApp {
-    fn default() -> Self {
-        Self::new()
+/// script output for the last request, errors in red
+fn script_console(ui: &mut egui::Ui, console: &[ConsoleLine]) {
+    egui::ScrollArea::vertical()
+        .id_salt("script_console_scroll")
+        .show(ui, |ui| {
+            for line in console {
+                let stage = line.stage.map_or("snooze", |stage| stage.label());
+                let text = RichText::new(format!("[{}] {}", stage, line.text)).monospace();
+                if line.error {
+                    ui.label(text.color(Color32::from_rgb(255, 108, 117)));
+                } else {
+                    ui.label(text);
+                }
+            }
+        });
+}
+
+/// one row per assertion: enabled, kind, then the fields that kind uses
+fn assertions_editor(
+    ui: &mut egui::Ui,
+    assertions: &mut Vec<Assertion>,
+    vars: &HashMap<String, String>,
+) {
+    ui.label("checked against every response to this request:");
+    let mut to_remove = None;
+    egui::ScrollArea::vertical()
+        .id_salt("assertions_scroll")
+        .show(ui, |ui| {
+            for (i, assertion) in assertions.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add(
+                            egui::Button::new("×")
+                                .fill(Color32::from_rgb(255, 88, 88))
+                                .min_size(egui::vec2(24.0, 18.0)),
+                        )
+                        .clicked()
+                    {
+                        info!("removing assertion at index {}", i);
+                        to_remove = Some(i);
+                    }
+                    ui.checkbox(&mut assertion.enabled, "");
+                    egui::ComboBox::from_id_salt(("assertion_kind", i))
+                        .width(130.0)
+                        .selected_text(assertion.kind.label())
+                        .show_ui(ui, |ui| {
+                            for kind in AssertionKind::ALL {
+                                ui.selectable_value(&mut assertion.kind, kind, kind.label());
+                            }
+                        });
+                    if let Some(hint) = assertion.kind.target_hint() {
+                        variable_text_edit(ui, &mut assertion.target, 150.0, hint, Some(vars));
+                    }
+                    if let Some(hint) = assertion.kind.expected_hint() {
+                        let width = ui.available_width().max(80.0);
+                        variable_text_edit(ui, &mut assertion.expected, width, hint, Some(vars));
+                    }
+                });
+            }
+        });
+    if let Some(i) = to_remove {
+        assertions.remove(i);
+    }
+    ui.add_space(4.0);
+    if ui.button("+ add assertion").clicked() {
+        info!("adding a new assertion");
+        assertions.push(Assertion::default());
     }
 }
 
-impl SnoozeApp {
-    fn new() -> Self {
-        let (tx, rx) = channel();
-        Self {
-            url: "".to_owned(),
-            selected_method: HttpMethod::default(),
-            response_text: "".to_owned(),
-            parsed_json: None,
-            last_duration: None,
-            in_flight: false,
-            tx: Some(tx),
-            rx: Some(rx),
-            headers: vec![("".to_owned(), "".to_owned())],
-            request_body: "".to_owned(),
-            queries: vec![("".to_owned(), "".to_owned())],
-            divider: 250.0,
-            selected_tab: EditorTab::Headers,
-            search_input: "".to_owned(),
+/// green when everything passed, red otherwise
+fn tests_color(passed: usize, total: usize) -> Color32 {
+    if passed == total {
+        Color32::from_rgb(152, 230, 121)
+    } else {
+        Color32::from_rgb(255, 108, 117)
+    }
+}
+
+/// the "tests" response tab, only shown when assertions ran
+fn tests_tab_label(ui: &mut egui::Ui, response_tab: &mut ResponseTab, results: &[AssertionResult]) {
+    if results.is_empty() {
+        return;
+    }
+    let passed = assertions::passed(results);
+    let label = RichText::new(format!("tests ({}/{})", passed, results.len()))
+        .color(tests_color(passed, results.len()));
+    if ui
+        .selectable_label(*response_tab == ResponseTab::Tests, label)
+        .clicked()
+    {
+        *response_tab = ResponseTab::Tests;
+    }
+}
+
+/// pass/fail for each assertion of the last request
+fn assertion_results(ui: &mut egui::Ui, results: &[AssertionResult]) {
+    egui::ScrollArea::vertical()
+        .id_salt("assertion_results_scroll")
+        .show(ui, |ui| {
+            egui::Grid::new("assertion_results")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for result in results {
+                        let (mark, color) = if result.passed {
+                            ("✔", Color32::from_rgb(152, 230, 121))
+                        } else {
+                            ("✘", Color32::from_rgb(255, 108, 117))
+                        };
+                        ui.label(RichText::new(mark).monospace().color(color));
+                        ui.label(RichText::new(&result.description).monospace());
+                        ui.label(RichText::new(&result.message).weak());
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+/// totals for a collection run, then a row per step
+fn run_summary(ui: &mut egui::Ui, run: &CollectionRun) {
+    let passed = run.passed();
+    let failed = run.results.len() - passed;
+    let total = run.steps * run.iterations;
+    let state = if !run.finished {
+        "running"
+    } else if run.stopped {
+        "stopped after a failure"
+    } else if run.results.len() < total {
+        "cancelled"
+    } else {
+        "done"
+    };
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "{}: {}/{} requests",
+            state,
+            run.results.len(),
+            total
+        ));
+        ui.label(
+            RichText::new(format!("{} passed", passed)).color(Color32::from_rgb(152, 230, 121)),
+        );
+        if failed > 0 {
+            ui.label(
+                RichText::new(format!("{} failed", failed)).color(Color32::from_rgb(255, 108, 117)),
+            );
+        }
+        ui.label(format!("{} ms", run.total_duration().as_millis()));
+    });
+    ui.separator();
+    egui::ScrollArea::vertical()
+        .id_salt("run_results_scroll")
+        .show(ui, |ui| {
+            egui::Grid::new("run_results")
+                .num_columns(if run.iterations > 1 { 7 } else { 6 })
+                .striped(true)
+                .show(ui, |ui| {
+                    for result in &run.results {
+                        run_step_row(ui, result, run.iterations > 1);
+                    }
+                });
+        });
+}
+
+fn run_step_row(ui: &mut egui::Ui, result: &StepResult, show_iteration: bool) {
+    let (mark, color) = if result.passed() {
+        ("✔", Color32::from_rgb(152, 230, 121))
+    } else {
+        ("✘", Color32::from_rgb(255, 108, 117))
+    };
+    if show_iteration {
+        ui.label(RichText::new(format!("#{}", result.iteration + 1)).weak());
+    }
+    ui.label(RichText::new(mark).monospace().color(color));
+    ui.label(
+        RichText::new(format!("{:?}", result.method))
+            .monospace()
+            .color(method_color(&result.method)),
+    );
+    // the url it went to, then anything its scripts printed
+    let mut details = vec![result.url.clone()];
+    details.extend(result.console.iter().map(|line| {
+        let stage = line.stage.map_or("snooze", |stage| stage.label());
+        format!("[{}] {}", stage, line.text)
+    }));
+    ui.label(RichText::new(&result.name).monospace())
+        .on_hover_text(details.join("\n"));
+    match (result.status, &result.error) {
+        (Some(status), _) => ui.label(
+            RichText::new(status.to_string())
+                .monospace()
+                .color(status_color(status)),
+        ),
+        (None, Some(error)) => ui
+            .label(
+                RichText::new("err")
+                    .monospace()
+                    .color(Color32::from_rgb(255, 108, 117)),
+            )
+            .on_hover_text(error),
+        (None, None) => ui.label(""),
+    };
+    ui.label(format!("{} ms", result.duration.as_millis()));
+    if result.assertions.is_empty() {
+        ui.label("");
+    } else {
+        let passed = assertions::passed(&result.assertions);
+        let total = result.assertions.len();
+        let failures: Vec<String> = result
+            .assertions
+            .iter()
+            .filter(|a| !a.passed)
+            .map(|a| format!("✘ {}: {}", a.description, a.message))
+            .collect();
+        let label = ui.label(
+            RichText::new(format!("tests {}/{}", passed, total))
+                .monospace()
+                .color(tests_color(passed, total)),
+        );
+        if !failures.is_empty() {
+            label.on_hover_text(failures.join("\n"));
         }
     }
+    ui.end_row();
+}
+
+/// a masked single line field; variables still resolve but aren't highlighted
+fn password_edit(ui: &mut egui::Ui, text: &mut String, width: f32) -> egui::Response {
+    ui.add(
+        egui::TextEdit::singleline(text)
+            .desired_width(width)
+            .password(true)
+            .hint_text("password"),
+    )
+}
+
+/// badge color for a status code, by class
+fn status_color(status: u16) -> Color32 {
+    match status {
+        200..=299 => Color32::from_rgb(152, 230, 121),
+        300..=399 => Color32::from_rgb(97, 175, 255),
+        400..=499 => Color32::from_rgb(255, 192, 123),
+        500..=599 => Color32::from_rgb(255, 108, 117),
+        _ => Color32::GRAY,
+    }
+}
+
+/// a checkbox and millisecond field per timeout; unchecked timeouts show
+/// `unset_label`. returns whether anything changed
+fn timeouts_editor(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    timeouts: &mut Timeouts,
+    unset_label: &str,
+) -> bool {
+    let mut changed = false;
+    egui::Grid::new(id_salt).num_columns(3).show(ui, |ui| {
+        for (label, value, default_ms) in [
+            ("connect", &mut timeouts.connect_ms, 10_000),
+            ("total", &mut timeouts.total_ms, 60_000),
+            ("read", &mut timeouts.read_ms, 30_000),
+        ] {
+            ui.label(label);
+            let mut enabled = value.is_some();
+            if ui.checkbox(&mut enabled, "").changed() {
+                *value = enabled.then_some(default_ms);
+                changed = true;
+            }
+            match value {
+                Some(ms) => {
+                    changed |= ui
+                        .add(egui::DragValue::new(ms).range(1..=3_600_000).suffix(" ms"))
+                        .changed();
+                }
+                None => {
+                    ui.label(RichText::new(unset_label).weak());
+                }
+            }
+            ui.end_row();
+        }
+    });
+    changed
 }
 
 impl eframe::App for SnoozeApp {
@@ -85,29 +2616,192 @@ impl eframe::App for SnoozeApp {
 
       let syntax = json_syntax::json_syntax();
         // drain channel
-        if let Some(rx) = &self.rx {
-            while let Ok((resp, dur)) = rx.try_recv() {
-                info!("received response in {} ms", dur.as_millis());
-                self.response_text = resp;
-                self.last_duration = Some(dur);
-                self.in_flight = false;
-                self.parsed_json = serde_json::from_str(&self.response_text).ok();
+        let received: Vec<_> = self
+            .rx
+            .as_ref()
+            .map(|rx| rx.try_iter().collect())
+            .unwrap_or_default();
+        for result in received {
+            let Some(pending) = self.pending.remove(&result.id) else {
+                info!("discarding result of cancelled request {}", result.id);
+                continue;
+            };
+            match pending.kind {
+                PendingKind::Send => {}
+                PendingKind::Introspection => {
+                    match self
+                        .tabs
+                        .iter_mut()
+                        .find(|t| t.id == pending.tab && t.introspecting == Some(result.id))
+                    {
+                        Some(tab) => tab.show_schema(result),
+                        None => info!("introspection {} finished in the background", result.id),
+                    }
+                    continue;
+                }
+            }
+            let mut result = result;
+            if let Err(e) = self.set_script_variables(&result.script_log.variables) {
+                result.script_log.console.push(ConsoleLine {
+                    stage: None,
+                    text: e,
+                    error: true,
+                });
+            }
+            let dur = result.duration;
+            // recorded as it went out, nothing is when it failed before sending
+            if let Some(sent) = &result.sent {
+                let mut entry = HistoryEntry::new(
+                    sent.method.clone(),
+                    &sent.url,
+                    &[],
+                    &sent.headers,
+                    &sent.body,
+                );
+                entry.status = result.response.as_ref().ok().map(|r| r.status);
+                entry.duration_ms = dur.as_millis() as u64;
+                entry.response_body = match &result.response {
+                    Ok(resp) => resp.text(),
+                    Err(e) => e.to_string(),
+                };
+                entry.assertions = result.assertions.clone();
+                self.history.push(entry);
+                self.save_history();
+            }
+            // the tab may have been closed or sent something newer since
+            let tab = self
+                .tabs
+                .iter_mut()
+                .find(|t| t.id == pending.tab && t.in_flight == Some(result.id));
+            match tab {
+                Some(tab) => {
+                    info!("received response in {} ms", dur.as_millis());
+                    tab.show_result(result);
+                }
+                None => info!("request {} finished in the background", result.id),
             }
         }
-        if self.in_flight {
+        self.poll_history_writer();
+        if !self.pending.is_empty() {
+            ctx.request_repaint();
+        }
+        if let Some(run) = self.run.as_mut().filter(|run| !run.finished) {
+            run.poll();
             ctx.request_repaint();
         }
 
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.label("snooze v0.1.1");
-                if let Some(dur) = self.last_duration {
+                let tab = &self.tabs[self.active_tab];
+                if let Some(resp) = &tab.last_response {
+                    let badge = RichText::new(format!(" {} {} ", resp.status, resp.reason))
+                        .monospace()
+                        .strong()
+                        .color(Color32::BLACK)
+                        .background_color(status_color(resp.status));
+                    ui.label(badge);
+                }
+                if let Some(RequestError::Timeout { .. }) = &tab.last_error {
+                    let badge = RichText::new(" TIMEOUT ")
+                        .monospace()
+                        .strong()
+                        .color(Color32::BLACK)
+                        .background_color(Color32::from_rgb(255, 192, 123));
+                    ui.label(badge);
+                }
+                let has_graphql_errors = tab.request.body_mode == BodyMode::Graphql
+                    && tab
+                        .parsed_json
+                        .as_ref()
+                        .is_some_and(|json| !graphql::response_errors(json).is_empty());
+                if has_graphql_errors {
+                    let badge = RichText::new(" GRAPHQL ERRORS ")
+                        .monospace()
+                        .strong()
+                        .color(Color32::BLACK)
+                        .background_color(Color32::from_rgb(255, 108, 117));
+                    ui.label(badge);
+                }
+                if let Some(dur) = tab.last_duration {
                     ui.label(format!(" | request took: {} ms", dur.as_millis()));
                 }
+                if let Some(resp) = &tab.last_response {
+                    let size = response::format_size(resp.body.len());
+                    match resp.content_length {
+                        Some(len) if len as usize != resp.body.len() => {
+                            ui.label(format!(" | size: {} (content-length: {})", size, len))
+                        }
+                        _ => ui.label(format!(" | size: {}", size)),
+                    };
+                }
+                if !self.pending.is_empty() {
+                    ui.spinner();
+                    ui.label(format!("{} in flight", self.pending.len()));
+                }
+                let upload = self
+                    .tab()
+                    .in_flight
+                    .and_then(|id| self.pending.get(&id))
+                    .and_then(|pending| pending.progress.get());
+                if let Some((sent, total)) = upload {
+                    ui.add(
+                        egui::ProgressBar::new(sent as f32 / total as f32)
+                            .desired_width(160.0)
+                            .text(format!(
+                                "uploaded {} of {}",
+                                response::format_size(sent as usize),
+                                response::format_size(total as usize)
+                            )),
+                    );
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .selectable_label(self.show_settings, "settings")
+                        .clicked()
+                    {
+                        self.show_settings = !self.show_settings;
+                    }
+                    if ui.selectable_label(self.show_history, "history").clicked() {
+                        self.show_history = !self.show_history;
+                    }
+                    self.environment_selector(ui);
+                });
             });
         });
 
+        self.environments_window(ctx);
+        self.curl_import_window(ctx);
+        self.settings_window(ctx);
+        self.schema_window(ctx);
+        self.runner_window(ctx);
+        let vars = self.environments.active_variables();
+
+        if self.show_history {
+            egui::SidePanel::right("history_panel")
+                .resizable(true)
+                .default_width(320.0)
+                .show(ctx, |ui| {
+                    self.history_panel(ui);
+                });
+        }
+
+        egui::SidePanel::left("collections_panel")
+            .resizable(true)
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                self.collections_panel(ui);
+            });
+
         egui::CentralPanel::default().show(ctx, |ui| {
+            self.tab_strip(ui);
+            ui.separator();
+
+            let mut send = false;
+            let mut cancel = false;
+            let mut copy_as = None;
+            let mut fetch_schema = false;
+            let tab = &mut self.tabs[self.active_tab];
             let avail_rect = ui.available_rect_before_wrap();
             let avail_width = avail_rect.width();
             let avail_height = avail_rect.height();
@@ -141,279 +2835,263 @@ impl eframe::App for SnoozeApp {
             );
 
             // left pane: request options
-            ui.allocate_ui_at_rect(left_inner, |ui| {
+            ui.allocate_new_ui(egui::UiBuilder::new().max_rect(left_inner), |ui| {
                 ui.set_max_width(left_inner.width());
 
                 ui.heading("request");
                 ui.separator();
 
                 ui.horizontal_wrapped(|ui| {
-                    let methods = [
-                        (HttpMethod::GET, Color32::from_rgb(97, 175, 255)),
-                        (HttpMethod::POST, Color32::from_rgb(152, 230, 121)),
-                        (HttpMethod::PUT, Color32::from_rgb(198, 120, 255)),
-                        (HttpMethod::DELETE, Color32::from_rgb(255, 108, 117)),
-                        (HttpMethod::PATCH, Color32::from_rgb(255, 192, 123)),
-                        (HttpMethod::HEAD, Color32::from_rgb(86, 182, 230)),
-                        (HttpMethod::OPTIONS, Color32::from_rgb(152, 195, 121)),
-                    ];
-                    for (method, color) in methods.iter() {
+                    for method in HttpMethod::ALL.iter() {
+                        let color = method_color(method);
                         let txt = format!("{:?}", method);
-                        let is_selected = self.selected_method == *method;
+                        let is_selected = tab.request.method == *method;
                         let btn_txt = RichText::new(&txt)
-                            .color(if is_selected { Color32::WHITE } else { *color });
+                            .color(if is_selected { Color32::WHITE } else { color });
                         let mut button = egui::Button::new(btn_txt);
                         if is_selected {
-                            button = button.fill(*color);
+                            button = button.fill(color);
                         }
                         if ui.add(button).clicked() {
                             info!("changed method to {:?}", method);
-                            self.selected_method = method.clone();
+                            tab.request.method = method.clone();
                         }
                     }
                 });
 
                 ui.add_space(10.0);
                 let url_width = ui.available_width();
+                let mut url_layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+                    let mut job = highlight::variable_layout_job(
+                        text,
+                        font_id,
+                        ui.visuals().text_color(),
+                        &vars,
+                    );
+                    job.wrap.max_width = wrap_width;
+                    ui.fonts(|f| f.layout_job(job))
+                };
                 ui.add(
-                    egui::TextEdit::multiline(&mut self.url)
+                    egui::TextEdit::multiline(&mut tab.request.url)
+                        .id_salt(("url", tab.id))
                         .hint_text("url")
                         .desired_rows(1)
                         .desired_width(url_width)
                         .font(egui::TextStyle::Monospace)
-                        .clip_text(true),
+                        .clip_text(true)
+                        .layouter(&mut url_layouter),
                 );
 
-                if ui.button("send").clicked() {
-                    if Url::parse(&self.url).is_err() {
-                        error!("invalid url: {}", self.url);
-                        self.response_text = format!("invalid url: {}", self.url);
-                        self.parsed_json = None;
-                        self.last_duration = None;
-                    } else {
-                        info!("{:?} {}", self.selected_method, self.url);
-                        let request_body = self.request_body.clone();
-                        let queries = self.queries.clone();
-                        let headers = self.headers.clone();
-                        let url = self.url.clone();
-                        let method = self.selected_method.clone();
-                        self.in_flight = true;
-                        if let Some(tx) = self.tx.clone() {
-                            std::thread::spawn(move || {
-                                info!("executing request: {:?} {}", method, url);
-                                let client = reqwest::blocking::Client::new();
-                                let mut req_builder = match method {
-                                    HttpMethod::GET => client.get(&url),
-                                    HttpMethod::POST => client.post(&url),
-                                    HttpMethod::PUT => client.put(&url),
-                                    HttpMethod::DELETE => client.delete(&url),
-                                    HttpMethod::PATCH => client.patch(&url),
-                                    HttpMethod::HEAD => client.head(&url),
-                                    HttpMethod::OPTIONS => {
-                                        client.request(reqwest::Method::OPTIONS, &url)
-                                    }
-                                };
-                                // add headers
-                                for (key, value) in headers.iter() {
-                                    if !key.is_empty() {
-                                        req_builder = req_builder.header(key, value);
-                                    }
-                                }
-                                let query_params: Vec<(&str, &str)> = queries
-                                    .iter()
-                                    .filter_map(|(k, v)| {
-                                        if !k.is_empty() {
-                                            Some((k.as_str(), v.as_str()))
-                                        } else {
-                                            None
-                                        }
-                                    })
-                                    .collect();
-                                if !query_params.is_empty() {
-                                    req_builder = req_builder.query(&query_params);
-                                }
-                                if matches!(
-                                    method,
-                                    HttpMethod::POST | HttpMethod::PUT | HttpMethod::PATCH
-                                ) {
-                                    req_builder = req_builder.body(request_body);
-                                }
-                                let start = Instant::now();
-                                let response = req_builder.send();
-                                let duration = start.elapsed();
-                                let result = match response {
-                                    Ok(resp) => match resp.text() {
-                                        Ok(txt) => {
-                                            info!("request succeeded in {} ms", duration.as_millis());
-                                            txt
-                                        }
-                                        Err(e) => {
-                                            error!("error reading response: {}", e);
-                                            format!("error reading response: {}", e)
-                                        }
-                                    },
-                                    Err(e) => {
-                                        error!("request error: {}", e);
-                                        format!("request error: {}", e)
-                                    }
-                                };
-                                let _ = tx.send((result, duration));
-                            });
-                        }
-                        self.response_text =
-                            format!("{:?} {}", self.selected_method, self.url);
-                        self.parsed_json = None;
-                        self.last_duration = None;
+                ui.horizontal(|ui| {
+                    if ui.button("send").clicked() {
+                        send = true;
                     }
+                    if tab.in_flight.is_some() && ui.button("cancel").clicked() {
+                        cancel = true;
+                    }
+                    if ui.button("paste curl").clicked() {
+                        self.curl_messages.clear();
+                        self.show_curl_import = true;
+                    }
+                    ui.menu_button("copy as…", |ui| {
+                        for language in codegen::Language::ALL {
+                            if ui.button(language.label()).clicked() {
+                                copy_as = Some(language);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+                if !tab.notice.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(Color32::from_rgb(255, 180, 84), &tab.notice);
+                        if ui.small_button("dismiss").clicked() {
+                            tab.notice.clear();
+                        }
+                    });
                 }
 
                 ui.separator();
 
                 ui.horizontal(|ui| {
                     if ui
-                        .selectable_label(self.selected_tab == EditorTab::Headers, "headers")
+                        .selectable_label(tab.request.tab == EditorTab::Headers, "headers")
                         .clicked()
                     {
                         info!("switched editor tab to: headers");
-                        self.selected_tab = EditorTab::Headers;
+                        tab.request.tab = EditorTab::Headers;
                     }
                     if ui
-                        .selectable_label(self.selected_tab == EditorTab::Body, "body")
+                        .selectable_label(tab.request.tab == EditorTab::Body, "body")
                         .clicked()
                     {
                         info!("switched editor tab to: body");
-                        self.selected_tab = EditorTab::Body;
+                        tab.request.tab = EditorTab::Body;
                     }
                     if ui
-                        .selectable_label(self.selected_tab == EditorTab::Query, "query")
+                        .selectable_label(tab.request.tab == EditorTab::Query, "query")
                         .clicked()
                     {
                         info!("switched editor tab to: query");
-                        self.selected_tab = EditorTab::Query;
+                        tab.request.tab = EditorTab::Query;
+                    }
+                    if ui
+                        .selectable_label(tab.request.tab == EditorTab::Auth, "auth")
+                        .clicked()
+                    {
+                        info!("switched editor tab to: auth");
+                        tab.request.tab = EditorTab::Auth;
+                    }
+                    if ui
+                        .selectable_label(tab.request.tab == EditorTab::Scripts, "scripts")
+                        .clicked()
+                    {
+                        info!("switched editor tab to: scripts");
+                        tab.request.tab = EditorTab::Scripts;
+                    }
+                    if ui
+                        .selectable_label(tab.request.tab == EditorTab::Tests, "tests")
+                        .clicked()
+                    {
+                        info!("switched editor tab to: tests");
+                        tab.request.tab = EditorTab::Tests;
+                    }
+                    if ui
+                        .selectable_label(tab.request.tab == EditorTab::Settings, "settings")
+                        .clicked()
+                    {
+                        info!("switched editor tab to: settings");
+                        tab.request.tab = EditorTab::Settings;
                     }
                 });
                 ui.separator();
 
-                match self.selected_tab {
+                match tab.request.tab {
                     EditorTab::Headers => {
-                        let mut to_remove = None;
-                        egui::ScrollArea::horizontal()
-                            .id_salt("headers_scroll")
-                            .show(ui, |ui| {
-                                for (i, header) in self.headers.iter_mut().enumerate() {
-                                    ui.horizontal(|ui| {
-                                        ui.set_max_width(ui.available_width());
-                                        let delete_btn_width = 24.0;
-                                        let spacing = 8.0;
-                                        let field_width = (ui.available_width()
-                                            - delete_btn_width
-                                            - spacing * 2.0)
-                                            / 2.0;
-                                        if ui
-                                            .add(
-                                                egui::Button::new("×")
-                                                    .fill(Color32::from_rgb(255, 88, 88))
-                                                    .min_size(egui::vec2(delete_btn_width, 18.0)),
-                                            )
-                                            .clicked()
-                                        {
-                                            info!("removing header at index {}", i);
-                                            to_remove = Some(i);
-                                        }
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut header.0)
-                                                .desired_width(field_width)
-                                                .clip_text(true)
-                                                .hint_text("key"),
-                                        );
-                                        ui.label(":");
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut header.1)
-                                                .desired_width(field_width)
-                                                .clip_text(true)
-                                                .hint_text("value"),
-                                        );
-                                    });
-                                }
-                            });
-                        if let Some(i) = to_remove {
-                            self.headers.remove(i);
-                        }
-                        ui.add_space(4.0);
-                        if ui.button("+ add header").clicked() {
-                            info!("adding a new header");
-                            self.headers.push(("".to_owned(), "".to_owned()));
-                        }
+                        key_value_editor(
+                            ui,
+                            "headers_scroll",
+                            &mut tab.request.headers,
+                            ":",
+                            "header",
+                            Some(&vars),
+                        );
                     }
 
                     EditorTab::Body => {
-                        ui.label("body:");
-                        egui::ScrollArea::horizontal()
-                            .id_salt("body_scroll")
-                            .show(ui, |ui| {
-                                // use the code editor instead of the plain text editor
-                                CodeEditor::default()
-                                    .id_source("code_editor")
-                                    .with_rows(12)
-                                    .with_fontsize(14.0)
-                                    .with_theme(ColorTheme::AYU_DARK)
-                                    .with_syntax(syntax.clone())
-                                    .with_numlines(true)
-                                    .vscroll(true)
-                                    .show(ui, &mut self.request_body);
-                            });
-                    }
-                    EditorTab::Query => {
-                        ui.label("query parameters:");
-                        let mut to_remove = None;
-                        egui::ScrollArea::horizontal()
-                            .id_salt("queries_scroll")
-                            .show(ui, |ui| {
-                                for (i, query) in self.queries.iter_mut().enumerate() {
-                                    ui.horizontal(|ui| {
-                                        ui.set_max_width(ui.available_width());
-                                        let delete_btn_width = 24.0;
-                                        let spacing = 8.0;
-                                        let field_width = (ui.available_width()
-                                            - delete_btn_width
-                                            - spacing * 2.0)
-                                            / 2.0;
-                                        if ui
-                                            .add(
-                                                egui::Button::new("×")
-                                                    .fill(Color32::from_rgb(255, 88, 88))
-                                                    .min_size(egui::vec2(delete_btn_width, 18.0)),
-                                            )
-                                            .clicked()
-                                        {
-                                            info!("removing query at index {}", i);
-                                            to_remove = Some(i);
-                                        }
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut query.0)
-                                                .desired_width(field_width)
-                                                .clip_text(true)
-                                                .hint_text("key"),
-                                        );
-                                        ui.label("=");
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut query.1)
-                                                .desired_width(field_width)
-                                                .clip_text(true)
-                                                .hint_text("value"),
+                        ui.horizontal(|ui| {
+                            ui.label("body:");
+                            for mode in BodyMode::ALL {
+                                if ui
+                                    .selectable_label(tab.request.body_mode == mode, mode.label())
+                                    .clicked()
+                                {
+                                    info!("switched body mode to: {}", mode.label());
+                                    tab.request.body_mode = mode;
+                                }
+                            }
+                        });
+                        match tab.request.body_mode {
+                            BodyMode::Raw => {
+                                let is_json = has_json_content_type(&tab.request.headers)
+                                    || tab.request.body.trim_start().starts_with(['{', '[']);
+                                if is_json {
+                                    json_body_actions(
+                                        ui,
+                                        &mut tab.request.body,
+                                        self.settings.json_indent,
+                                    );
+                                }
+                                let output = egui::ScrollArea::horizontal()
+                                    .id_salt("body_scroll")
+                                    .show(ui, |ui| {
+                                        // use the code editor instead of the plain text editor
+                                        CodeEditor::default()
+                                            .id_source(format!("code_editor_{}", tab.id))
+                                            .with_rows(12)
+                                            .with_fontsize(14.0)
+                                            .with_theme(ColorTheme::AYU_DARK)
+                                            .with_syntax(syntax.clone())
+                                            .with_numlines(true)
+                                            .vscroll(true)
+                                            .show(ui, &mut tab.request.body)
+                                    })
+                                    .inner;
+                                mark_undefined_variables(ui, &output, &vars);
+                                let mut unresolved = Vec::new();
+                                let resolved = environment::substitute(
+                                    &tab.request.body,
+                                    &vars,
+                                    &mut unresolved,
+                                );
+                                // checked with variables filled in, as it will be sent
+                                if is_json && !resolved.trim().is_empty() {
+                                    if let Err(e) = json_format::validate(&resolved) {
+                                        mark_json_error(ui, &output, &e);
+                                        ui.colored_label(
+                                            Color32::from_rgb(255, 108, 117),
+                                            format!("invalid json: {}", e),
                                         );
-                                    });
+                                    }
                                 }
-                            });
-                        if let Some(i) = to_remove {
-                            self.queries.remove(i);
-                        }
-                        ui.add_space(4.0);
-                        if ui.button("+ add query parameter").clicked() {
-                            info!("adding a new query parameter");
-                            self.queries.push(("".to_owned(), "".to_owned()));
+                                if !unresolved.is_empty() {
+                                    ui.colored_label(
+                                        Color32::from_rgb(255, 108, 117),
+                                        format!("undefined variables: {}", unresolved.join(", ")),
+                                    );
+                                }
+                            }
+                            BodyMode::FormUrlEncoded => {
+                                key_value_editor(
+                                    ui,
+                                    "form_scroll",
+                                    &mut tab.request.form,
+                                    "=",
+                                    "field",
+                                    Some(&vars),
+                                );
+                            }
+                            BodyMode::Multipart => {
+                                multipart_editor(ui, &mut tab.request.multipart, &vars);
+                            }
+                            BodyMode::Binary => {
+                                binary_file_editor(ui, &mut tab.request.binary, &vars);
+                            }
+                            BodyMode::Graphql => {
+                                fetch_schema = graphql_editor(ui, tab, &mut self.show_schema);
+                            }
                         }
                     }
+                    EditorTab::Query => {
+                        ui.label("query parameters:");
+                        key_value_editor(
+                            ui,
+                            "queries_scroll",
+                            &mut tab.request.queries,
+                            "=",
+                            "query parameter",
+                            Some(&vars),
+                        );
+                    }
+                    EditorTab::Auth => {
+                        auth_editor(ui, &mut tab.request.auth, &vars, self.executor.tokens());
+                    }
+                    EditorTab::Scripts => {
+                        scripts_editor(ui, tab.id, &mut tab.request.scripts);
+                    }
+                    EditorTab::Tests => {
+                        assertions_editor(ui, &mut tab.request.assertions, &vars);
+                    }
+                    EditorTab::Settings => {
+                        ui.label("timeouts for this request (unchecked uses the global setting):");
+                        timeouts_editor(
+                            ui,
+                            "request_timeouts",
+                            &mut tab.request.timeouts,
+                            "global",
+                        );
+                    }
                 }
             });
 
@@ -444,17 +3122,207 @@ impl eframe::App for SnoozeApp {
                 .rect_filled(divider_rect, egui::Rounding::same(4.0), divider_color);
 
             // right pane: response & json search if applicable
-            ui.allocate_ui_at_rect(right_inner, |ui| {
+            ui.allocate_new_ui(egui::UiBuilder::new().max_rect(right_inner), |ui| {
                 ui.heading("response");
                 ui.separator();
 
-                if let Some(ref json) = self.parsed_json {
-                    // search controls outside scroll area
+                if let Some(resp) = &tab.last_response {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .selectable_label(tab.response_tab == ResponseTab::Body, "body")
+                            .clicked()
+                        {
+                            tab.response_tab = ResponseTab::Body;
+                        }
+                        if ui
+                            .selectable_label(
+                                tab.response_tab == ResponseTab::Headers,
+                                format!("headers ({})", resp.headers.len()),
+                            )
+                            .clicked()
+                        {
+                            tab.response_tab = ResponseTab::Headers;
+                        }
+                        console_tab_label(ui, &mut tab.response_tab, &tab.console);
+                        tests_tab_label(ui, &mut tab.response_tab, &tab.assertion_results);
+                        if !resp.version.is_empty() {
+                            ui.label(RichText::new(&resp.version).monospace().weak());
+                        }
+                        let detected = Viewer::detect(resp, tab.parsed_json.is_some());
+                        let selected = match tab.viewer {
+                            Some(viewer) => viewer.label().to_owned(),
+                            None => format!("auto ({})", detected.label()),
+                        };
+                        egui::ComboBox::from_id_salt(("response_viewer", tab.id))
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    &mut tab.viewer,
+                                    None,
+                                    format!("auto ({})", detected.label()),
+                                );
+                                for viewer in Viewer::ALL {
+                                    ui.selectable_value(
+                                        &mut tab.viewer,
+                                        Some(viewer),
+                                        viewer.label(),
+                                    );
+                                }
+                            });
+                        if ui
+                            .button("save as…")
+                            .on_hover_text("the body exactly as received")
+                            .clicked()
+                        {
+                            tab.save_status =
+                                save_response_file(&resp.suggested_filename(), &resp.body);
+                        }
+                        if let Some(json) = &tab.parsed_json {
+                            if ui.button("save pretty…").clicked() {
+                                tab.save_status = match serde_json::to_string_pretty(json) {
+                                    Ok(text) => save_response_file(
+                                        &resp.suggested_filename(),
+                                        text.as_bytes(),
+                                    ),
+                                    Err(e) => format!("failed to format json: {}", e),
+                                };
+                            }
+                        }
+                        if !tab.save_status.is_empty() {
+                            ui.label(RichText::new(&tab.save_status).weak());
+                        }
+                    });
+                    ui.separator();
+                } else if !tab.console.is_empty() || !tab.assertion_results.is_empty() {
+                    // a failed request leaves no response, only script output and failed tests
+                    ui.horizontal(|ui| {
+                        if ui
+                            .selectable_label(tab.response_tab == ResponseTab::Body, "error")
+                            .clicked()
+                        {
+                            tab.response_tab = ResponseTab::Body;
+                        }
+                        console_tab_label(ui, &mut tab.response_tab, &tab.console);
+                        tests_tab_label(ui, &mut tab.response_tab, &tab.assertion_results);
+                    });
+                    ui.separator();
+                }
+
+                let graphql_errors = match (&tab.parsed_json, tab.request.body_mode) {
+                    (Some(json), BodyMode::Graphql) => graphql::response_errors(json),
+                    _ => Vec::new(),
+                };
+                if !graphql_errors.is_empty() {
+                    egui::Frame::none()
+                        .fill(Color32::from_rgb(70, 22, 26))
+                        .rounding(egui::Rounding::same(6.0))
+                        .inner_margin(egui::Margin::same(8.0))
+                        .show(ui, |ui| {
+                            ui.set_width(ui.available_width());
+                            ui.label(
+                                RichText::new(format!("{} graphql error(s)", graphql_errors.len()))
+                                    .strong()
+                                    .color(Color32::from_rgb(255, 108, 117)),
+                            );
+                            for error in &graphql_errors {
+                                ui.label(RichText::new(error).monospace());
+                            }
+                        });
+                }
+
+                let viewer = match &tab.last_response {
+                    Some(resp) => tab
+                        .viewer
+                        .unwrap_or_else(|| Viewer::detect(resp, tab.parsed_json.is_some())),
+                    None if tab.parsed_json.is_some() => Viewer::Json,
+                    None => Viewer::Text,
+                };
+                if tab.response_tab == ResponseTab::Console && !tab.console.is_empty() {
+                    script_console(ui, &tab.console);
+                } else if tab.response_tab == ResponseTab::Tests
+                    && !tab.assertion_results.is_empty()
+                {
+                    assertion_results(ui, &tab.assertion_results);
+                } else if let (ResponseTab::Headers, Some(resp)) =
+                    (&tab.response_tab, &tab.last_response)
+                {
+                    egui::ScrollArea::vertical()
+                        .id_salt("response_headers_scroll")
+                        .show(ui, |ui| {
+                            egui::Grid::new("response_headers")
+                                .num_columns(2)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for (key, value) in resp.headers.iter() {
+                                        ui.label(RichText::new(key).monospace().strong());
+                                        ui.add(
+                                            egui::Label::new(RichText::new(value).monospace())
+                                                .wrap(),
+                                        );
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                } else if let (Viewer::Markup, Some(resp)) = (viewer, &tab.last_response) {
+                    let job = tab.view_cache.markup(ui, resp).clone();
+                    egui::ScrollArea::both()
+                        .id_salt("response_markup_scroll")
+                        .show(ui, |ui| ui.add(egui::Label::new(job).extend()));
+                } else if let (Viewer::Hex, Some(resp)) = (viewer, &tab.last_response) {
+                    let dump = tab.view_cache.hex(resp);
+                    egui::ScrollArea::both()
+                        .id_salt("response_hex_scroll")
+                        .show(ui, |ui| {
+                            ui.add(egui::Label::new(RichText::new(dump).monospace()).extend())
+                        });
+                } else if let (Viewer::Image, Some(resp)) = (viewer, &tab.last_response) {
+                    match tab.view_cache.image(ui.ctx(), tab.id, resp) {
+                        Ok(texture) => {
+                            let [width, height] = texture.size();
+                            ui.label(RichText::new(format!("{} × {} px", width, height)).weak());
+                            egui::ScrollArea::both()
+                                .id_salt("response_image_scroll")
+                                .show(ui, |ui| {
+                                    ui.add(egui::Image::new(texture).shrink_to_fit());
+                                });
+                        }
+                        Err(e) => {
+                            ui.colored_label(
+                                Color32::from_rgb(255, 108, 117),
+                                format!("could not decode image: {}", e),
+                            );
+                        }
+                    }
+                } else if let (Viewer::Json, Some(json)) = (viewer, &tab.parsed_json) {
+                    // filter and search controls outside scroll area
+                    let filter_response = ui
+                        .horizontal(|ui| {
+                            ui.label("filter:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut tab.request.json_filter)
+                                    .hint_text("$.items[*].id or .items | map(.id)")
+                                    .code_editor()
+                                    .desired_width(f32::INFINITY),
+                            )
+                        })
+                        .inner;
+                    let expr = tab.request.json_filter.trim();
+                    let (json, filtered) = if expr.is_empty() {
+                        (json, false)
+                    } else {
+                        match tab.view_cache.filtered(expr, json) {
+                            Ok(filtered) => (filtered, true),
+                            Err(e) => {
+                                ui.colored_label(Color32::from_rgb(255, 108, 117), e);
+                                (json, false)
+                            }
+                        }
+                    };
+                    let mut filter_to = None;
                     ui.label("search:");
                     let (text_edit_response, clear_button_response) = ui
                         .horizontal(|ui| {
-                            let text_edit_response =
-                                ui.text_edit_singleline(&mut self.search_input);
+                            let text_edit_response = ui.text_edit_singleline(&mut tab.search_input);
                             let clear_button_response = ui.button("clear");
                             (text_edit_response, clear_button_response)
                         })
@@ -464,38 +3332,66 @@ impl eframe::App for SnoozeApp {
                     egui::ScrollArea::vertical()
                         .max_height(ui.available_height())
                         .show(ui, |ui| {
-                            let tree_response = JsonTree::new("json_tree", json)
-                                .default_expand(if self.search_input.is_empty() {
+                            let tree_response = JsonTree::new(("json_tree", tab.id), json)
+                                .default_expand(if tab.search_input.is_empty() {
                                     DefaultExpand::All
                                 } else {
-                                    DefaultExpand::SearchResults(&self.search_input)
+                                    DefaultExpand::SearchResults(&tab.search_input)
+                                })
+                                .on_render(|ui, context| {
+                                    let response = context.render_default(ui);
+                                    response.context_menu(|ui| {
+                                        json_node_menu(ui, &context, filtered, &mut filter_to)
+                                    });
                                 })
                                 .show(ui);
-                            if text_edit_response.changed() {
+                            if text_edit_response.changed() || filter_response.changed() {
                                 tree_response.reset_expanded(ui);
                             }
                             if clear_button_response.clicked() {
-                                self.search_input.clear();
+                                tab.search_input.clear();
                                 tree_response.reset_expanded(ui);
                             }
                             if ui.button("reset expanded").clicked() {
                                 tree_response.reset_expanded(ui);
                             }
                         });
+                    if let Some(filter) = filter_to {
+                        tab.request.json_filter = filter;
+                    }
                 } else {
                     egui::ScrollArea::vertical().show(ui, |ui| {
                         ui.add(egui::Label::new(
-                            egui::RichText::new(&self.response_text).monospace(),
+                            egui::RichText::new(&tab.response_text).monospace(),
                         ));
                     });
                 }
             });
+
+            if send {
+                self.send_request();
+            }
+            if cancel {
+                self.cancel_request();
+            }
+            if fetch_schema {
+                self.fetch_schema();
+            }
+            if let Some(language) = copy_as {
+                info!("copying request as {}", language.label());
+                ui.ctx().copy_text(self.snippet(language));
+            }
         });
     }
 }
 
 fn main() -> eframe::Result<()> {
     env_logger::init();
+    // `Snooze run …` runs collections headless instead of opening the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "run") {
+        std::process::exit(cli::main(&args[1..]));
+    }
     let nat_options = eframe::NativeOptions::default();
     eframe::run_native(
         "snooze",
@@ -0,0 +1,33 @@
+use egui_code_editor::Syntax;
+use std::collections::BTreeSet;
+
+pub fn rhai_syntax() -> Syntax {
+    Syntax {
+        language: "rhai",
+        case_sensitive: true,
+        comment: "//",
+        comment_multiline: ["/*", "*/"],
+        hyperlinks: BTreeSet::new(),
+        keywords: BTreeSet::from([
+            "let", "const", "if", "else", "switch", "while", "loop", "for", "in", "do", "until",
+            "break", "continue", "return", "throw", "try", "catch", "fn", "private", "import",
+            "export", "as", "true", "false",
+        ]),
+        types: BTreeSet::from(["request", "response", "this", "global"]),
+        // the functions snooze adds
+        special: BTreeSet::from([
+            "variable",
+            "set_variable",
+            "print",
+            "debug",
+            "timestamp",
+            "timestamp_ms",
+            "uuid",
+            "sha256",
+            "hmac_sha256",
+            "base64_encode",
+            "base64_decode",
+            "parse_json",
+        ]),
+    }
+}
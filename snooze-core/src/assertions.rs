@@ -0,0 +1,446 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::json_filter;
+use crate::response::{HttpResponse, RequestError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum AssertionKind {
+    #[default]
+    StatusEquals,
+    StatusInRange,
+    HeaderPresent,
+    HeaderMatches,
+    JsonEquals,
+    JsonExists,
+    JsonType,
+    BodyContains,
+    BodyMatches,
+    DurationUnder,
+}
+
+impl AssertionKind {
+    pub const ALL: [AssertionKind; 10] = [
+        AssertionKind::StatusEquals,
+        AssertionKind::StatusInRange,
+        AssertionKind::HeaderPresent,
+        AssertionKind::HeaderMatches,
+        AssertionKind::JsonEquals,
+        AssertionKind::JsonExists,
+        AssertionKind::JsonType,
+        AssertionKind::BodyContains,
+        AssertionKind::BodyMatches,
+        AssertionKind::DurationUnder,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AssertionKind::StatusEquals => "status equals",
+            AssertionKind::StatusInRange => "status in range",
+            AssertionKind::HeaderPresent => "header present",
+            AssertionKind::HeaderMatches => "header matches",
+            AssertionKind::JsonEquals => "json value equals",
+            AssertionKind::JsonExists => "json value exists",
+            AssertionKind::JsonType => "json value type",
+            AssertionKind::BodyContains => "body contains",
+            AssertionKind::BodyMatches => "body matches",
+            AssertionKind::DurationUnder => "duration under",
+        }
+    }
+
+    /// what `target` holds, or `None` for kinds that don't use it
+    pub fn target_hint(&self) -> Option<&'static str> {
+        match self {
+            AssertionKind::HeaderPresent | AssertionKind::HeaderMatches => Some("header name"),
+            AssertionKind::JsonEquals | AssertionKind::JsonExists | AssertionKind::JsonType => {
+                Some("$.path or .path")
+            }
+            _ => None,
+        }
+    }
+
+    /// what `expected` holds, or `None` for kinds that don't use it
+    pub fn expected_hint(&self) -> Option<&'static str> {
+        match self {
+            AssertionKind::StatusEquals => Some("200"),
+            AssertionKind::StatusInRange => Some("200-299 or 2xx"),
+            AssertionKind::HeaderMatches | AssertionKind::BodyMatches => Some("regex"),
+            AssertionKind::JsonEquals => Some("json, or text for a string"),
+            AssertionKind::JsonType => Some("string, number, boolean, array, object or null"),
+            AssertionKind::BodyContains => Some("text"),
+            AssertionKind::DurationUnder => Some("milliseconds"),
+            AssertionKind::HeaderPresent | AssertionKind::JsonExists => None,
+        }
+    }
+}
+
+/// one check run against every response to its request. both text fields
+/// are kept when switching kinds so nothing typed is lost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Assertion {
+    pub enabled: bool,
+    pub kind: AssertionKind,
+    /// the header name or json path being checked
+    pub target: String,
+    pub expected: String,
+}
+
+impl Default for Assertion {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            kind: AssertionKind::default(),
+            target: String::new(),
+            expected: String::new(),
+        }
+    }
+}
+
+impl Assertion {
+    /// applies `f` to every text field, e.g. to substitute variables
+    pub fn map_fields(&self, mut f: impl FnMut(&str) -> String) -> Assertion {
+        Assertion {
+            enabled: self.enabled,
+            kind: self.kind,
+            target: f(&self.target),
+            expected: f(&self.expected),
+        }
+    }
+
+    /// the check in words, e.g. `status in 200-299`
+    pub fn description(&self) -> String {
+        let (target, expected) = (self.target.trim(), self.expected.trim());
+        match self.kind {
+            AssertionKind::StatusEquals => format!("status is {}", expected),
+            AssertionKind::StatusInRange => format!("status in {}", expected),
+            AssertionKind::HeaderPresent => format!("header {} is present", target),
+            AssertionKind::HeaderMatches => format!("header {} matches /{}/", target, expected),
+            AssertionKind::JsonEquals => format!("{} equals {}", target, expected),
+            AssertionKind::JsonExists => format!("{} exists", target),
+            AssertionKind::JsonType => format!("{} is {}", target, expected),
+            AssertionKind::BodyContains => format!("body contains {:?}", expected),
+            AssertionKind::BodyMatches => format!("body matches /{}/", expected),
+            AssertionKind::DurationUnder => format!("duration under {} ms", expected),
+        }
+    }
+
+    /// `Ok` with what was found when the check holds, `Err` with why not
+    fn check(
+        &self,
+        response: &HttpResponse,
+        json: Option<&Value>,
+        duration: Duration,
+    ) -> Result<String, String> {
+        let (target, expected) = (self.target.trim(), self.expected.trim());
+        match self.kind {
+            AssertionKind::StatusEquals => {
+                let want: u16 = expected
+                    .parse()
+                    .map_err(|_| format!("{:?} isn't a status code", expected))?;
+                outcome(response.status == want, format!("got {}", response.status))
+            }
+            AssertionKind::StatusInRange => {
+                let (low, high) = parse_status_range(expected)?;
+                outcome(
+                    (low..=high).contains(&response.status),
+                    format!("got {}", response.status),
+                )
+            }
+            AssertionKind::HeaderPresent => {
+                let found = header_values(response, target).next().is_some();
+                outcome(found, if found { "present" } else { "missing" }.to_owned())
+            }
+            AssertionKind::HeaderMatches => {
+                let re = regex(expected)?;
+                let values: Vec<&str> = header_values(response, target).collect();
+                if values.is_empty() {
+                    return Err("missing".to_owned());
+                }
+                outcome(
+                    values.iter().any(|v| re.is_match(v)),
+                    format!("got {:?}", values.join(", ")),
+                )
+            }
+            AssertionKind::JsonEquals | AssertionKind::JsonExists | AssertionKind::JsonType => {
+                let json = json.ok_or_else(|| "the body isn't json".to_owned())?;
+                let found = json_value(target, json)?;
+                match (self.kind, found) {
+                    (AssertionKind::JsonExists, found) => {
+                        outcome(found.is_some(), found.map_or("missing".to_owned(), short))
+                    }
+                    (_, None) => Err("missing".to_owned()),
+                    (AssertionKind::JsonType, Some(value)) => outcome(
+                        type_name(&value) == expected.to_ascii_lowercase(),
+                        format!("got {}", type_name(&value)),
+                    ),
+                    (_, Some(value)) => {
+                        // text that isn't json is compared as a string
+                        let want = serde_json::from_str(expected)
+                            .unwrap_or_else(|_| Value::String(expected.to_owned()));
+                        outcome(json_equal(&value, &want), format!("got {}", short(value)))
+                    }
+                }
+            }
+            AssertionKind::BodyContains => {
+                let found = response.text().contains(expected);
+                outcome(found, if found { "found" } else { "not found" }.to_owned())
+            }
+            AssertionKind::BodyMatches => {
+                let found = regex(expected)?.is_match(&response.text());
+                outcome(found, if found { "matched" } else { "no match" }.to_owned())
+            }
+            AssertionKind::DurationUnder => {
+                let limit: u64 = expected
+                    .parse()
+                    .map_err(|_| format!("{:?} isn't a number of milliseconds", expected))?;
+                let took = duration.as_millis() as u64;
+                outcome(took < limit, format!("took {} ms", took))
+            }
+        }
+    }
+}
+
+/// how one assertion went for one response, as shown and kept in history
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssertionResult {
+    pub description: String,
+    pub passed: bool,
+    /// what was actually found, or why the check couldn't run
+    pub message: String,
+}
+
+/// runs the enabled `assertions` against `response`; a failed request fails them all
+pub fn evaluate(
+    assertions: &[Assertion],
+    response: &Result<HttpResponse, RequestError>,
+    duration: Duration,
+) -> Vec<AssertionResult> {
+    let json = response
+        .as_ref()
+        .ok()
+        .and_then(|r| serde_json::from_slice::<Value>(&r.body).ok());
+    assertions
+        .iter()
+        .filter(|a| a.enabled)
+        .map(|assertion| {
+            let result = match response {
+                Ok(response) => assertion.check(response, json.as_ref(), duration),
+                Err(e) => Err(format!("no response: {}", e)),
+            };
+            let (passed, message) = match result {
+                Ok(message) => (true, message),
+                Err(message) => (false, message),
+            };
+            AssertionResult {
+                description: assertion.description(),
+                passed,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// how many of `results` passed
+pub fn passed(results: &[AssertionResult]) -> usize {
+    results.iter().filter(|r| r.passed).count()
+}
+
+fn outcome(passed: bool, message: String) -> Result<String, String> {
+    if passed {
+        Ok(message)
+    } else {
+        Err(message)
+    }
+}
+
+/// `200-299`, `2xx` or a single code
+fn parse_status_range(range: &str) -> Result<(u16, u16), String> {
+    let invalid = || format!("{:?} isn't a status range like 200-299 or 2xx", range);
+    if let Some(class) = range
+        .strip_suffix("xx")
+        .or_else(|| range.strip_suffix("XX"))
+    {
+        return match class.parse::<u16>() {
+            Ok(class @ 1..=5) => Ok((class * 100, class * 100 + 99)),
+            _ => Err(invalid()),
+        };
+    }
+    let (low, high) = range.split_once('-').unwrap_or((range, range));
+    let low = low.trim().parse().map_err(|_| invalid())?;
+    let high = high.trim().parse().map_err(|_| invalid())?;
+    Ok((low, high))
+}
+
+fn header_values<'a>(response: &'a HttpResponse, name: &'a str) -> impl Iterator<Item = &'a str> {
+    response
+        .headers
+        .iter()
+        .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+fn regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("invalid regex: {}", e))
+}
+
+/// the value at `path`. jsonpath matching exactly one value gives that value
+/// and several give them as an array; for jq paths a null counts as missing
+fn json_value(path: &str, json: &Value) -> Result<Option<Value>, String> {
+    let value = json_filter::apply(path, json)?;
+    if !path.starts_with('$') {
+        return Ok((!value.is_null()).then_some(value));
+    }
+    match value {
+        Value::Array(mut matches) if matches.len() == 1 => Ok(matches.pop()),
+        Value::Array(matches) if matches.is_empty() => Ok(None),
+        value => Ok(Some(value)),
+    }
+}
+
+/// like `==` but with `1` equal to `1.0`
+fn json_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| json_equal(x, y))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, v)| y.get(k).is_some_and(|other| json_equal(v, other)))
+        }
+        _ => a == b,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// a value for a result message, cut short if it's long
+fn short(value: Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(80) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assertion(kind: AssertionKind, target: &str, expected: &str) -> Assertion {
+        Assertion {
+            kind,
+            target: target.to_owned(),
+            expected: expected.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn response() -> Result<HttpResponse, RequestError> {
+        Ok(HttpResponse {
+            status: 201,
+            headers: vec![("Content-Type".to_owned(), "application/json".to_owned())],
+            body: br#"{"id": 7, "name": "ann", "tags": ["a", "b"], "price": 2.0}"#.to_vec(),
+            ..Default::default()
+        })
+    }
+
+    fn run(assertion: Assertion) -> AssertionResult {
+        evaluate(&[assertion], &response(), Duration::from_millis(120)).remove(0)
+    }
+
+    #[test]
+    fn status_and_headers() {
+        assert!(run(assertion(AssertionKind::StatusEquals, "", "201")).passed);
+        assert!(run(assertion(AssertionKind::StatusInRange, "", "2xx")).passed);
+        assert!(run(assertion(AssertionKind::StatusInRange, "", "200 - 201")).passed);
+        let failed = run(assertion(AssertionKind::StatusEquals, "", "200"));
+        assert_eq!((failed.passed, failed.message.as_str()), (false, "got 201"));
+        assert!(run(assertion(AssertionKind::HeaderPresent, "content-type", "")).passed);
+        assert!(
+            run(assertion(
+                AssertionKind::HeaderMatches,
+                "Content-Type",
+                "^application/.*json$"
+            ))
+            .passed
+        );
+        assert_eq!(
+            run(assertion(AssertionKind::HeaderMatches, "ETag", ".")).message,
+            "missing"
+        );
+    }
+
+    #[test]
+    fn status_classes_outside_1xx_to_5xx_are_invalid() {
+        assert_eq!(parse_status_range("5XX"), Ok((500, 599)));
+        for range in ["700xx", "0xx", "6xx", "xx", "-1xx"] {
+            assert_eq!(
+                parse_status_range(range),
+                Err(format!(
+                    "{:?} isn't a status range like 200-299 or 2xx",
+                    range
+                ))
+            );
+        }
+        let failed = run(assertion(AssertionKind::StatusInRange, "", "700xx"));
+        assert!(!failed.passed);
+    }
+
+    #[test]
+    fn json_paths() {
+        assert!(run(assertion(AssertionKind::JsonEquals, "$.id", "7")).passed);
+        assert!(run(assertion(AssertionKind::JsonEquals, ".name", "ann")).passed);
+        assert!(run(assertion(AssertionKind::JsonEquals, "$.price", "2")).passed);
+        assert!(
+            run(assertion(
+                AssertionKind::JsonEquals,
+                "$.tags",
+                r#"["a","b"]"#
+            ))
+            .passed
+        );
+        assert!(run(assertion(AssertionKind::JsonExists, "$.tags[1]", "")).passed);
+        assert!(!run(assertion(AssertionKind::JsonExists, ".missing", "")).passed);
+        assert!(run(assertion(AssertionKind::JsonType, "$.tags", "array")).passed);
+        let failed = run(assertion(AssertionKind::JsonType, "$.id", "string"));
+        assert_eq!(failed.message, "got number");
+    }
+
+    #[test]
+    fn body_duration_and_failures() {
+        assert!(run(assertion(AssertionKind::BodyContains, "", "\"ann\"")).passed);
+        assert!(run(assertion(AssertionKind::BodyMatches, "", r#""id":\s*\d+"#)).passed);
+        assert!(run(assertion(AssertionKind::DurationUnder, "", "500")).passed);
+        assert!(!run(assertion(AssertionKind::DurationUnder, "", "100")).passed);
+        assert!(run(assertion(AssertionKind::BodyMatches, "", "("))
+            .message
+            .starts_with("invalid regex"));
+        let disabled = Assertion {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(evaluate(&[disabled], &response(), Duration::ZERO).is_empty());
+        let failed = evaluate(
+            &[assertion(AssertionKind::StatusEquals, "", "200")],
+            &Err(RequestError::Failed("refused".to_owned())),
+            Duration::ZERO,
+        );
+        assert!(!failed[0].passed);
+        assert!(failed[0].message.starts_with("no response"));
+    }
+}
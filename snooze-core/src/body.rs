@@ -0,0 +1,500 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::sync::Notify;
+
+use crate::graphql::GraphqlBody;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum BodyMode {
+    #[default]
+    Raw,
+    FormUrlEncoded,
+    Multipart,
+    Binary,
+    Graphql,
+}
+
+impl BodyMode {
+    pub const ALL: [BodyMode; 5] = [
+        BodyMode::Raw,
+        BodyMode::FormUrlEncoded,
+        BodyMode::Multipart,
+        BodyMode::Binary,
+        BodyMode::Graphql,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BodyMode::Raw => "raw",
+            BodyMode::FormUrlEncoded => "form urlencoded",
+            BodyMode::Multipart => "multipart form",
+            BodyMode::Binary => "binary file",
+            BodyMode::Graphql => "graphql",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum PartKind {
+    #[default]
+    Text,
+    File,
+}
+
+/// one field of a multipart body
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormPart {
+    pub name: String,
+    pub kind: PartKind,
+    /// the text to send, or the path of the file to read it from
+    pub value: String,
+    /// left empty, file parts get a type guessed from their extension and
+    /// text parts go without
+    pub content_type: String,
+}
+
+impl FormPart {
+    /// applies `f` to every text field, e.g. to substitute variables
+    pub fn map_fields(&self, mut f: impl FnMut(&str) -> String) -> FormPart {
+        FormPart {
+            name: f(&self.name),
+            kind: self.kind,
+            value: f(&self.value),
+            content_type: f(&self.content_type),
+        }
+    }
+
+    /// the file name sent for file parts
+    pub fn file_name(&self) -> String {
+        Path::new(&self.value)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.value.clone())
+    }
+
+    pub fn content_type(&self) -> Option<String> {
+        match (self.content_type.trim(), self.kind) {
+            ("", PartKind::Text) => None,
+            ("", PartKind::File) => Some(
+                mime_guess::from_path(&self.value)
+                    .first_or_octet_stream()
+                    .to_string(),
+            ),
+            (content_type, _) => Some(content_type.to_owned()),
+        }
+    }
+}
+
+/// a file sent as the whole body, streamed from disk when the request goes out
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinaryFile {
+    pub path: String,
+    /// guessed from the extension when empty
+    pub content_type: String,
+}
+
+impl BinaryFile {
+    /// applies `f` to every text field, e.g. to substitute variables
+    pub fn map_fields(&self, mut f: impl FnMut(&str) -> String) -> BinaryFile {
+        BinaryFile {
+            path: f(&self.path),
+            content_type: f(&self.content_type),
+        }
+    }
+
+    pub fn guessed_content_type(&self) -> String {
+        mime_guess::from_path(&self.path)
+            .first_or_octet_stream()
+            .to_string()
+    }
+
+    pub fn content_type(&self) -> String {
+        match self.content_type.trim() {
+            "" => self.guessed_content_type(),
+            content_type => content_type.to_owned(),
+        }
+    }
+}
+
+/// the body as it will be sent, with variables substituted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RequestBody {
+    Raw(String),
+    Form(Vec<(String, String)>),
+    Multipart(Vec<FormPart>),
+    File(BinaryFile),
+    Graphql(GraphqlBody),
+}
+
+impl RequestBody {
+    /// applies `f` to every text field, e.g. to substitute variables
+    pub fn map_fields(&self, mut f: impl FnMut(&str) -> String) -> RequestBody {
+        match self {
+            RequestBody::Raw(text) => RequestBody::Raw(f(text)),
+            RequestBody::Form(pairs) => {
+                RequestBody::Form(pairs.iter().map(|(k, v)| (f(k), f(v))).collect())
+            }
+            RequestBody::Multipart(parts) => {
+                RequestBody::Multipart(parts.iter().map(|p| p.map_fields(&mut f)).collect())
+            }
+            RequestBody::File(file) => RequestBody::File(file.map_fields(f)),
+            RequestBody::Graphql(graphql) => RequestBody::Graphql(graphql.map_fields(f)),
+        }
+    }
+
+    /// the editor mode this body is written in
+    pub fn mode(&self) -> BodyMode {
+        match self {
+            RequestBody::Raw(_) => BodyMode::Raw,
+            RequestBody::Form(_) => BodyMode::FormUrlEncoded,
+            RequestBody::Multipart(_) => BodyMode::Multipart,
+            RequestBody::File(_) => BodyMode::Binary,
+            RequestBody::Graphql(_) => BodyMode::Graphql,
+        }
+    }
+
+    /// the body as text, for history and snippets; file parts show as `name=@path`
+    pub fn summary(&self) -> String {
+        match self {
+            RequestBody::Raw(text) => text.clone(),
+            RequestBody::Form(pairs) => form_urlencode(pairs),
+            RequestBody::Multipart(parts) => parts
+                .iter()
+                .filter(|p| !p.name.is_empty())
+                .map(|p| match p.kind {
+                    PartKind::Text => format!("{}={}", p.name, p.value),
+                    PartKind::File => format!("{}=@{}", p.name, p.value),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            RequestBody::File(file) => format!("@{}", file.path),
+            RequestBody::Graphql(graphql) => {
+                graphql.to_json().unwrap_or_else(|_| graphql.query.clone())
+            }
+        }
+    }
+}
+
+/// how much of a streamed body has been handed to the connection, shared
+/// between the request task and the ui
+#[derive(Debug, Default)]
+pub struct UploadProgress {
+    sent: AtomicU64,
+    total: AtomicU64,
+    /// whether the whole body has been handed over, for the read timeout
+    finished: AtomicBool,
+    notify: Notify,
+}
+
+impl UploadProgress {
+    fn start(&self, total: u64) {
+        self.sent.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+        self.finished.store(false, Ordering::SeqCst);
+    }
+
+    /// marks the body as handed over, e.g. right away for requests without one
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    /// resolves once the whole body has been handed to the connection
+    pub async fn finished(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.finished.load(Ordering::SeqCst) {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// bytes sent and the total, once a streamed upload has started
+    pub fn get(&self) -> Option<(u64, u64)> {
+        match self.total.load(Ordering::Relaxed) {
+            0 => None,
+            total => Some((self.sent.load(Ordering::Relaxed).min(total), total)),
+        }
+    }
+}
+
+/// opens `path` for streaming as a body, returning its length and a reader
+/// that counts what is read into `progress`
+pub async fn open_tracked(
+    path: &str,
+    progress: &Arc<UploadProgress>,
+) -> Result<(u64, impl AsyncRead + Send + Unpin + 'static), String> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let len = file
+        .metadata()
+        .await
+        .map_err(|e| format!("failed to read {}: {}", path, e))?
+        .len();
+    progress.start(len);
+    Ok((
+        len,
+        Tracked {
+            inner: file,
+            remaining: len,
+            progress: progress.clone(),
+        },
+    ))
+}
+
+/// a reader over an in-memory body that tells `progress` when it's been read
+/// through, without showing as an upload
+pub fn track_bytes(
+    bytes: Vec<u8>,
+    progress: &Arc<UploadProgress>,
+) -> impl AsyncRead + Send + Unpin + 'static {
+    progress.finished.store(bytes.is_empty(), Ordering::SeqCst);
+    Tracked {
+        remaining: bytes.len() as u64,
+        inner: io::Cursor::new(bytes),
+        progress: progress.clone(),
+    }
+}
+
+struct Tracked<R> {
+    inner: R,
+    /// bytes left to read, the upload is finished at zero
+    remaining: u64,
+    progress: Arc<UploadProgress>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Tracked<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            let read = (buf.filled().len() - before) as u64;
+            self.progress.sent.fetch_add(read, Ordering::Relaxed);
+            self.remaining = self.remaining.saturating_sub(read);
+            if self.remaining == 0 || read == 0 {
+                self.progress.finish();
+            }
+        }
+        poll
+    }
+}
+
+/// `pairs` as `application/x-www-form-urlencoded`, skipping rows without a name
+pub fn form_urlencode(pairs: &[(String, String)]) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(pairs.iter().filter(|(k, _)| !k.is_empty()))
+        .finish()
+}
+
+/// `parts` as a `multipart/form-data` body, reading file parts from disk,
+/// along with the content type naming its boundary
+pub async fn encode_multipart(parts: &[FormPart]) -> Result<(Vec<u8>, String), String> {
+    let boundary: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(24)
+        .map(char::from)
+        .collect();
+    let boundary = format!("----snooze{}", boundary);
+    let mut body = Vec::new();
+    for part in parts.iter().filter(|p| !p.name.is_empty()) {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        let mut disposition = format!("form-data; name=\"{}\"", escape_quoted(&part.name));
+        let data = match part.kind {
+            PartKind::Text => part.value.clone().into_bytes(),
+            PartKind::File => {
+                disposition.push_str(&format!(
+                    "; filename=\"{}\"",
+                    escape_quoted(&part.file_name())
+                ));
+                tokio::fs::read(&part.value)
+                    .await
+                    .map_err(|e| format!("failed to read {}: {}", part.value, e))?
+            }
+        };
+        body.extend_from_slice(format!("Content-Disposition: {}\r\n", disposition).as_bytes());
+        if let Some(content_type) = part.content_type() {
+            body.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+        }
+        body.extend_from_slice(b"\r\n");
+        body.extend_from_slice(&data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    Ok((body, format!("multipart/form-data; boundary={}", boundary)))
+}
+
+/// quotes and line breaks can't appear raw in a disposition parameter
+fn escape_quoted(s: &str) -> String {
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(name: &str, value: &str) -> FormPart {
+        FormPart {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// writes `contents` to `name` in a directory of its own for `test`
+    fn temp_file(test: &str, name: &str, contents: &[u8]) -> String {
+        let dir = std::env::temp_dir().join(format!("snooze-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn percent_encodes_form_fields_and_skips_unnamed_rows() {
+        let pairs = vec![
+            ("q".to_owned(), "a b&c=d".to_owned()),
+            (String::new(), "skipped".to_owned()),
+            ("name".to_owned(), "zoë".to_owned()),
+        ];
+        assert_eq!(form_urlencode(&pairs), "q=a+b%26c%3Dd&name=zo%C3%AB");
+        assert_eq!(form_urlencode(&[]), "");
+    }
+
+    #[test]
+    fn escapes_quotes_and_line_breaks_in_disposition_values() {
+        assert_eq!(escape_quoted("a \"b\"\r\nc"), "a %22b%22%0D%0Ac");
+    }
+
+    #[tokio::test]
+    async fn encodes_text_and_file_parts_between_boundaries() {
+        let path = temp_file("multipart", "say \"hi\".txt", b"hello\nfile");
+        let parts = vec![
+            text("name", "ann"),
+            text("", "skipped"),
+            FormPart {
+                content_type: "application/json".to_owned(),
+                ..text("meta", "{}")
+            },
+            FormPart {
+                name: "up\"load".to_owned(),
+                kind: PartKind::File,
+                value: path.clone(),
+                content_type: String::new(),
+            },
+        ];
+        let (body, content_type) = encode_multipart(&parts).await.unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        assert!(boundary.starts_with("----snooze"), "{}", boundary);
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            format!(
+                "--{b}\r\n\
+                 Content-Disposition: form-data; name=\"name\"\r\n\
+                 \r\n\
+                 ann\r\n\
+                 --{b}\r\n\
+                 Content-Disposition: form-data; name=\"meta\"\r\n\
+                 Content-Type: application/json\r\n\
+                 \r\n\
+                 {{}}\r\n\
+                 --{b}\r\n\
+                 Content-Disposition: form-data; name=\"up%22load\"; filename=\"say %22hi%22.txt\"\r\n\
+                 Content-Type: text/plain\r\n\
+                 \r\n\
+                 hello\nfile\r\n\
+                 --{b}--\r\n",
+                b = boundary
+            )
+        );
+        std::fs::remove_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+
+        // a fresh boundary every time
+        let (_, again) = encode_multipart(&parts[..1]).await.unwrap();
+        assert_ne!(again, content_type);
+    }
+
+    #[tokio::test]
+    async fn tracks_file_uploads_until_they_are_read_through() {
+        use tokio::io::AsyncReadExt;
+
+        let path = temp_file("tracked", "upload.bin", &[7; 20_000]);
+        let progress = Arc::new(UploadProgress::default());
+        let (len, mut reader) = open_tracked(&path, &progress).await.unwrap();
+        assert_eq!(len, 20_000);
+        assert_eq!(progress.get(), Some((0, 20_000)));
+        let mut first = [0; 1000];
+        reader.read_exact(&mut first).await.unwrap();
+        assert_eq!(progress.get(), Some((1000, 20_000)));
+        assert!(!progress.finished.load(Ordering::SeqCst));
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest.len(), 19_000);
+        assert_eq!(progress.get(), Some((20_000, 20_000)));
+        progress.finished().await;
+        std::fs::remove_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+
+        let error = match open_tracked("/no/such/upload.bin", &progress).await {
+            Ok(_) => panic!("opened a missing file"),
+            Err(e) => e,
+        };
+        assert!(
+            error.starts_with("failed to read /no/such/upload.bin"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn in_memory_bodies_finish_without_showing_as_uploads() {
+        use tokio::io::AsyncReadExt;
+
+        let progress = Arc::new(UploadProgress::default());
+        let mut reader = track_bytes(b"hello".to_vec(), &progress);
+        assert!(!progress.finished.load(Ordering::SeqCst));
+        let mut read = String::new();
+        reader.read_to_string(&mut read).await.unwrap();
+        assert_eq!(read, "hello");
+        progress.finished().await;
+        assert_eq!(progress.get(), None);
+
+        // nothing to read, so it's finished from the start
+        let _reader = track_bytes(Vec::new(), &progress);
+        progress.finished().await;
+    }
+
+    #[tokio::test]
+    async fn fails_cleanly_on_a_missing_file_part() {
+        let parts = vec![FormPart {
+            name: "upload".to_owned(),
+            kind: PartKind::File,
+            value: "/no/such/file.bin".to_owned(),
+            content_type: String::new(),
+        }];
+        let error = encode_multipart(&parts).await.unwrap_err();
+        assert!(
+            error.starts_with("failed to read /no/such/file.bin"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,604 @@
+use url::Url;
+
+use crate::body::{FormPart, PartKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Curl,
+    RustBlocking,
+    RustAsync,
+    Python,
+    JavaScript,
+}
+
+impl Language {
+    pub const ALL: [Language; 5] = [
+        Language::Curl,
+        Language::RustBlocking,
+        Language::RustAsync,
+        Language::Python,
+        Language::JavaScript,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Language::Curl => "curl",
+            Language::RustBlocking => "rust reqwest (blocking)",
+            Language::RustAsync => "rust reqwest (async)",
+            Language::Python => "python requests",
+            Language::JavaScript => "javascript fetch",
+        }
+    }
+}
+
+/// the request to translate; rows with an empty key are skipped and an empty
+/// body is left out entirely
+#[derive(Default)]
+pub struct SnippetRequest<'a> {
+    pub method: &'a str,
+    pub url: &'a str,
+    pub queries: &'a [(String, String)],
+    pub headers: &'a [(String, String)],
+    pub body: &'a str,
+    /// multipart fields, sent instead of `body` when there are any
+    pub multipart: &'a [FormPart],
+    /// path of a file sent as the whole body, instead of `body`
+    pub file: Option<&'a str>,
+}
+
+impl SnippetRequest<'_> {
+    fn queries(&self) -> impl Iterator<Item = &(String, String)> {
+        self.queries.iter().filter(|(k, _)| !k.is_empty())
+    }
+
+    fn headers(&self) -> impl Iterator<Item = &(String, String)> {
+        self.headers.iter().filter(|(k, _)| !k.is_empty())
+    }
+
+    fn parts(&self) -> impl Iterator<Item = &FormPart> {
+        self.multipart.iter().filter(|p| !p.name.is_empty())
+    }
+
+    /// the url with query parameters appended, for targets without a params api
+    fn full_url(&self) -> String {
+        let pairs: Vec<_> = self.queries().collect();
+        match Url::parse(self.url) {
+            Ok(mut url) if !pairs.is_empty() => {
+                url.query_pairs_mut()
+                    .extend_pairs(pairs.iter().map(|(k, v)| (k, v)));
+                url.into()
+            }
+            _ => self.url.to_owned(),
+        }
+    }
+}
+
+/// equivalent code for `request` in another tool or language
+pub fn generate(language: Language, request: &SnippetRequest) -> String {
+    match language {
+        Language::Curl => curl(request),
+        Language::RustBlocking => rust(request, false),
+        Language::RustAsync => rust(request, true),
+        Language::Python => python(request),
+        Language::JavaScript => javascript(request),
+    }
+}
+
+fn curl(request: &SnippetRequest) -> String {
+    let mut parts = vec![match request.method {
+        "GET" => "curl".to_owned(),
+        "HEAD" => "curl --head".to_owned(),
+        method => format!("curl -X {}", method),
+    }];
+    parts[0].push(' ');
+    parts[0].push_str(&shell_quote(&request.full_url()));
+    for (k, v) in request.headers() {
+        parts.push(format!("-H {}", shell_quote(&format!("{}: {}", k, v))));
+    }
+    for part in request.parts() {
+        let mut field = match part.kind {
+            PartKind::Text => format!("{}={}", part.name, part.value),
+            PartKind::File => format!("{}=@{}", part.name, part.value),
+        };
+        if !part.content_type.is_empty() {
+            field.push_str(&format!(";type={}", part.content_type));
+        }
+        parts.push(format!("-F {}", shell_quote(&field)));
+    }
+    if let Some(path) = request.file {
+        parts.push(format!(
+            "--data-binary {}",
+            shell_quote(&format!("@{}", path))
+        ));
+    } else if !request.body.is_empty() && request.multipart.is_empty() {
+        parts.push(format!("--data-raw {}", shell_quote(request.body)));
+    }
+    parts.join(" \\\n  ")
+}
+
+fn rust(request: &SnippetRequest, is_async: bool) -> String {
+    let (client, wait) = if is_async {
+        ("reqwest::Client::new()", ".await")
+    } else {
+        ("reqwest::blocking::Client::new()", "")
+    };
+    let mut out = String::new();
+    if is_async {
+        out.push_str(
+            "#[tokio::main]\nasync fn main() -> Result<(), Box<dyn std::error::Error>> {\n",
+        );
+    } else {
+        out.push_str("fn main() -> Result<(), Box<dyn std::error::Error>> {\n");
+    }
+    out.push_str(&format!("    let client = {};\n", client));
+    let has_form = request.parts().next().is_some();
+    if has_form {
+        let module = if is_async {
+            "reqwest::multipart"
+        } else {
+            "reqwest::blocking::multipart"
+        };
+        out.push_str(&format!("    let form = {}::Form::new()\n", module));
+        for part in request.parts() {
+            let mut value = match part.kind {
+                PartKind::Text => format!("{}::Part::text({})", module, rust_string(&part.value)),
+                PartKind::File => format!(
+                    "{}::Part::bytes(std::fs::read({})?).file_name({})",
+                    module,
+                    rust_string(&part.value),
+                    rust_string(&part.file_name())
+                ),
+            };
+            if let Some(content_type) = part.content_type() {
+                value.push_str(&format!(".mime_str({})?", rust_string(&content_type)));
+            }
+            out.push_str(&format!(
+                "        .part({}, {})\n",
+                rust_string(&part.name),
+                value
+            ));
+        }
+        // end the builder chain on its last line
+        out.pop();
+        out.push_str(";\n");
+    }
+    out.push_str("    let response = client\n");
+    let url = rust_string(request.url);
+    match request.method {
+        "GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "HEAD" => out.push_str(&format!(
+            "        .{}({})\n",
+            request.method.to_lowercase(),
+            url
+        )),
+        method => out.push_str(&format!(
+            "        .request(reqwest::Method::{}, {})\n",
+            method, url
+        )),
+    }
+    let queries: Vec<String> = request
+        .queries()
+        .map(|(k, v)| format!("({}, {})", rust_string(k), rust_string(v)))
+        .collect();
+    if !queries.is_empty() {
+        out.push_str(&format!("        .query(&[{}])\n", queries.join(", ")));
+    }
+    for (k, v) in request.headers() {
+        out.push_str(&format!(
+            "        .header({}, {})\n",
+            rust_string(k),
+            rust_string(v)
+        ));
+    }
+    if has_form {
+        out.push_str("        .multipart(form)\n");
+    } else if let Some(path) = request.file {
+        out.push_str(&format!(
+            "        .body(std::fs::read({})?)\n",
+            rust_string(path)
+        ));
+    } else if !request.body.is_empty() {
+        out.push_str(&format!(
+            "        .body({})\n",
+            rust_raw_string(request.body)
+        ));
+    }
+    out.push_str(&format!("        .send(){}?;\n", wait));
+    out.push_str("    println!(\"{}\", response.status());\n");
+    out.push_str(&format!(
+        "    println!(\"{{}}\", response.text(){}?);\n",
+        wait
+    ));
+    out.push_str("    Ok(())\n}\n");
+    out
+}
+
+fn python(request: &SnippetRequest) -> String {
+    let mut out = String::from("import requests\n\n");
+    out.push_str(&format!(
+        "response = requests.{}(\n    {},\n",
+        request.method.to_lowercase(),
+        quoted(request.url)
+    ));
+    let queries: Vec<String> = request
+        .queries()
+        .map(|(k, v)| format!("({}, {})", quoted(k), quoted(v)))
+        .collect();
+    if !queries.is_empty() {
+        out.push_str(&format!("    params=[{}],\n", queries.join(", ")));
+    }
+    let headers: Vec<String> = request
+        .headers()
+        .map(|(k, v)| format!("        {}: {},\n", quoted(k), quoted(v)))
+        .collect();
+    if !headers.is_empty() {
+        out.push_str(&format!("    headers={{\n{}    }},\n", headers.concat()));
+    }
+    if request.parts().next().is_some() {
+        let (files, fields): (Vec<&FormPart>, Vec<&FormPart>) =
+            request.parts().partition(|p| p.kind == PartKind::File);
+        // requests only sends multipart when there are files, so text fields
+        // go in `files` too with no file name
+        let entries: Vec<String> = fields
+            .iter()
+            .map(|p| {
+                format!(
+                    "        ({}, (None, {})),\n",
+                    quoted(&p.name),
+                    quoted(&p.value)
+                )
+            })
+            .chain(files.iter().map(|p| {
+                format!(
+                    "        ({}, ({}, open({}, \"rb\"), {})),\n",
+                    quoted(&p.name),
+                    quoted(&p.file_name()),
+                    quoted(&p.value),
+                    quoted(&p.content_type().unwrap_or_default())
+                )
+            }))
+            .collect();
+        out.push_str(&format!("    files=[\n{}    ],\n", entries.concat()));
+    } else if let Some(path) = request.file {
+        out.push_str(&format!("    data=open({}, \"rb\"),\n", quoted(path)));
+    } else if !request.body.is_empty() {
+        out.push_str(&format!("    data={},\n", quoted(request.body)));
+    }
+    out.push_str(")\nprint(response.status_code)\nprint(response.text)\n");
+    out
+}
+
+fn javascript(request: &SnippetRequest) -> String {
+    let mut out = String::new();
+    let has_form = request.parts().next().is_some();
+    if has_form {
+        out.push_str("const form = new FormData();\n");
+        for part in request.parts() {
+            match part.kind {
+                PartKind::Text => out.push_str(&format!(
+                    "form.append({}, {});\n",
+                    quoted(&part.name),
+                    quoted(&part.value)
+                )),
+                // fetch can't read local paths, the contents have to be supplied
+                PartKind::File => out.push_str(&format!(
+                    "form.append({}, new Blob([/* contents of {} */], {{ type: {} }}), {});\n",
+                    quoted(&part.name),
+                    in_comment(&part.value),
+                    quoted(&part.content_type().unwrap_or_default()),
+                    quoted(&part.file_name())
+                )),
+            }
+        }
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "const response = await fetch({}, {{\n  method: {},\n",
+        quoted(&request.full_url()),
+        quoted(request.method)
+    ));
+    let headers: Vec<String> = request
+        .headers()
+        .map(|(k, v)| format!("    {}: {},\n", quoted(k), quoted(v)))
+        .collect();
+    if !headers.is_empty() {
+        out.push_str(&format!("  headers: {{\n{}  }},\n", headers.concat()));
+    }
+    if has_form {
+        out.push_str("  body: form,\n");
+    } else if let Some(path) = request.file {
+        out.push_str(&format!(
+            "  body: new Blob([/* contents of {} */]),\n",
+            in_comment(path)
+        ));
+    } else if !request.body.is_empty() {
+        out.push_str(&format!("  body: {},\n", quoted(request.body)));
+    }
+    out.push_str("});\nconsole.log(response.status);\nconsole.log(await response.text());\n");
+    out
+}
+
+/// wraps `s` in single quotes for posix shells
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// a double-quoted string literal; json escaping is also valid python and
+/// javascript
+fn quoted(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+/// `s` with anything that would end a `/* */` comment early broken up
+fn in_comment(s: &str) -> String {
+    s.replace("*/", "*\\/")
+}
+
+fn rust_string(s: &str) -> String {
+    format!("{:?}", s)
+}
+
+/// a raw string literal with enough `#`s that `s` can't terminate it early
+fn rust_raw_string(s: &str) -> String {
+    let mut hashes = 0;
+    while s.contains(&format!("\"{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    let fence = "#".repeat(hashes);
+    format!("r{}\"{}\"{}", fence, s, fence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn curl_quotes_single_quotes_and_appends_queries() {
+        let queries = pairs(&[("q", "it's"), ("", "skipped")]);
+        let headers = pairs(&[("Accept", "application/json")]);
+        let request = SnippetRequest {
+            method: "POST",
+            url: "https://example.com/search",
+            queries: &queries,
+            headers: &headers,
+            body: r#"{"name":"o'brien"}"#,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(Language::Curl, &request),
+            "curl -X POST 'https://example.com/search?q=it%27s' \\\n  \
+             -H 'Accept: application/json' \\\n  \
+             --data-raw '{\"name\":\"o'\\''brien\"}'"
+        );
+    }
+
+    #[test]
+    fn curl_uses_head_flag_and_omits_get() {
+        let request = SnippetRequest {
+            method: "HEAD",
+            url: "https://example.com",
+            queries: &[],
+            headers: &[],
+            body: "",
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(Language::Curl, &request),
+            "curl --head 'https://example.com'"
+        );
+        let request = SnippetRequest {
+            method: "GET",
+            ..request
+        };
+        assert_eq!(
+            generate(Language::Curl, &request),
+            "curl 'https://example.com'"
+        );
+    }
+
+    #[test]
+    fn rust_blocking_builds_request_chain() {
+        let queries = pairs(&[("page", "2")]);
+        let headers = pairs(&[("X-Token", "a\"b")]);
+        let request = SnippetRequest {
+            method: "PUT",
+            url: "https://example.com/items/1",
+            queries: &queries,
+            headers: &headers,
+            body: r#"{"a":"b"}"#,
+            ..Default::default()
+        };
+        let expected = r##"fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let client = reqwest::blocking::Client::new();
+    let response = client
+        .put("https://example.com/items/1")
+        .query(&[("page", "2")])
+        .header("X-Token", "a\"b")
+        .body(r#"{"a":"b"}"#)
+        .send()?;
+    println!("{}", response.status());
+    println!("{}", response.text()?);
+    Ok(())
+}
+"##;
+        assert_eq!(generate(Language::RustBlocking, &request), expected);
+    }
+
+    #[test]
+    fn rust_async_awaits_and_maps_options() {
+        let request = SnippetRequest {
+            method: "OPTIONS",
+            url: "https://example.com",
+            queries: &[],
+            headers: &[],
+            body: "",
+            ..Default::default()
+        };
+        let snippet = generate(Language::RustAsync, &request);
+        assert!(snippet.starts_with("#[tokio::main]\nasync fn main()"));
+        assert!(snippet.contains("let client = reqwest::Client::new();"));
+        assert!(snippet.contains(".request(reqwest::Method::OPTIONS, \"https://example.com\")"));
+        assert!(snippet.contains(".send().await?;"));
+        assert!(snippet.contains("response.text().await?"));
+        assert!(!snippet.contains(".body("));
+    }
+
+    #[test]
+    fn rust_raw_string_grows_fence() {
+        assert_eq!(rust_raw_string("plain"), "r\"plain\"");
+        assert_eq!(rust_raw_string("a\"b"), "r#\"a\"b\"#");
+        assert_eq!(rust_raw_string("a\"#b"), "r##\"a\"#b\"##");
+    }
+
+    #[test]
+    fn python_uses_params_headers_and_data() {
+        let queries = pairs(&[("q", "rust")]);
+        let headers = pairs(&[("Content-Type", "application/json")]);
+        let request = SnippetRequest {
+            method: "POST",
+            url: "https://example.com/api",
+            queries: &queries,
+            headers: &headers,
+            body: "{\"msg\":\"line\nbreak\"}",
+            ..Default::default()
+        };
+        let expected = r#"import requests
+
+response = requests.post(
+    "https://example.com/api",
+    params=[("q", "rust")],
+    headers={
+        "Content-Type": "application/json",
+    },
+    data="{\"msg\":\"line\nbreak\"}",
+)
+print(response.status_code)
+print(response.text)
+"#;
+        assert_eq!(generate(Language::Python, &request), expected);
+    }
+
+    #[test]
+    fn javascript_fetch_escapes_strings() {
+        let queries = pairs(&[("a", "1 2")]);
+        let headers = pairs(&[("Authorization", "Bearer \"x\"")]);
+        let request = SnippetRequest {
+            method: "DELETE",
+            url: "https://example.com/items",
+            queries: &queries,
+            headers: &headers,
+            body: "",
+            ..Default::default()
+        };
+        let expected = r#"const response = await fetch("https://example.com/items?a=1+2", {
+  method: "DELETE",
+  headers: {
+    "Authorization": "Bearer \"x\"",
+  },
+});
+console.log(response.status);
+console.log(await response.text());
+"#;
+        assert_eq!(generate(Language::JavaScript, &request), expected);
+    }
+
+    #[test]
+    fn multipart_parts_become_form_fields() {
+        let parts = vec![
+            FormPart {
+                name: "title".to_owned(),
+                value: "cat".to_owned(),
+                ..Default::default()
+            },
+            FormPart {
+                name: "photo".to_owned(),
+                kind: PartKind::File,
+                value: "/tmp/cat.png".to_owned(),
+                ..Default::default()
+            },
+            FormPart::default(),
+        ];
+        let request = SnippetRequest {
+            method: "POST",
+            url: "https://example.com/upload",
+            queries: &[],
+            headers: &[],
+            body: "",
+            multipart: &parts,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(Language::Curl, &request),
+            "curl -X POST 'https://example.com/upload' \\\n  \
+             -F 'title=cat' \\\n  \
+             -F 'photo=@/tmp/cat.png'"
+        );
+        let rust = generate(Language::RustAsync, &request);
+        assert!(rust.contains(
+            "        .part(\"photo\", reqwest::multipart::Part::bytes(std::fs::read(\"/tmp/cat.png\")?)\
+             .file_name(\"cat.png\").mime_str(\"image/png\")?);\n"
+        ));
+        assert!(rust.contains("        .multipart(form)\n"));
+        let python = generate(Language::Python, &request);
+        assert!(python.contains("        (\"title\", (None, \"cat\")),\n"));
+        assert!(python.contains(
+            "        (\"photo\", (\"cat.png\", open(\"/tmp/cat.png\", \"rb\"), \"image/png\")),\n"
+        ));
+        assert!(generate(Language::JavaScript, &request).contains("  body: form,\n"));
+    }
+
+    #[test]
+    fn file_body_is_read_from_disk() {
+        let headers = pairs(&[("Content-Type", "image/png")]);
+        let request = SnippetRequest {
+            method: "PUT",
+            url: "https://example.com/cat.png",
+            headers: &headers,
+            file: Some("/tmp/cat's.png"),
+            ..Default::default()
+        };
+        assert!(generate(Language::Curl, &request).ends_with("--data-binary '@/tmp/cat'\\''s.png'"));
+        assert!(generate(Language::RustBlocking, &request)
+            .contains("        .body(std::fs::read(\"/tmp/cat's.png\")?)\n"));
+        assert!(generate(Language::Python, &request)
+            .contains("    data=open(\"/tmp/cat's.png\", \"rb\"),\n"));
+    }
+
+    #[test]
+    fn file_paths_cant_end_javascript_comments() {
+        let parts = vec![FormPart {
+            name: "upload".to_owned(),
+            value: "/tmp/a*/b.png".to_owned(),
+            kind: PartKind::File,
+            ..Default::default()
+        }];
+        let request = SnippetRequest {
+            method: "POST",
+            url: "https://example.com/",
+            multipart: &parts,
+            ..Default::default()
+        };
+        let code = generate(Language::JavaScript, &request);
+        assert!(
+            code.contains("/* contents of /tmp/a*\\/b.png */"),
+            "{}",
+            code
+        );
+        assert_eq!(code.matches("*/").count(), 1, "{}", code);
+
+        let request = SnippetRequest {
+            method: "PUT",
+            url: "https://example.com/",
+            file: Some("/tmp/a*/b.png"),
+            ..Default::default()
+        };
+        let code = generate(Language::JavaScript, &request);
+        assert!(code.contains("  body: new Blob([/* contents of /tmp/a*\\/b.png */]),\n"));
+    }
+}
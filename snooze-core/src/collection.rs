@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::assertions::Assertion;
+use crate::auth::Auth;
+use crate::body::{BinaryFile, BodyMode, FormPart};
+use crate::graphql::GraphqlBody;
+use crate::script::Scripts;
+use crate::settings::Timeouts;
+use crate::HttpMethod;
+
+/// the editor tab a request was saved with, reopened when it's loaded
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub enum EditorTab {
+    #[default]
+    Headers,
+    Body,
+    Query,
+    Auth,
+    Scripts,
+    Tests,
+    Settings,
+}
+
+const REQUEST_EXTENSION: &str = "json";
+
+/// a single request as stored on disk, one pretty-printed json file per request
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub queries: Vec<(String, String)>,
+    pub body: String,
+    pub body_mode: BodyMode,
+    /// fields of a form urlencoded body
+    pub form: Vec<(String, String)>,
+    pub multipart: Vec<FormPart>,
+    pub binary: BinaryFile,
+    pub graphql: GraphqlBody,
+    /// jsonpath or jq expression the json response is filtered with
+    pub json_filter: String,
+    pub tab: EditorTab,
+    pub timeouts: Timeouts,
+    pub auth: Auth,
+    pub scripts: Scripts,
+    /// checks run against every response
+    pub assertions: Vec<Assertion>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CollectionNode {
+    Folder {
+        name: String,
+        path: PathBuf,
+        children: Vec<CollectionNode>,
+    },
+    Request {
+        name: String,
+        path: PathBuf,
+    },
+}
+
+/// a workspace directory holding the `collections` folder tree
+pub struct Workspace {
+    pub root: PathBuf,
+    pub tree: Vec<CollectionNode>,
+}
+
+impl Workspace {
+    pub fn open(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let mut workspace = Self {
+            root: root.into(),
+            tree: Vec::new(),
+        };
+        fs::create_dir_all(workspace.collections_dir())?;
+        workspace.refresh()?;
+        Ok(workspace)
+    }
+
+    pub fn collections_dir(&self) -> PathBuf {
+        self.root.join("collections")
+    }
+
+    pub fn environments_path(&self) -> PathBuf {
+        self.root.join("environments.json")
+    }
+
+    pub fn history_path(&self) -> PathBuf {
+        self.root.join("history.json")
+    }
+
+    pub fn settings_path(&self) -> PathBuf {
+        self.root.join("settings.json")
+    }
+
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.tree = read_tree(&self.collections_dir())?;
+        Ok(())
+    }
+
+    /// every folder in the tree (including the collections root), as
+    /// (display label, path) pairs in depth-first order
+    pub fn folders(&self) -> Vec<(String, PathBuf)> {
+        fn walk(nodes: &[CollectionNode], prefix: &str, out: &mut Vec<(String, PathBuf)>) {
+            for node in nodes {
+                if let CollectionNode::Folder {
+                    name,
+                    path,
+                    children,
+                } = node
+                {
+                    let label = format!("{}/{}", prefix, name);
+                    out.push((label.clone(), path.clone()));
+                    walk(children, &label, out);
+                }
+            }
+        }
+        let mut out = vec![("/".to_owned(), self.collections_dir())];
+        walk(&self.tree, "", &mut out);
+        out
+    }
+
+    /// every request under `folder`, in the order the tree shows them
+    pub fn requests_in(folder: &Path) -> io::Result<Vec<PathBuf>> {
+        fn walk(nodes: Vec<CollectionNode>, out: &mut Vec<PathBuf>) {
+            for node in nodes {
+                match node {
+                    CollectionNode::Folder { children, .. } => walk(children, out),
+                    CollectionNode::Request { path, .. } => out.push(path),
+                }
+            }
+        }
+        let mut out = Vec::new();
+        walk(read_tree(folder)?, &mut out);
+        Ok(out)
+    }
+
+    pub fn request_path(folder: &Path, name: &str) -> PathBuf {
+        folder.join(format!("{}.{}", sanitize_name(name), REQUEST_EXTENSION))
+    }
+
+    pub fn load_request(path: &Path) -> io::Result<SavedRequest> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save_request(path: &Path, request: &SavedRequest) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut contents = serde_json::to_string_pretty(request)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        contents.push('\n');
+        fs::write(path, contents)
+    }
+
+    pub fn create_folder(parent: &Path, name: &str) -> io::Result<PathBuf> {
+        let path = parent.join(sanitize_name(name));
+        fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+
+    pub fn delete(path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+}
+
+/// workspace location from `SNOOZE_WORKSPACE`, falling back to `./snooze-workspace`
+pub fn default_workspace_dir() -> PathBuf {
+    std::env::var_os("SNOOZE_WORKSPACE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("snooze-workspace"))
+}
+
+/// display name of a saved request, i.e. its file stem
+pub fn request_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn sanitize_name(name: &str) -> String {
+    let cleaned: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        "untitled".to_owned()
+    } else {
+        cleaned
+    }
+}
+
+fn read_tree(dir: &Path) -> io::Result<Vec<CollectionNode>> {
+    let mut folders = Vec::new();
+    let mut requests = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            let children = read_tree(&path)?;
+            folders.push(CollectionNode::Folder {
+                name,
+                path,
+                children,
+            });
+        } else if path.extension().is_some_and(|ext| ext == REQUEST_EXTENSION) {
+            requests.push(CollectionNode::Request {
+                name: request_name(&path),
+                path,
+            });
+        }
+    }
+    let key = |node: &CollectionNode| match node {
+        CollectionNode::Folder { name, .. } | CollectionNode::Request { name, .. } => {
+            name.to_lowercase()
+        }
+    };
+    folders.sort_by_key(key);
+    requests.sort_by_key(key);
+    folders.extend(requests);
+    Ok(folders)
+}
@@ -0,0 +1,582 @@
+use base64::Engine;
+
+use crate::body::{BodyMode, FormPart, PartKind};
+use crate::history;
+use crate::HttpMethod;
+
+/// a request recovered from a curl command line
+#[derive(Debug, Clone, Default)]
+pub struct CurlImport {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub queries: Vec<(String, String)>,
+    pub body: String,
+    pub body_mode: BodyMode,
+    pub multipart: Vec<FormPart>,
+    /// a file sent as the whole body
+    pub file: Option<String>,
+    /// options that were recognised but could not be carried over
+    pub warnings: Vec<String>,
+}
+
+/// flags that only change what curl prints, or that snooze already behaves
+/// like (following redirects, decompressing responses)
+const IGNORED_FLAGS: [&str; 16] = [
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-v",
+    "--verbose",
+    "-i",
+    "--include",
+    "-#",
+    "--progress-bar",
+    "-f",
+    "--fail",
+    "-L",
+    "--location",
+    "--compressed",
+    "--no-progress-meter",
+];
+
+/// options taking an argument that snooze has no equivalent for
+const UNSUPPORTED_WITH_ARG: [&str; 20] = [
+    "-o",
+    "--output",
+    "-m",
+    "--max-time",
+    "--connect-timeout",
+    "--retry",
+    "-x",
+    "--proxy",
+    "--cacert",
+    "--cert",
+    "-E",
+    "--key",
+    "-T",
+    "--upload-file",
+    "--resolve",
+    "-w",
+    "--write-out",
+    "-c",
+    "--cookie-jar",
+    "--max-redirs",
+];
+
+const UNSUPPORTED_FLAGS: [&str; 6] = [
+    "-k",
+    "--insecure",
+    "-O",
+    "--remote-name",
+    "--http2",
+    "--http1.1",
+];
+
+/// short options whose value may be attached, e.g. `-XPOST`
+const SHORT_WITH_ARG: &str = "XHdubFAeoxmTwcE";
+
+pub fn parse_curl(command: &str) -> Result<CurlImport, String> {
+    let mut args = tokenize(command)?.into_iter().peekable();
+    match args.peek() {
+        Some(first) if first == "curl" => {
+            args.next();
+        }
+        Some(_) => {}
+        None => return Err("empty command".to_owned()),
+    }
+
+    let mut import = CurlImport::default();
+    let mut method: Option<String> = None;
+    let mut url: Option<String> = None;
+    let mut data: Vec<String> = Vec::new();
+    let mut form: Vec<FormPart> = Vec::new();
+    let mut head = false;
+    let mut get = false;
+    let mut has_content_type = false;
+
+    while let Some(arg) = args.next() {
+        let (flag, attached) = split_flag(&arg);
+        let mut value = |name: &str| -> Result<String, String> {
+            match &attached {
+                Some(v) => Ok(v.clone()),
+                None => args
+                    .next()
+                    .ok_or_else(|| format!("missing value for {}", name)),
+            }
+        };
+        match flag.as_str() {
+            "-X" | "--request" => method = Some(value(&flag)?.to_uppercase()),
+            "-H" | "--header" => {
+                let header = value(&flag)?;
+                match header.split_once(':') {
+                    Some((k, v)) => {
+                        let key = k.trim().to_owned();
+                        has_content_type |= key.eq_ignore_ascii_case("content-type");
+                        import.headers.push((key, v.trim().to_owned()));
+                    }
+                    None => import
+                        .warnings
+                        .push(format!("ignored malformed header: {}", header)),
+                }
+            }
+            "-d" | "--data" | "--data-raw" | "--data-binary" | "--data-ascii" => {
+                let body = value(&flag)?;
+                if flag == "--data-binary" && body.starts_with('@') {
+                    import.file = Some(body[1..].to_owned());
+                } else if flag != "--data-raw" && body.starts_with('@') {
+                    import.warnings.push(format!(
+                        "{} {}: reading data from a file is not supported",
+                        flag, body
+                    ));
+                } else {
+                    data.push(body);
+                }
+            }
+            "--data-urlencode" => data.push(urlencode_data(&value(&flag)?)),
+            "--json" => {
+                data.push(value(&flag)?);
+                if !has_content_type {
+                    import
+                        .headers
+                        .push(("Content-Type".to_owned(), "application/json".to_owned()));
+                    has_content_type = true;
+                }
+                import
+                    .headers
+                    .push(("Accept".to_owned(), "application/json".to_owned()));
+            }
+            "-F" | "--form" => {
+                let field = value(&flag)?;
+                match field.split_once('=') {
+                    Some((name, v)) if v.starts_with('@') || v.starts_with('<') => {
+                        if v.starts_with('<') {
+                            import.warnings.push(format!(
+                                "form field {} reads its value from a file, it was imported as a file part",
+                                name
+                            ));
+                        }
+                        let mut options = v[1..].split(';');
+                        let path = options.next().unwrap_or_default();
+                        let content_type = options
+                            .find_map(|o| o.strip_prefix("type="))
+                            .unwrap_or_default();
+                        form.push(FormPart {
+                            name: name.to_owned(),
+                            kind: PartKind::File,
+                            value: path.to_owned(),
+                            content_type: content_type.to_owned(),
+                        });
+                    }
+                    Some((name, v)) => form.push(FormPart {
+                        name: name.to_owned(),
+                        value: v.to_owned(),
+                        ..Default::default()
+                    }),
+                    None => import
+                        .warnings
+                        .push(format!("ignored malformed form field: {}", field)),
+                }
+            }
+            "-u" | "--user" => {
+                let credentials = value(&flag)?;
+                let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+                import
+                    .headers
+                    .push(("Authorization".to_owned(), format!("Basic {}", encoded)));
+            }
+            "-A" | "--user-agent" => import
+                .headers
+                .push(("User-Agent".to_owned(), value(&flag)?)),
+            "-e" | "--referer" => import.headers.push(("Referer".to_owned(), value(&flag)?)),
+            "-b" | "--cookie" => {
+                let cookie = value(&flag)?;
+                if cookie.contains('=') {
+                    import.headers.push(("Cookie".to_owned(), cookie));
+                } else {
+                    import.warnings.push(format!(
+                        "{} {}: cookie files are not supported",
+                        flag, cookie
+                    ));
+                }
+            }
+            "-I" | "--head" => head = true,
+            "-G" | "--get" => get = true,
+            "--url" => url = Some(value(&flag)?),
+            f if IGNORED_FLAGS.contains(&f) => {}
+            f if UNSUPPORTED_WITH_ARG.contains(&f) => {
+                let v = value(f)?;
+                import
+                    .warnings
+                    .push(format!("unsupported option {} {}", f, v));
+            }
+            f if UNSUPPORTED_FLAGS.contains(&f) => {
+                import.warnings.push(format!("unsupported option {}", f));
+            }
+            f if f.starts_with('-') && f.len() > 1 => match expand_short_flags(f) {
+                Some(flags) => {
+                    for short in flags {
+                        match short.as_str() {
+                            "-I" => head = true,
+                            "-G" => get = true,
+                            s if IGNORED_FLAGS.contains(&s) => {}
+                            s => import.warnings.push(format!("unsupported option {}", s)),
+                        }
+                    }
+                }
+                None => import.warnings.push(format!("unsupported option {}", f)),
+            },
+            _ => match url {
+                None => url = Some(arg.clone()),
+                Some(_) => import
+                    .warnings
+                    .push(format!("ignored extra argument: {}", arg)),
+            },
+        }
+    }
+
+    let url = url.ok_or_else(|| "no url found in curl command".to_owned())?;
+    let (url, mut queries) = history::split_queries(&url);
+    import.url = url;
+
+    if !form.is_empty() {
+        // the boundary is generated when sending, so a typed content type would be wrong
+        import.body_mode = BodyMode::Multipart;
+        import.multipart = form;
+        import
+            .headers
+            .retain(|(k, _)| !k.eq_ignore_ascii_case("content-type"));
+    } else if import.file.is_some() {
+        if !data.is_empty() {
+            import
+                .warnings
+                .push("data options given along with a file body were dropped".to_owned());
+        }
+        import.body_mode = BodyMode::Binary;
+    } else if !data.is_empty() {
+        let joined = data.join("&");
+        if get {
+            queries.extend(form_pairs(&joined));
+        } else {
+            import.body = joined;
+            if !has_content_type {
+                import.headers.push((
+                    "Content-Type".to_owned(),
+                    "application/x-www-form-urlencoded".to_owned(),
+                ));
+            }
+        }
+    }
+    import.queries = queries;
+
+    let has_body = !import.body.is_empty() || !import.multipart.is_empty() || import.file.is_some();
+    import.method = match method {
+        Some(m) => HttpMethod::ALL
+            .iter()
+            .find(|candidate| format!("{:?}", candidate) == m)
+            .cloned()
+            .unwrap_or_else(|| {
+                import
+                    .warnings
+                    .push(format!("unsupported method {}, using GET", m));
+                HttpMethod::GET
+            }),
+        None if head => HttpMethod::HEAD,
+        None if has_body => HttpMethod::POST,
+        None => HttpMethod::GET,
+    };
+    Ok(import)
+}
+
+/// splits `-XPOST` into (`-X`, `POST`); long options never carry attached values
+fn split_flag(arg: &str) -> (String, Option<String>) {
+    let mut chars = arg.chars();
+    if arg.len() > 2 && !arg.starts_with("--") && chars.next() == Some('-') {
+        if let Some(c) = chars.next() {
+            if SHORT_WITH_ARG.contains(c) {
+                return (format!("-{}", c), Some(arg[2..].to_owned()));
+            }
+        }
+    }
+    (arg.to_owned(), None)
+}
+
+/// expands bundled short flags such as `-sSL` into `-s`, `-S`, `-L`
+fn expand_short_flags(arg: &str) -> Option<Vec<String>> {
+    if arg.starts_with("--") || arg.len() < 3 {
+        return None;
+    }
+    Some(arg[1..].chars().map(|c| format!("-{}", c)).collect())
+}
+
+/// `--data-urlencode` accepts `content`, `=content` or `name=content`
+fn urlencode_data(value: &str) -> String {
+    let encode = |s: &str| url::form_urlencoded::byte_serialize(s.as_bytes()).collect::<String>();
+    match value.split_once('=') {
+        Some(("", content)) => encode(content),
+        Some((name, content)) => format!("{}={}", name, encode(content)),
+        None => encode(value),
+    }
+}
+
+fn form_pairs(data: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(data.as_bytes())
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
+
+/// splits a shell command line into words, handling single, double and `$'...'`
+/// quoting plus backslash line continuations
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(escaped) => {
+                    current.push(escaped);
+                    in_token = true;
+                }
+                None => {}
+            },
+            '\'' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("unterminated single quote".to_owned()),
+                    }
+                }
+            }
+            '"' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_owned()),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err("unterminated double quote".to_owned()),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => current.push('\n'),
+                            Some('r') => current.push('\r'),
+                            Some('t') => current.push('\t'),
+                            Some('0') => current.push('\0'),
+                            Some(kind @ ('u' | 'x')) => {
+                                let max = if kind == 'u' { 4 } else { 2 };
+                                let mut hex = String::new();
+                                while let Some(h) = chars.peek().filter(|h| h.is_ascii_hexdigit()) {
+                                    hex.push(*h);
+                                    chars.next();
+                                    if hex.len() == max {
+                                        break;
+                                    }
+                                }
+                                if let Some(ch) =
+                                    u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+                                {
+                                    current.push(ch);
+                                }
+                            }
+                            Some(c) => current.push(c),
+                            None => return Err("unterminated $' quote".to_owned()),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err("unterminated $' quote".to_owned()),
+                    }
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn imports_a_multi_line_command_copied_from_a_browser() {
+        let import = parse_curl(
+            "curl 'https://api.example.com/users?page=2' \\\n  \
+             -X 'PUT' \\\n  \
+             -H 'Accept: application/json' \\\r\n  \
+             -H \"Content-Type: application/json\" \\\n  \
+             --data-raw '{\"name\": \"ann\"}' \\\n  \
+             --compressed",
+        )
+        .unwrap();
+        assert_eq!(import.method, HttpMethod::PUT);
+        assert_eq!(import.url, "https://api.example.com/users");
+        assert_eq!(import.queries, pairs(&[("page", "2")]));
+        assert_eq!(
+            import.headers,
+            pairs(&[
+                ("Accept", "application/json"),
+                ("Content-Type", "application/json")
+            ])
+        );
+        assert_eq!(import.body, r#"{"name": "ann"}"#);
+        assert_eq!(import.body_mode, BodyMode::Raw);
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+    }
+
+    #[test]
+    fn handles_every_kind_of_quoting() {
+        let import = parse_curl(
+            r#"curl --url "https://example.com/a b" -H "X-Quote: say \"hi\" \$HOME" -H 'X-Single: it'\''s' -H $'X-Ansi: a\tbé' -d name=a\ b"#,
+        )
+        .unwrap();
+        assert_eq!(import.url, "https://example.com/a b");
+        assert_eq!(
+            import.headers,
+            pairs(&[
+                ("X-Quote", r#"say "hi" $HOME"#),
+                ("X-Single", "it's"),
+                ("X-Ansi", "a\tbé"),
+                ("Content-Type", "application/x-www-form-urlencoded"),
+            ])
+        );
+        assert_eq!(import.body, "name=a b");
+        assert_eq!(import.method, HttpMethod::POST);
+    }
+
+    #[test]
+    fn joins_data_flags_and_sends_basic_auth() {
+        let import = parse_curl(
+            "curl -XPATCH -uann:hunter2 -d a=1 --data b=2 --data-urlencode 'q=a b&c' https://example.com",
+        )
+        .unwrap();
+        assert_eq!(import.method, HttpMethod::PATCH);
+        assert_eq!(import.body, "a=1&b=2&q=a+b%26c");
+        assert_eq!(
+            import.headers,
+            pairs(&[
+                ("Authorization", "Basic YW5uOmh1bnRlcjI="),
+                ("Content-Type", "application/x-www-form-urlencoded"),
+            ])
+        );
+    }
+
+    #[test]
+    fn moves_data_into_the_query_with_get() {
+        let import = parse_curl("curl -G -d q=rust -d page=2 https://example.com/search").unwrap();
+        assert_eq!(import.method, HttpMethod::GET);
+        assert!(import.body.is_empty());
+        assert_eq!(import.queries, pairs(&[("q", "rust"), ("page", "2")]));
+    }
+
+    #[test]
+    fn expands_combined_short_flags() {
+        let import = parse_curl("curl -sSLI https://example.com").unwrap();
+        assert_eq!(import.method, HttpMethod::HEAD);
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+
+        let import = parse_curl("curl -sk https://example.com").unwrap();
+        assert_eq!(import.warnings, ["unsupported option -k"]);
+    }
+
+    #[test]
+    fn imports_multipart_forms_and_file_bodies() {
+        let import = parse_curl(
+            "curl -F name=ann -F 'avatar=@me.png;type=image/png' -H 'Content-Type: multipart/form-data' https://example.com/upload",
+        )
+        .unwrap();
+        assert_eq!(import.method, HttpMethod::POST);
+        assert_eq!(import.body_mode, BodyMode::Multipart);
+        assert!(import.headers.is_empty());
+        assert_eq!(import.multipart[0].value, "ann");
+        assert_eq!(import.multipart[1].kind, PartKind::File);
+        assert_eq!(import.multipart[1].value, "me.png");
+        assert_eq!(import.multipart[1].content_type, "image/png");
+
+        let import = parse_curl("curl --data-binary @dump.bin https://example.com").unwrap();
+        assert_eq!(import.body_mode, BodyMode::Binary);
+        assert_eq!(import.file.as_deref(), Some("dump.bin"));
+    }
+
+    #[test]
+    fn warns_about_options_it_cannot_carry_over() {
+        let import = parse_curl(
+            "curl --frobnicate -o out.json --insecure -d @body.json https://example.com extra",
+        )
+        .unwrap();
+        assert_eq!(
+            import.warnings,
+            [
+                "unsupported option --frobnicate",
+                "unsupported option -o out.json",
+                "unsupported option --insecure",
+                "-d @body.json: reading data from a file is not supported",
+                "ignored extra argument: extra",
+            ]
+        );
+        assert_eq!(import.url, "https://example.com");
+    }
+
+    #[test]
+    fn rejects_commands_it_cannot_read() {
+        assert_eq!(
+            parse_curl("curl 'https://example.com").unwrap_err(),
+            "unterminated single quote"
+        );
+        assert_eq!(
+            parse_curl(r#"curl -H "Accept: */* https://example.com"#).unwrap_err(),
+            "unterminated double quote"
+        );
+        assert_eq!(
+            parse_curl("curl -X POST -H 'Accept: */*'").unwrap_err(),
+            "no url found in curl command"
+        );
+        assert_eq!(
+            parse_curl("curl https://example.com -H").unwrap_err(),
+            "missing value for -H"
+        );
+        assert_eq!(parse_curl("  ").unwrap_err(), "empty command");
+    }
+}
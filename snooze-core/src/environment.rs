@@ -0,0 +1,189 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// a named set of variables substituted into `{{name}}` placeholders
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Environment {
+    pub name: String,
+    pub variables: Vec<(String, String)>,
+}
+
+/// every environment in a workspace plus the currently active one,
+/// stored together in `environments.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Environments {
+    pub active: Option<String>,
+    pub environments: Vec<Environment>,
+}
+
+impl Environments {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        contents.push('\n');
+        fs::write(path, contents)
+    }
+
+    pub fn active(&self) -> Option<&Environment> {
+        let active = self.active.as_ref()?;
+        self.environments.iter().find(|env| &env.name == active)
+    }
+
+    /// variables of the active environment; later rows win over earlier ones
+    pub fn active_variables(&self) -> HashMap<String, String> {
+        self.active()
+            .map(|env| {
+                env.variables
+                    .iter()
+                    .filter(|(k, _)| !k.is_empty())
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn variable_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap())
+}
+
+/// byte ranges of every `{{name}}` placeholder in `input`, with the variable name
+pub fn variable_spans(input: &str) -> Vec<(Range<usize>, &str)> {
+    variable_regex()
+        .captures_iter(input)
+        .filter_map(|caps| {
+            let whole = caps.get(0)?;
+            let name = caps.get(1)?;
+            Some((whole.range(), name.as_str()))
+        })
+        .collect()
+}
+
+/// byte ranges of the placeholders in `input` that `vars` doesn't define
+pub fn undefined_spans(input: &str, vars: &HashMap<String, String>) -> Vec<Range<usize>> {
+    variable_spans(input)
+        .into_iter()
+        .filter(|(_, name)| !vars.contains_key(*name))
+        .map(|(range, _)| range)
+        .collect()
+}
+
+/// replaces `{{name}}` placeholders with values from `vars`; unknown names are
+/// left untouched and appended to `unresolved`
+pub fn substitute(
+    input: &str,
+    vars: &HashMap<String, String>,
+    unresolved: &mut Vec<String>,
+) -> String {
+    variable_regex()
+        .replace_all(input, |caps: &regex::Captures| {
+            let name = &caps[1];
+            match vars.get(name) {
+                Some(value) => value.clone(),
+                None => {
+                    if !unresolved.iter().any(|n| n == name) {
+                        unresolved.push(name.to_owned());
+                    }
+                    caps[0].to_owned()
+                }
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn substitutes_known_variables_and_reports_the_rest_once() {
+        let vars = vars(&[("host", "example.com"), ("id", "7")]);
+        let mut unresolved = Vec::new();
+        let out = substitute(
+            "https://{{host}}/users/{{ id }}?t={{token}}&again={{token}}",
+            &vars,
+            &mut unresolved,
+        );
+        assert_eq!(
+            out,
+            "https://example.com/users/7?t={{token}}&again={{token}}"
+        );
+        assert_eq!(unresolved, ["token"]);
+    }
+
+    #[test]
+    fn ignores_text_that_only_looks_like_a_placeholder() {
+        let mut unresolved = Vec::new();
+        let input = "{{}} {{a b}} {single} {{{nested}}}";
+        let out = substitute(input, &vars(&[("nested", "x")]), &mut unresolved);
+        assert_eq!(out, "{{}} {{a b}} {single} {x}");
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn finds_placeholder_spans_and_the_undefined_ones() {
+        let input = "{{base}}/{{ path }}/é{{missing}}";
+        let spans: Vec<_> = variable_spans(input)
+            .into_iter()
+            .map(|(range, name)| (&input[range], name))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("{{base}}", "base"),
+                ("{{ path }}", "path"),
+                ("{{missing}}", "missing")
+            ]
+        );
+        let undefined = undefined_spans(input, &vars(&[("base", ""), ("path", "")]));
+        assert_eq!(undefined.len(), 1);
+        assert_eq!(&input[undefined[0].clone()], "{{missing}}");
+    }
+
+    #[test]
+    fn later_rows_of_the_active_environment_win() {
+        let environments = Environments {
+            active: Some("dev".to_owned()),
+            environments: vec![
+                Environment {
+                    name: "prod".to_owned(),
+                    variables: vec![("host".to_owned(), "prod".to_owned())],
+                },
+                Environment {
+                    name: "dev".to_owned(),
+                    variables: vec![
+                        ("host".to_owned(), "old".to_owned()),
+                        ("host".to_owned(), "dev".to_owned()),
+                        (String::new(), "skipped".to_owned()),
+                    ],
+                },
+            ],
+        };
+        assert_eq!(environments.active_variables(), vars(&[("host", "dev")]));
+    }
+}
@@ -0,0 +1,638 @@
+use log::{error, info};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::runtime::Runtime;
+use tokio::task::AbortHandle;
+
+use crate::assertions::{self, Assertion};
+use crate::auth::{Auth, AuthKind, DigestChallenge};
+use crate::body::{self, BodyMode, RequestBody, UploadProgress};
+use crate::collection::SavedRequest;
+use crate::environment;
+use crate::oauth::TokenCache;
+use crate::response::{HttpResponse, RequestError, RequestResult, SentRequest, TimeoutKind};
+use crate::runner::{self, PreparedStep, RunEvent, RunOptions, RunStep};
+use crate::script::{self, ScriptLog, Scripts};
+use crate::settings::Timeouts;
+use crate::sigv4;
+use crate::HttpMethod;
+
+/// in-memory bodies this big are streamed, so the read timeout can wait for
+/// the upload to finish
+const STREAMED_BODY_BYTES: usize = 256 * 1024;
+
+/// the editor fields with environment variables substituted in
+pub struct ResolvedRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub queries: Vec<(String, String)>,
+    pub body: RequestBody,
+    pub auth: Auth,
+    pub scripts: Scripts,
+    pub assertions: Vec<Assertion>,
+    /// what the scripts can read and set, starting from the active environment
+    pub variables: HashMap<String, String>,
+}
+
+impl ResolvedRequest {
+    /// `request` with `vars` substituted; unknown placeholders are left as-is
+    /// and reported in `unresolved`
+    pub fn resolve(
+        request: &SavedRequest,
+        vars: &HashMap<String, String>,
+        unresolved: &mut Vec<String>,
+    ) -> ResolvedRequest {
+        let resolve_pairs = |pairs: &[(String, String)], unresolved: &mut Vec<String>| {
+            pairs
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        environment::substitute(k, vars, unresolved),
+                        environment::substitute(v, vars, unresolved),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        ResolvedRequest {
+            method: request.method.clone(),
+            url: environment::substitute(&request.url, vars, unresolved),
+            headers: resolve_pairs(&request.headers, unresolved),
+            queries: resolve_pairs(&request.queries, unresolved),
+            body: match request.body_mode {
+                BodyMode::Raw => {
+                    RequestBody::Raw(environment::substitute(&request.body, vars, unresolved))
+                }
+                BodyMode::FormUrlEncoded => {
+                    RequestBody::Form(resolve_pairs(&request.form, unresolved))
+                }
+                BodyMode::Binary => RequestBody::File(
+                    request
+                        .binary
+                        .map_fields(|field| environment::substitute(field, vars, unresolved)),
+                ),
+                BodyMode::Graphql => RequestBody::Graphql(
+                    request
+                        .graphql
+                        .map_fields(|field| environment::substitute(field, vars, unresolved)),
+                ),
+                BodyMode::Multipart => RequestBody::Multipart(
+                    request
+                        .multipart
+                        .iter()
+                        .map(|part| {
+                            part.map_fields(|field| {
+                                environment::substitute(field, vars, unresolved)
+                            })
+                        })
+                        .collect(),
+                ),
+            },
+            auth: request
+                .auth
+                .map_fields(|field| environment::substitute(field, vars, unresolved)),
+            scripts: request.scripts.clone(),
+            assertions: request
+                .assertions
+                .iter()
+                .map(|a| a.map_fields(|field| environment::substitute(field, vars, unresolved)))
+                .collect(),
+            variables: vars.clone(),
+        }
+    }
+
+    /// applies `f` to every text field, e.g. to substitute variables
+    pub fn map_fields(&self, mut f: impl FnMut(&str) -> String) -> ResolvedRequest {
+        let mut pairs = |pairs: &[(String, String)]| {
+            pairs.iter().map(|(k, v)| (f(k), f(v))).collect::<Vec<_>>()
+        };
+        let headers = pairs(&self.headers);
+        let queries = pairs(&self.queries);
+        ResolvedRequest {
+            method: self.method.clone(),
+            url: f(&self.url),
+            headers,
+            queries,
+            body: self.body.map_fields(&mut f),
+            auth: self.auth.map_fields(&mut f),
+            scripts: self.scripts.clone(),
+            assertions: self
+                .assertions
+                .iter()
+                .map(|a| a.map_fields(&mut f))
+                .collect(),
+            variables: self.variables.clone(),
+        }
+    }
+
+    /// the editor headers plus the ones auth adds, which replace any typed
+    /// header of the same name
+    pub fn headers_with_auth(&self) -> Vec<(String, String)> {
+        let auth_headers = self.auth.headers();
+        let mut headers: Vec<(String, String)> = self
+            .headers
+            .iter()
+            .filter(|(k, _)| !auth_headers.iter().any(|(a, _)| a.eq_ignore_ascii_case(k)))
+            .cloned()
+            .collect();
+        headers.extend(auth_headers);
+        headers
+    }
+
+    pub fn queries_with_auth(&self) -> Vec<(String, String)> {
+        self.queries
+            .iter()
+            .cloned()
+            .chain(self.auth.queries())
+            .collect()
+    }
+}
+
+/// a long-lived runtime plus pooled clients shared by every request, so
+/// repeated calls reuse connections and tls sessions
+pub struct Executor {
+    runtime: Runtime,
+    /// connect timeouts are fixed per client, so there is one client per value
+    clients: Mutex<HashMap<Option<Duration>, reqwest::Client>>,
+    tokens: Arc<TokenCache>,
+}
+
+impl Executor {
+    pub fn new() -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("snooze-worker")
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            runtime,
+            clients: Mutex::new(HashMap::new()),
+            tokens: Arc::new(TokenCache::default()),
+        })
+    }
+
+    pub fn tokens(&self) -> &TokenCache {
+        &self.tokens
+    }
+
+    fn client(&self, connect: Option<Duration>) -> reqwest::Result<reqwest::Client> {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = clients.get(&connect) {
+            return Ok(client.clone());
+        }
+        let mut builder = reqwest::Client::builder();
+        if let Some(connect) = connect {
+            builder = builder.connect_timeout(connect);
+        }
+        let client = builder.build()?;
+        clients.insert(connect, client.clone());
+        Ok(client)
+    }
+
+    /// runs `request` as a task and sends its result to `tx` tagged with `id`;
+    /// aborting the returned handle drops the request without sending anything
+    pub fn spawn(
+        &self,
+        id: u64,
+        request: ResolvedRequest,
+        timeouts: Timeouts,
+        progress: Arc<UploadProgress>,
+        tx: Sender<RequestResult>,
+    ) -> AbortHandle {
+        let client = self.client(timeouts.connect());
+        let tokens = self.tokens.clone();
+        let task = self.runtime.spawn(async move {
+            let checks = request.assertions.clone();
+            let mut script_log = ScriptLog::default();
+            let mut sent = None;
+            let (response, duration) = match client {
+                Ok(client) => {
+                    execute(
+                        &client,
+                        &tokens,
+                        request,
+                        &timeouts,
+                        &progress,
+                        &mut script_log,
+                        &mut sent,
+                    )
+                    .await
+                }
+                Err(e) => {
+                    error!("failed to build client: {}", e);
+                    (
+                        Err(RequestError::Failed(format!(
+                            "failed to build client: {}",
+                            e
+                        ))),
+                        Duration::ZERO,
+                    )
+                }
+            };
+            let assertions = assertions::evaluate(&checks, &response, duration);
+            let _ = tx.send(RequestResult {
+                id,
+                sent,
+                response,
+                duration,
+                script_log,
+                assertions,
+            });
+        });
+        task.abort_handle()
+    }
+
+    /// runs `steps` in the background, reporting each result on `tx`.
+    /// `timeouts` are the global ones each step's own settings fall back to
+    pub fn run(
+        &self,
+        steps: Vec<RunStep>,
+        variables: HashMap<String, String>,
+        data: Vec<HashMap<String, String>>,
+        options: RunOptions,
+        timeouts: &Timeouts,
+        tx: Sender<RunEvent>,
+    ) -> AbortHandle {
+        let steps = steps
+            .into_iter()
+            .map(|step| {
+                let timeouts = step.request.timeouts.or(timeouts);
+                let client = self
+                    .client(timeouts.connect())
+                    .map_err(|e| format!("failed to build client: {}", e));
+                PreparedStep {
+                    step,
+                    client,
+                    timeouts,
+                }
+            })
+            .collect();
+        let task = self.runtime.spawn(runner::run(
+            steps,
+            self.tokens.clone(),
+            variables,
+            data,
+            options,
+            tx,
+        ));
+        task.abort_handle()
+    }
+}
+
+/// runs the pre-request script and fetches any oauth2 token `request` needs,
+/// then sends it, reads the whole response and runs the post-response script.
+/// returns how long the exchange took. file bodies report how far their
+/// upload got to `progress`; script output goes to `script_log` and the
+/// request as it went out to `sent`
+pub async fn execute(
+    client: &reqwest::Client,
+    tokens: &TokenCache,
+    mut request: ResolvedRequest,
+    timeouts: &Timeouts,
+    progress: &Arc<UploadProgress>,
+    script_log: &mut ScriptLog,
+    sent: &mut Option<SentRequest>,
+) -> (Result<HttpResponse, RequestError>, Duration) {
+    let mut variables = std::mem::take(&mut request.variables);
+    if request.scripts.has_pre_request() {
+        let script = request.scripts.pre_request.clone();
+        if let Err(e) = script::run_pre_request(&script, &mut request, &mut variables, script_log) {
+            error!("{}", e);
+            return (Err(RequestError::Failed(e)), Duration::ZERO);
+        }
+        // placeholders left for the script to fill
+        let mut unresolved = Vec::new();
+        request =
+            request.map_fields(|field| environment::substitute(field, &variables, &mut unresolved));
+        if !unresolved.is_empty() {
+            let message = format!(
+                "unresolved variables after the pre-request script: {}",
+                unresolved.join(", ")
+            );
+            error!("{}", message);
+            return (Err(RequestError::Failed(message)), Duration::ZERO);
+        }
+    }
+    if request.auth.kind == AuthKind::OAuth2 {
+        match tokens.access_token(client, &request.auth.oauth2).await {
+            Ok(token) => request.auth.token = token,
+            Err(e) => {
+                error!("oauth2: {}", e);
+                return (
+                    Err(RequestError::Failed(format!("oauth2: {}", e))),
+                    Duration::ZERO,
+                );
+            }
+        }
+    }
+    info!("executing request: {:?} {}", request.method, request.url);
+    let start = Instant::now();
+    let post_response = request
+        .scripts
+        .has_post_response()
+        .then(|| request.scripts.post_response.clone());
+    let result = send(client, request, timeouts, progress, sent).await;
+    let duration = start.elapsed();
+    if let (Ok(response), Some(script)) = (&result, &post_response) {
+        script::run_post_response(script, response, &mut variables, script_log);
+    }
+    match &result {
+        Ok(_) => info!("request succeeded in {} ms", duration.as_millis()),
+        Err(e) => error!("{}", e),
+    }
+    (result, duration)
+}
+
+async fn send(
+    client: &reqwest::Client,
+    request: ResolvedRequest,
+    timeouts: &Timeouts,
+    progress: &Arc<UploadProgress>,
+    sent: &mut Option<SentRequest>,
+) -> Result<HttpResponse, RequestError> {
+    let built = build(client, &request, timeouts, progress).await?;
+    *sent = Some(SentRequest::new(
+        request.method.clone(),
+        &built,
+        &request.body,
+    ));
+    let mut resp = execute_timed(client, built, timeouts, progress).await?;
+    // digest needs the server's challenge first, then the request goes again
+    if request.auth.kind == AuthKind::Digest {
+        if let Some(challenge) = digest_challenge(&resp) {
+            info!("answering digest challenge for realm {}", challenge.realm);
+            let mut retry = build(client, &request, timeouts, progress).await?;
+            let uri = &retry.url()[url::Position::BeforePath..];
+            // streamed bodies can't be hashed up front, auth-int gets an empty one
+            let body = retry.body().and_then(|b| b.as_bytes()).unwrap_or_default();
+            let authorization = challenge.authorization(
+                &request.auth.username,
+                &request.auth.password,
+                retry.method().as_str(),
+                uri,
+                body,
+            );
+            let value = reqwest::header::HeaderValue::from_str(&authorization)
+                .map_err(|e| RequestError::Failed(format!("invalid digest header: {}", e)))?;
+            retry
+                .headers_mut()
+                .insert(reqwest::header::AUTHORIZATION, value);
+            *sent = Some(SentRequest::new(
+                request.method.clone(),
+                &retry,
+                &request.body,
+            ));
+            resp = execute_timed(client, retry, timeouts, progress).await?;
+        }
+    }
+    let mut response = HttpResponse::from_head(&resp);
+    while let Some(chunk) = read_timeout(timeouts, resp.chunk())
+        .await?
+        .map_err(|e| classify(e, timeouts, "error reading response"))?
+    {
+        response.body.extend_from_slice(&chunk);
+    }
+    Ok(response)
+}
+
+/// the request with headers, queries, body and signature in place, ready to send
+async fn build(
+    client: &reqwest::Client,
+    request: &ResolvedRequest,
+    timeouts: &Timeouts,
+    progress: &Arc<UploadProgress>,
+) -> Result<reqwest::Request, RequestError> {
+    let url = &request.url;
+    let mut req_builder = match request.method {
+        HttpMethod::GET => client.get(url),
+        HttpMethod::POST => client.post(url),
+        HttpMethod::PUT => client.put(url),
+        HttpMethod::DELETE => client.delete(url),
+        HttpMethod::PATCH => client.patch(url),
+        HttpMethod::HEAD => client.head(url),
+        HttpMethod::OPTIONS => client.request(reqwest::Method::OPTIONS, url),
+    };
+    for (key, value) in request.headers_with_auth().iter() {
+        if !key.is_empty() {
+            req_builder = req_builder.header(key, value);
+        }
+    }
+    let queries = request.queries_with_auth();
+    let query_params: Vec<(&str, &str)> = queries
+        .iter()
+        .filter(|(k, _)| !k.is_empty())
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    if !query_params.is_empty() {
+        req_builder = req_builder.query(&query_params);
+    }
+    if let Some(total) = timeouts.total() {
+        req_builder = req_builder.timeout(total);
+    }
+    let mut built = req_builder
+        .build()
+        .map_err(|e| RequestError::Failed(format!("invalid request: {}", e)))?;
+    if request.method.has_body() {
+        set_body(&mut built, &request.body, progress).await?;
+    }
+    if request.auth.kind == AuthKind::SigV4 {
+        sigv4::sign(&request.auth.sigv4, &mut built, SystemTime::now())
+            .map_err(|e| RequestError::Failed(format!("sigv4: {}", e)))?;
+    }
+    Ok(built)
+}
+
+/// encodes `body` for its mode. form, file and graphql bodies default the
+/// content type, while multipart ones always replace it since it has to name
+/// the boundary
+async fn set_body(
+    built: &mut reqwest::Request,
+    body: &RequestBody,
+    progress: &Arc<UploadProgress>,
+) -> Result<(), RequestError> {
+    use reqwest::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+    let (bytes, content_type) = match body {
+        RequestBody::Raw(text) => {
+            *built.body_mut() = Some(text.clone().into());
+            return Ok(());
+        }
+        RequestBody::Form(pairs) => {
+            built
+                .headers_mut()
+                .entry(CONTENT_TYPE)
+                .or_insert(HeaderValue::from_static(
+                    "application/x-www-form-urlencoded",
+                ));
+            *built.body_mut() = Some(body::form_urlencode(pairs).into());
+            return Ok(());
+        }
+        RequestBody::Graphql(graphql) => {
+            let json = graphql.to_json().map_err(RequestError::Failed)?;
+            built
+                .headers_mut()
+                .entry(CONTENT_TYPE)
+                .or_insert(HeaderValue::from_static("application/json"));
+            *built.body_mut() = Some(json.into());
+            return Ok(());
+        }
+        RequestBody::File(file) => {
+            let (len, reader) = body::open_tracked(&file.path, progress)
+                .await
+                .map_err(RequestError::Failed)?;
+            let content_type = HeaderValue::from_str(&file.content_type())
+                .map_err(|e| RequestError::Failed(format!("invalid content type: {}", e)))?;
+            let headers = built.headers_mut();
+            headers.entry(CONTENT_TYPE).or_insert(content_type);
+            // a known length avoids chunked encoding, which some servers refuse
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+            *built.body_mut() = Some(reqwest::Body::wrap_stream(
+                tokio_util::io::ReaderStream::new(reader),
+            ));
+            return Ok(());
+        }
+        RequestBody::Multipart(parts) => body::encode_multipart(parts)
+            .await
+            .map_err(RequestError::Failed)?,
+    };
+    let value = HeaderValue::from_str(&content_type)
+        .map_err(|e| RequestError::Failed(format!("invalid content type: {}", e)))?;
+    built.headers_mut().insert(CONTENT_TYPE, value);
+    *built.body_mut() = Some(bytes.into());
+    Ok(())
+}
+
+/// the digest challenge of a 401 response, if it offered one we can answer
+fn digest_challenge(resp: &reqwest::Response) -> Option<DigestChallenge> {
+    if resp.status() != reqwest::StatusCode::UNAUTHORIZED {
+        return None;
+    }
+    resp.headers()
+        .get_all(reqwest::header::WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(DigestChallenge::parse)
+}
+
+/// sends `req` and waits for the response head. the read timeout only starts
+/// once the body has been handed to the connection, so slow uploads don't hit it
+async fn execute_timed(
+    client: &reqwest::Client,
+    mut req: reqwest::Request,
+    timeouts: &Timeouts,
+    progress: &Arc<UploadProgress>,
+) -> Result<reqwest::Response, RequestError> {
+    match req.body().map(|b| b.as_bytes()) {
+        // small bodies go out with the head and stay bytes, so 307 and 308
+        // redirects can send them again
+        None => progress.finish(),
+        Some(Some(bytes)) if bytes.len() < STREAMED_BODY_BYTES => progress.finish(),
+        Some(Some(bytes)) => {
+            let bytes = bytes.to_vec();
+            // a known length avoids chunked encoding, as with files
+            req.headers_mut()
+                .entry(reqwest::header::CONTENT_LENGTH)
+                .or_insert(reqwest::header::HeaderValue::from(bytes.len()));
+            let reader = body::track_bytes(bytes, progress);
+            *req.body_mut() = Some(reqwest::Body::wrap_stream(
+                tokio_util::io::ReaderStream::new(reader),
+            ));
+        }
+        // file bodies report when they've been read through themselves
+        Some(None) => {}
+    }
+    let response = client.execute(req);
+    let result = match timeouts.read() {
+        None => response.await,
+        Some(limit) => {
+            let waited_too_long = async {
+                progress.finished().await;
+                tokio::time::sleep(limit).await;
+            };
+            tokio::select! {
+                result = response => result,
+                _ = waited_too_long => {
+                    return Err(RequestError::Timeout {
+                        kind: TimeoutKind::Read,
+                        limit: Some(limit),
+                    })
+                }
+            }
+        }
+    };
+    result.map_err(|e| classify(e, timeouts, "request error"))
+}
+
+/// fails with a read timeout when `fut` waits longer than the configured limit
+async fn read_timeout<F: Future>(timeouts: &Timeouts, fut: F) -> Result<F::Output, RequestError> {
+    match timeouts.read() {
+        Some(limit) => tokio::time::timeout(limit, fut)
+            .await
+            .map_err(|_| RequestError::Timeout {
+                kind: TimeoutKind::Read,
+                limit: Some(limit),
+            }),
+        None => Ok(fut.await),
+    }
+}
+
+fn classify(e: reqwest::Error, timeouts: &Timeouts, context: &str) -> RequestError {
+    if e.is_timeout() {
+        if e.is_connect() {
+            RequestError::Timeout {
+                kind: TimeoutKind::Connect,
+                limit: timeouts.connect(),
+            }
+        } else {
+            RequestError::Timeout {
+                kind: TimeoutKind::Total,
+                limit: timeouts.total(),
+            }
+        }
+    } else {
+        RequestError::Failed(format!("{}: {}", context, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assertions::AssertionKind;
+
+    #[test]
+    fn resolve_substitutes_and_reports_unknown_variables() {
+        let saved = SavedRequest {
+            url: "{{base}}/users/{{id}}".to_owned(),
+            headers: vec![("Authorization".to_owned(), "typed".to_owned())],
+            auth: Auth {
+                kind: AuthKind::Bearer,
+                token: "{{token}}".to_owned(),
+                ..Default::default()
+            },
+            assertions: vec![Assertion {
+                kind: AssertionKind::JsonEquals,
+                target: "$.id".to_owned(),
+                expected: "{{id}}".to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let vars = HashMap::from([
+            ("base".to_owned(), "https://api.test".to_owned()),
+            ("id".to_owned(), "7".to_owned()),
+        ]);
+        let mut unresolved = Vec::new();
+        let request = ResolvedRequest::resolve(&saved, &vars, &mut unresolved);
+        assert_eq!(request.url, "https://api.test/users/7");
+        assert_eq!(request.assertions[0].expected, "7");
+        assert_eq!(unresolved, ["token"]);
+        // auth replaces a typed header of the same name
+        assert_eq!(
+            request.headers_with_auth(),
+            [("Authorization".to_owned(), "Bearer {{token}}".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,789 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// asks a server to describe its schema; type references are unwrapped six
+/// levels deep, enough for `[[Type!]!]!`
+pub const INTROSPECTION_QUERY: &str = "query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      description
+      fields(includeDeprecated: true) {
+        name
+        description
+        args { name description type { ...TypeRef } defaultValue }
+        type { ...TypeRef }
+      }
+      inputFields { name description type { ...TypeRef } defaultValue }
+      enumValues(includeDeprecated: true) { name description }
+      possibleTypes { name }
+    }
+  }
+}
+
+fragment TypeRef on __Type {
+  kind name ofType { kind name ofType { kind name ofType { kind name ofType {
+    kind name ofType { kind name ofType { kind name } } } } } }
+}";
+
+/// a graphql body as typed: the document, its variables as json text and
+/// which operation to run when the document has several
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphqlBody {
+    pub query: String,
+    pub variables: String,
+    pub operation_name: String,
+}
+
+impl GraphqlBody {
+    /// applies `f` to every text field, e.g. to substitute variables
+    pub fn map_fields(&self, mut f: impl FnMut(&str) -> String) -> GraphqlBody {
+        GraphqlBody {
+            query: f(&self.query),
+            variables: f(&self.variables),
+            operation_name: f(&self.operation_name),
+        }
+    }
+
+    /// the json payload sent over http; empty variables and operation name
+    /// are left out
+    pub fn to_json(&self) -> Result<String, String> {
+        let mut payload = serde_json::Map::new();
+        payload.insert("query".to_owned(), Value::String(self.query.clone()));
+        let variables = self.variables.trim();
+        if !variables.is_empty() {
+            let variables: Value = serde_json::from_str(variables)
+                .map_err(|e| format!("invalid variables json: {}", e))?;
+            if !variables.is_object() {
+                return Err("variables must be a json object".to_owned());
+            }
+            payload.insert("variables".to_owned(), variables);
+        }
+        let operation_name = self.operation_name.trim();
+        if !operation_name.is_empty() {
+            payload.insert(
+                "operationName".to_owned(),
+                Value::String(operation_name.to_owned()),
+            );
+        }
+        Ok(Value::Object(payload).to_string())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NamedRef {
+    name: String,
+}
+
+/// a reference to a type as written in a field or argument, e.g. `[User!]!`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeRef {
+    kind: String,
+    name: Option<String>,
+    of_type: Option<Box<TypeRef>>,
+}
+
+impl TypeRef {
+    /// the named type under any list and non-null wrappers
+    pub fn named(&self) -> &str {
+        match (&self.name, &self.of_type) {
+            (Some(name), _) => name,
+            (None, Some(inner)) => inner.named(),
+            (None, None) => "",
+        }
+    }
+}
+
+impl fmt::Display for TypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind.as_str(), &self.of_type) {
+            ("NON_NULL", Some(inner)) => write!(f, "{}!", inner),
+            ("LIST", Some(inner)) => write!(f, "[{}]", inner),
+            _ => write!(f, "{}", self.named()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputValue {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub ty: TypeRef,
+    pub default_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Field {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub args: Vec<InputValue>,
+    #[serde(rename = "type")]
+    pub ty: TypeRef,
+}
+
+impl Field {
+    /// `name(arg: Type, ...): Type`
+    pub fn signature(&self) -> String {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|a| format!("{}: {}", a.name, a.ty))
+            .collect();
+        if args.is_empty() {
+            format!("{}: {}", self.name, self.ty)
+        } else {
+            format!("{}({}): {}", self.name, args.join(", "), self.ty)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnumValue {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaType {
+    pub kind: String,
+    pub name: String,
+    pub description: Option<String>,
+    fields: Option<Vec<Field>>,
+    input_fields: Option<Vec<InputValue>>,
+    enum_values: Option<Vec<EnumValue>>,
+    possible_types: Option<Vec<NamedRef>>,
+}
+
+impl SchemaType {
+    pub fn fields(&self) -> &[Field] {
+        self.fields.as_deref().unwrap_or_default()
+    }
+
+    pub fn input_fields(&self) -> &[InputValue] {
+        self.input_fields.as_deref().unwrap_or_default()
+    }
+
+    pub fn enum_values(&self) -> &[EnumValue] {
+        self.enum_values.as_deref().unwrap_or_default()
+    }
+
+    /// members of a union or implementations of an interface
+    pub fn possible_types(&self) -> impl Iterator<Item = &str> {
+        self.possible_types
+            .iter()
+            .flatten()
+            .map(|t| t.name.as_str())
+    }
+
+    fn field(&self, name: &str) -> Option<&Field> {
+        self.fields().iter().find(|f| f.name == name)
+    }
+}
+
+/// the result of an introspection query
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schema {
+    query_type: Option<NamedRef>,
+    mutation_type: Option<NamedRef>,
+    subscription_type: Option<NamedRef>,
+    pub types: Vec<SchemaType>,
+}
+
+impl Schema {
+    /// reads the `data.__schema` of an introspection response
+    pub fn parse(response: &str) -> Result<Schema, String> {
+        let value: Value =
+            serde_json::from_str(response).map_err(|e| format!("not json: {}", e))?;
+        let Some(schema) = value.pointer("/data/__schema") else {
+            return Err(response_errors(&value)
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| "no __schema in the response".to_owned()));
+        };
+        let mut schema: Schema =
+            serde_json::from_value(schema.clone()).map_err(|e| format!("bad schema: {}", e))?;
+        schema.types.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(schema)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SchemaType> {
+        self.types.iter().find(|t| t.name == name)
+    }
+
+    /// the type behind `query`, `mutation` or `subscription`
+    pub fn root(&self, operation: &str) -> Option<&str> {
+        let root = match operation {
+            "query" => &self.query_type,
+            "mutation" => &self.mutation_type,
+            "subscription" => &self.subscription_type,
+            _ => return None,
+        };
+        root.as_ref().map(|r| r.name.as_str())
+    }
+
+    /// the types declared by the server, without the introspection ones
+    pub fn user_types(&self) -> impl Iterator<Item = &SchemaType> {
+        self.types.iter().filter(|t| !t.name.starts_with("__"))
+    }
+}
+
+/// a suggestion for the word under the cursor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    /// the type or signature, shown on hover
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Name(&'a str),
+    Punct(char),
+    /// strings, numbers and anything else that only matters as a value
+    Value,
+}
+
+/// splits `src` into tokens, skipping whitespace, commas and comments
+fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '#' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                tokens.push(Token::Value);
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                    end = j + c.len_utf8();
+                }
+                tokens.push(Token::Name(&src[i..end]));
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                while chars
+                    .next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '.' || *c == '-')
+                    .is_some()
+                {}
+                tokens.push(Token::Value);
+            }
+            '.' => {
+                while chars.next_if(|(_, c)| *c == '.').is_some() {}
+                tokens.push(Token::Punct('.'));
+            }
+            c if c.is_whitespace() || c == ',' => {}
+            c => tokens.push(Token::Punct(c)),
+        }
+    }
+    tokens
+}
+
+/// what the cursor is inside of
+#[derive(Debug, Clone, PartialEq)]
+enum Context {
+    /// between operations
+    Document,
+    /// a selection set on this type, `None` when the type isn't known
+    Selection(Option<String>),
+    /// the arguments of `field` on `parent`, at a position where a name goes
+    Arguments { parent: String, field: String },
+    /// after `on` in a fragment or inline fragment
+    TypeCondition,
+    /// somewhere nothing useful can be suggested
+    Other,
+}
+
+/// an open argument list while replaying tokens
+struct OpenArgs<'a> {
+    /// the field they belong to, `None` for a directive's arguments
+    field: Option<&'a str>,
+    parent: Option<String>,
+    /// nesting of object and list values
+    depth: usize,
+    expect_name: bool,
+}
+
+/// works out the context at the end of `src` by replaying its tokens
+fn context(schema: &Schema, src: &str) -> Context {
+    // the type of each open selection set
+    let mut scopes: Vec<Option<String>> = Vec::new();
+    // the type the next `{` opens, set by an operation keyword, field or fragment
+    let mut next: Option<Option<String>> = None;
+    // the last field name in the innermost selection set
+    let mut field: Option<&str> = None;
+    let mut args: Option<OpenArgs> = None;
+    let mut variable_defs = 0usize;
+    let mut after_on = false;
+    let mut after_at = false;
+    let mut directive = false;
+
+    for token in tokenize(src) {
+        if let Some(open) = args.as_mut() {
+            match token {
+                Token::Punct(')') if open.depth == 0 => args = None,
+                Token::Punct('{' | '[') => open.depth += 1,
+                Token::Punct('}' | ']') => {
+                    open.depth = open.depth.saturating_sub(1);
+                    open.expect_name = open.depth == 0;
+                }
+                Token::Punct(':') if open.depth == 0 => open.expect_name = false,
+                Token::Name(_) | Token::Value if open.depth == 0 => {
+                    open.expect_name = !open.expect_name;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if variable_defs > 0 {
+            match token {
+                Token::Punct('(') => variable_defs += 1,
+                Token::Punct(')') => variable_defs -= 1,
+                _ => {}
+            }
+            continue;
+        }
+        let current = scopes.last().cloned().flatten();
+        let was_directive = std::mem::take(&mut directive);
+        match token {
+            Token::Name(_) if std::mem::take(&mut after_at) => directive = true,
+            Token::Name(name) if after_on => {
+                next = Some(Some(name.to_owned()));
+                after_on = false;
+            }
+            Token::Name("on") => after_on = true,
+            Token::Name(name) if scopes.is_empty() => match name {
+                "query" | "mutation" | "subscription" => {
+                    next = Some(schema.root(name).map(str::to_owned));
+                }
+                "fragment" => next = Some(None),
+                _ => {}
+            },
+            // an alias is followed by `:` and the field name, which replaces it here
+            Token::Name(name) => {
+                field = Some(name);
+                next = Some(
+                    current
+                        .as_deref()
+                        .and_then(|t| schema.get(t))
+                        .and_then(|t| t.field(name))
+                        .map(|f| f.ty.named().to_owned()),
+                );
+            }
+            Token::Punct('@') => after_at = true,
+            Token::Punct('(') if scopes.is_empty() && !was_directive => variable_defs = 1,
+            Token::Punct('(') => {
+                args = Some(OpenArgs {
+                    field: if was_directive { None } else { field },
+                    parent: current,
+                    depth: 0,
+                    expect_name: true,
+                });
+            }
+            Token::Punct('{') => {
+                let ty = match next.take() {
+                    Some(ty) => ty,
+                    // a bare `{ ... }` is shorthand for a query
+                    None if scopes.is_empty() => schema.root("query").map(str::to_owned),
+                    None => None,
+                };
+                scopes.push(ty);
+                field = None;
+            }
+            Token::Punct('}') => {
+                scopes.pop();
+                field = None;
+                next = None;
+            }
+            // `...` starts a fragment spread or an inline fragment on the current type
+            Token::Punct('.') => next = Some(current),
+            _ => {}
+        }
+    }
+
+    if let Some(open) = args {
+        return match open {
+            OpenArgs {
+                field: Some(field),
+                parent: Some(parent),
+                depth: 0,
+                expect_name: true,
+            } => Context::Arguments {
+                parent,
+                field: field.to_owned(),
+            },
+            _ => Context::Other,
+        };
+    }
+    if after_on {
+        Context::TypeCondition
+    } else if variable_defs > 0 {
+        Context::Other
+    } else if let Some(scope) = scopes.last() {
+        Context::Selection(scope.clone())
+    } else {
+        Context::Document
+    }
+}
+
+/// suggestions for the word ending at char index `cursor` of `query`, along
+/// with the char index that word starts at
+pub fn completions(schema: &Schema, query: &str, cursor: usize) -> (usize, Vec<Completion>) {
+    let before: String = query.chars().take(cursor).collect();
+    let prefix_len = before
+        .chars()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .count();
+    let start = cursor.min(before.chars().count()) - prefix_len;
+    let head: String = before.chars().take(start).collect();
+    // a variable reference, nothing in the schema to offer
+    if head.ends_with('$') {
+        return (start, Vec::new());
+    }
+    let prefix: String = before
+        .chars()
+        .skip(start)
+        .collect::<String>()
+        .to_lowercase();
+
+    let candidates: Vec<Completion> = match context(schema, &head) {
+        Context::Document => ["query", "mutation", "subscription", "fragment"]
+            .into_iter()
+            .map(|k| Completion {
+                label: k.to_owned(),
+                detail: "keyword".to_owned(),
+            })
+            .collect(),
+        Context::Selection(Some(ty)) => schema
+            .get(&ty)
+            .map(|t| {
+                t.fields()
+                    .iter()
+                    .map(|f| Completion {
+                        label: f.name.clone(),
+                        detail: f.signature(),
+                    })
+                    .chain(std::iter::once(Completion {
+                        label: "__typename".to_owned(),
+                        detail: "__typename: String!".to_owned(),
+                    }))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        Context::Arguments { parent, field } => schema
+            .get(&parent)
+            .and_then(|t| t.field(&field))
+            .map(|f| {
+                f.args
+                    .iter()
+                    .map(|a| Completion {
+                        label: a.name.clone(),
+                        detail: format!("{}: {}", a.name, a.ty),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        Context::TypeCondition => schema
+            .user_types()
+            .filter(|t| matches!(t.kind.as_str(), "OBJECT" | "INTERFACE" | "UNION"))
+            .map(|t| Completion {
+                label: t.name.clone(),
+                detail: t.kind.to_lowercase(),
+            })
+            .collect(),
+        Context::Selection(None) | Context::Other => Vec::new(),
+    };
+    let completions = candidates
+        .into_iter()
+        .filter(|c| c.label.to_lowercase().starts_with(&prefix) && c.label.len() > prefix.len())
+        .collect();
+    (start, completions)
+}
+
+/// the `errors` of a graphql response as readable lines, with location and
+/// path when the server gave them
+pub fn response_errors(response: &Value) -> Vec<String> {
+    let Some(errors) = response.get("errors").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    errors
+        .iter()
+        .map(|error| {
+            let mut line = error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+                .unwrap_or_else(|| error.to_string());
+            if let Some(location) = error
+                .get("locations")
+                .and_then(Value::as_array)
+                .and_then(|l| l.first())
+            {
+                let get = |k: &str| location.get(k).and_then(Value::as_u64).unwrap_or(0);
+                line.push_str(&format!(
+                    " (line {}, column {})",
+                    get("line"),
+                    get("column")
+                ));
+            }
+            if let Some(path) = error.get("path").and_then(Value::as_array) {
+                let path: Vec<String> = path
+                    .iter()
+                    .map(|p| match p {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                    .collect();
+                line.push_str(&format!(" at {}", path.join(".")));
+            }
+            line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn named(kind: &str, name: &str) -> Value {
+        json!({"kind": kind, "name": name, "ofType": null})
+    }
+
+    fn non_null(inner: Value) -> Value {
+        json!({"kind": "NON_NULL", "name": null, "ofType": inner})
+    }
+
+    fn list(inner: Value) -> Value {
+        json!({"kind": "LIST", "name": null, "ofType": inner})
+    }
+
+    fn field(name: &str, args: Value, ty: Value) -> Value {
+        json!({"name": name, "description": null, "args": args, "type": ty})
+    }
+
+    fn arg(name: &str, ty: Value) -> Value {
+        json!({"name": name, "description": null, "type": ty, "defaultValue": null})
+    }
+
+    /// a trimmed introspection response for a small user directory
+    fn fixture() -> String {
+        let user = || named("OBJECT", "User");
+        let id = || non_null(named("SCALAR", "ID"));
+        json!({"data": {"__schema": {
+            "queryType": {"name": "Query"},
+            "mutationType": {"name": "Mutation"},
+            "subscriptionType": null,
+            "types": [
+                {"kind": "OBJECT", "name": "Query", "fields": [
+                    field("user", json!([arg("id", id())]), user()),
+                    field("users", json!([
+                        arg("first", named("SCALAR", "Int")),
+                        arg("role", named("ENUM", "Role")),
+                    ]), non_null(list(non_null(user())))),
+                    field("node", json!([arg("id", id())]), named("INTERFACE", "Node")),
+                ]},
+                {"kind": "OBJECT", "name": "Mutation", "fields": [
+                    field("createUser", json!([
+                        arg("input", non_null(named("INPUT_OBJECT", "NewUser"))),
+                    ]), non_null(user())),
+                ]},
+                {"kind": "OBJECT", "name": "User", "description": "someone with an account", "fields": [
+                    field("id", json!([]), id()),
+                    field("name", json!([]), named("SCALAR", "String")),
+                    field("friends", json!([arg("first", named("SCALAR", "Int"))]),
+                        non_null(list(non_null(user())))),
+                ]},
+                {"kind": "INTERFACE", "name": "Node", "fields": [field("id", json!([]), id())],
+                    "possibleTypes": [{"name": "User"}]},
+                {"kind": "ENUM", "name": "Role", "enumValues": [
+                    {"name": "ADMIN", "description": null},
+                    {"name": "GUEST", "description": "read only"},
+                ]},
+                {"kind": "INPUT_OBJECT", "name": "NewUser", "inputFields": [
+                    arg("name", non_null(named("SCALAR", "String"))),
+                ]},
+                {"kind": "SCALAR", "name": "ID"},
+                {"kind": "SCALAR", "name": "String"},
+                {"kind": "SCALAR", "name": "Int"},
+                {"kind": "OBJECT", "name": "__Schema", "fields": []},
+            ],
+        }}})
+        .to_string()
+    }
+
+    fn labels(schema: &Schema, query: &str) -> Vec<String> {
+        let cursor = query.chars().count();
+        completions(schema, query, cursor)
+            .1
+            .into_iter()
+            .map(|c| c.label)
+            .collect()
+    }
+
+    #[test]
+    fn parses_an_introspection_response() {
+        let schema = Schema::parse(&fixture()).unwrap();
+        assert_eq!(schema.root("query"), Some("Query"));
+        assert_eq!(schema.root("mutation"), Some("Mutation"));
+        assert_eq!(schema.root("subscription"), None);
+        // sorted by name, with the introspection types left out of user_types
+        let names: Vec<&str> = schema.user_types().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["ID", "Int", "Mutation", "NewUser", "Node", "Query", "Role", "String", "User"]
+        );
+        assert!(schema.get("__Schema").is_some());
+
+        let query = schema.get("Query").unwrap();
+        let users = &query.fields()[1];
+        assert_eq!(users.ty.to_string(), "[User!]!");
+        assert_eq!(users.ty.named(), "User");
+        assert_eq!(users.signature(), "users(first: Int, role: Role): [User!]!");
+        assert_eq!(query.fields()[0].signature(), "user(id: ID!): User");
+
+        let user = schema.get("User").unwrap();
+        assert_eq!(user.description.as_deref(), Some("someone with an account"));
+        assert_eq!(user.fields()[1].signature(), "name: String");
+        let roles: Vec<&str> = schema
+            .get("Role")
+            .unwrap()
+            .enum_values()
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect();
+        assert_eq!(roles, ["ADMIN", "GUEST"]);
+        assert_eq!(
+            schema.get("NewUser").unwrap().input_fields()[0]
+                .ty
+                .to_string(),
+            "String!"
+        );
+        let nodes: Vec<&str> = schema.get("Node").unwrap().possible_types().collect();
+        assert_eq!(nodes, ["User"]);
+        assert!(schema.get("ID").unwrap().fields().is_empty());
+    }
+
+    #[test]
+    fn reports_why_a_response_has_no_schema() {
+        let errors = r#"{"errors": [{"message": "introspection is disabled",
+            "locations": [{"line": 2, "column": 3}], "path": ["__schema", 0]}]}"#;
+        assert_eq!(
+            Schema::parse(errors).unwrap_err(),
+            "introspection is disabled (line 2, column 3) at __schema.0"
+        );
+        assert_eq!(
+            Schema::parse(r#"{"data": {}}"#).unwrap_err(),
+            "no __schema in the response"
+        );
+        assert!(Schema::parse("<html>").unwrap_err().starts_with("not json"));
+    }
+
+    #[test]
+    fn completes_fields_of_the_type_under_the_cursor() {
+        let schema = Schema::parse(&fixture()).unwrap();
+        assert_eq!(labels(&schema, "query { us"), ["user", "users"]);
+        assert_eq!(
+            labels(&schema, "{ user(id: 1) { "),
+            ["id", "name", "friends", "__typename"]
+        );
+        // through a list, an alias and a nested selection
+        assert_eq!(
+            labels(&schema, "query Q { me: users(first: 2) { friends { na"),
+            ["name"]
+        );
+        assert_eq!(
+            labels(&schema, "mutation { createUser(input: {name: \"x\"}) { i"),
+            ["id"]
+        );
+        // a closed selection set goes back to the parent's fields
+        assert_eq!(labels(&schema, "{ user(id: 1) { id } no"), ["node"]);
+    }
+
+    #[test]
+    fn completes_arguments_keywords_and_type_conditions() {
+        let schema = Schema::parse(&fixture()).unwrap();
+        assert_eq!(labels(&schema, "{ users("), ["first", "role"]);
+        assert_eq!(labels(&schema, "{ users(first: 2, r"), ["role"]);
+        // a value is expected after the colon
+        assert!(labels(&schema, "{ users(first: ").is_empty());
+        assert_eq!(labels(&schema, "mu"), ["mutation"]);
+        assert_eq!(labels(&schema, "{ node(id: 1) { ... on U"), ["User"]);
+        assert_eq!(
+            labels(&schema, "fragment F on "),
+            ["Mutation", "Node", "Query", "User"]
+        );
+        assert!(labels(&schema, "query Q($id: ").is_empty());
+        assert!(labels(&schema, "{ user(id: $i").is_empty());
+        assert!(labels(&schema, "{ nothing { ").is_empty());
+        // comments and strings don't confuse it
+        assert_eq!(labels(&schema, "# {\n{ user(id: \"}\") { na"), ["name"]);
+    }
+
+    #[test]
+    fn completions_start_at_the_word_under_the_cursor() {
+        let schema = Schema::parse(&fixture()).unwrap();
+        let query = "{ usé { na";
+        let (start, found) = completions(&schema, query, query.chars().count());
+        assert_eq!(start, query.chars().count() - 2);
+        assert!(found.is_empty());
+        // the cursor can sit mid-word, and a word that's already complete isn't offered
+        let (start, found) = completions(&schema, "{ users }", 5);
+        assert_eq!(start, 2);
+        assert_eq!(
+            found[1],
+            Completion {
+                label: "users".to_owned(),
+                detail: "users(first: Int, role: Role): [User!]!".to_owned(),
+            }
+        );
+        assert!(labels(&schema, "{ users").is_empty());
+    }
+
+    #[test]
+    fn serializes_the_payload() {
+        let body = GraphqlBody {
+            query: "query Q { id }".to_owned(),
+            variables: " ".to_owned(),
+            operation_name: String::new(),
+        };
+        assert_eq!(body.to_json().unwrap(), r#"{"query":"query Q { id }"}"#);
+        let body = GraphqlBody {
+            variables: r#"{"id": 1}"#.to_owned(),
+            operation_name: "Q".to_owned(),
+            ..body
+        };
+        let payload: Value = serde_json::from_str(&body.to_json().unwrap()).unwrap();
+        assert_eq!(payload["variables"]["id"], 1);
+        assert_eq!(payload["operationName"], "Q");
+        let body = GraphqlBody {
+            variables: "[1]".to_owned(),
+            ..body
+        };
+        assert_eq!(
+            body.to_json().unwrap_err(),
+            "variables must be a json object"
+        );
+    }
+}
@@ -0,0 +1,531 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+use crate::assertions::AssertionResult;
+use crate::body::{BodyMode, RequestBody};
+use crate::collection::SavedRequest;
+use crate::HttpMethod;
+
+/// oldest entries are dropped once either cap is exceeded
+const MAX_ENTRIES: usize = 500;
+const MAX_TOTAL_BYTES: usize = 8 * 1024 * 1024;
+/// response bodies are truncated to this many bytes before being recorded
+const MAX_RESPONSE_BYTES: usize = 64 * 1024;
+
+/// one sent request and what came back, as recorded in `history.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryEntry {
+    /// seconds since the unix epoch
+    pub timestamp: u64,
+    pub method: HttpMethod,
+    /// url with the query parameters applied
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// the body as text, for searching and showing
+    pub body: String,
+    pub body_mode: BodyMode,
+    /// the body as sent, `None` in entries recorded before it was kept
+    pub request_body: Option<RequestBody>,
+    pub status: Option<u16>,
+    pub duration_ms: u64,
+    pub response_body: String,
+    /// how the request's assertions went
+    pub assertions: Vec<AssertionResult>,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        method: HttpMethod,
+        url: &str,
+        queries: &[(String, String)],
+        headers: &[(String, String)],
+        body: &RequestBody,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            method,
+            url: url_with_queries(url, queries),
+            headers: headers
+                .iter()
+                .filter(|(k, _)| !k.is_empty())
+                .cloned()
+                .collect(),
+            body: body.summary(),
+            body_mode: body.mode(),
+            request_body: Some(body.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// puts the body back into `request` in the mode it was sent in. older
+    /// entries only have the text, which comes back as a raw body
+    pub fn restore_body(&self, request: &mut SavedRequest) {
+        match &self.request_body {
+            Some(RequestBody::Raw(text)) => request.body = text.clone(),
+            Some(RequestBody::Form(pairs)) => request.form = pairs.clone(),
+            Some(RequestBody::Multipart(parts)) => request.multipart = parts.clone(),
+            Some(RequestBody::File(file)) => request.binary = file.clone(),
+            Some(RequestBody::Graphql(graphql)) => request.graphql = graphql.clone(),
+            None => request.body = self.body.clone(),
+        }
+        request.body_mode = self.body_mode;
+    }
+
+    pub fn host(&self) -> String {
+        Url::parse(&self.url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_owned))
+            .unwrap_or_default()
+    }
+
+    fn size(&self) -> usize {
+        self.url.len()
+            + self.body.len()
+            + self.response_body.len()
+            // the kept body takes about as much as its text
+            + self.request_body.as_ref().map_or(0, |_| self.body.len())
+            + self
+                .headers
+                .iter()
+                .map(|(k, v)| k.len() + v.len())
+                .sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StatusFilter {
+    #[default]
+    Any,
+    Informational,
+    Success,
+    Redirect,
+    ClientError,
+    ServerError,
+    Failed,
+}
+
+impl StatusFilter {
+    pub const ALL: [StatusFilter; 7] = [
+        StatusFilter::Any,
+        StatusFilter::Informational,
+        StatusFilter::Success,
+        StatusFilter::Redirect,
+        StatusFilter::ClientError,
+        StatusFilter::ServerError,
+        StatusFilter::Failed,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatusFilter::Any => "any status",
+            StatusFilter::Informational => "1xx",
+            StatusFilter::Success => "2xx",
+            StatusFilter::Redirect => "3xx",
+            StatusFilter::ClientError => "4xx",
+            StatusFilter::ServerError => "5xx",
+            StatusFilter::Failed => "failed",
+        }
+    }
+
+    fn matches(&self, status: Option<u16>) -> bool {
+        match (self, status) {
+            (StatusFilter::Any, _) => true,
+            (StatusFilter::Failed, status) => status.is_none(),
+            (_, None) => false,
+            (StatusFilter::Informational, Some(s)) => (100..200).contains(&s),
+            (StatusFilter::Success, Some(s)) => (200..300).contains(&s),
+            (StatusFilter::Redirect, Some(s)) => (300..400).contains(&s),
+            (StatusFilter::ClientError, Some(s)) => (400..500).contains(&s),
+            (StatusFilter::ServerError, Some(s)) => (500..600).contains(&s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub search: String,
+    pub method: Option<HttpMethod>,
+    pub status: StatusFilter,
+    pub host: String,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        if self.method.as_ref().is_some_and(|m| *m != entry.method) {
+            return false;
+        }
+        if !self.status.matches(entry.status) {
+            return false;
+        }
+        if !self.host.is_empty() && !contains_ignore_case(&entry.host(), &self.host) {
+            return false;
+        }
+        self.search.is_empty()
+            || contains_ignore_case(&entry.url, &self.search)
+            || contains_ignore_case(&entry.body, &self.search)
+            || contains_ignore_case(&entry.response_body, &self.search)
+    }
+}
+
+/// sent requests, oldest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, contents)
+    }
+
+    pub fn push(&mut self, mut entry: HistoryEntry) {
+        truncate_utf8(&mut entry.response_body, MAX_RESPONSE_BYTES);
+        self.entries.push(entry);
+        let mut drop = self.entries.len().saturating_sub(MAX_ENTRIES);
+        let mut total: usize = self.entries[drop..].iter().map(HistoryEntry::size).sum();
+        while total > MAX_TOTAL_BYTES && drop < self.entries.len() - 1 {
+            total -= self.entries[drop].size();
+            drop += 1;
+        }
+        self.entries.drain(..drop);
+    }
+}
+
+/// saves history on a background thread, so writing the file never holds up
+/// the ui. snapshots queued while one is being written collapse into the newest
+pub struct HistoryWriter {
+    tx: Option<Sender<History>>,
+    results: Receiver<io::Result<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HistoryWriter {
+    pub fn new(path: PathBuf) -> io::Result<Self> {
+        let (tx, rx) = channel::<History>();
+        let (results_tx, results) = channel();
+        let thread = thread::Builder::new()
+            .name("snooze-history".to_owned())
+            .spawn(move || {
+                while let Ok(mut history) = rx.recv() {
+                    while let Ok(newer) = rx.try_recv() {
+                        history = newer;
+                    }
+                    if results_tx.send(history.save(&path)).is_err() {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self {
+            tx: Some(tx),
+            results,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn save(&self, history: &History) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(history.clone());
+        }
+    }
+
+    /// how the latest finished save went, if one finished since the last call
+    pub fn poll(&self) -> Option<io::Result<()>> {
+        self.results.try_iter().last()
+    }
+}
+
+impl Drop for HistoryWriter {
+    /// finishes the pending save, so closing right after a response keeps it
+    fn drop(&mut self) {
+        self.tx.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// `url` with the non-empty query pairs appended, as the worker would send it
+pub fn url_with_queries(url: &str, queries: &[(String, String)]) -> String {
+    let pairs: Vec<&(String, String)> = queries.iter().filter(|(k, _)| !k.is_empty()).collect();
+    match Url::parse(url) {
+        Ok(mut parsed) if !pairs.is_empty() => {
+            parsed
+                .query_pairs_mut()
+                .extend_pairs(pairs.iter().map(|(k, v)| (k, v)));
+            parsed.into()
+        }
+        _ => url.to_owned(),
+    }
+}
+
+/// splits the query string off `url` into editable key/value pairs
+pub fn split_queries(url: &str) -> (String, Vec<(String, String)>) {
+    match Url::parse(url) {
+        Ok(mut parsed) if parsed.query().is_some() => {
+            let queries = parsed
+                .query_pairs()
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect();
+            parsed.set_query(None);
+            (parsed.into(), queries)
+        }
+        _ => (url.to_owned(), Vec::new()),
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+fn truncate_utf8(s: &mut String, max: usize) {
+    if s.len() > max {
+        let mut end = max;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::{FormPart, PartKind};
+
+    fn entry(url: &str, body_bytes: usize) -> HistoryEntry {
+        HistoryEntry {
+            url: url.to_owned(),
+            body: "x".repeat(body_bytes),
+            ..Default::default()
+        }
+    }
+
+    fn queries(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn drops_the_oldest_entries_past_the_count_cap() {
+        let mut history = History::default();
+        for i in 0..MAX_ENTRIES + 3 {
+            history.push(entry(&format!("http://h/{}", i), 0));
+        }
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries[0].url, "http://h/3");
+    }
+
+    #[test]
+    fn drops_the_oldest_entries_past_the_byte_cap_but_keeps_the_newest() {
+        let mut history = History::default();
+        for i in 0..4 {
+            history.push(entry(&format!("http://h/{}", i), 3 * 1024 * 1024));
+        }
+        let urls: Vec<&str> = history.entries.iter().map(|e| e.url.as_str()).collect();
+        assert_eq!(urls, ["http://h/2", "http://h/3"]);
+
+        history.push(entry("http://h/huge", MAX_TOTAL_BYTES + 1));
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.entries[0].url, "http://h/huge");
+    }
+
+    #[test]
+    fn entries_dropped_by_count_dont_count_towards_the_byte_cap() {
+        let mut history = History::default();
+        for _ in 0..MAX_ENTRIES {
+            history.push(entry("http://h/", 1000));
+        }
+        let small = history.entries[0].size();
+        // fills the byte cap exactly once the oldest entry goes for the count cap
+        let big = MAX_TOTAL_BYTES - (MAX_ENTRIES - 1) * small;
+        history.push(entry("http://h/big", big - "http://h/big".len()));
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries.last().unwrap().url, "http://h/big");
+    }
+
+    #[test]
+    fn truncates_recorded_response_bodies_on_a_char_boundary() {
+        let mut history = History::default();
+        let mut long = entry("http://h/", 0);
+        long.response_body = format!("a{}", "é".repeat(MAX_RESPONSE_BYTES));
+        history.push(long);
+        let body = &history.entries[0].response_body;
+        assert_eq!(body.len(), MAX_RESPONSE_BYTES - 1);
+        assert!(body.ends_with('é'));
+    }
+
+    fn matches(entry: &HistoryEntry, f: fn(&mut HistoryFilter)) -> bool {
+        let mut filter = HistoryFilter::default();
+        f(&mut filter);
+        filter.matches(entry)
+    }
+
+    #[test]
+    fn filters_by_method_status_host_and_search() {
+        let mut entry = HistoryEntry::new(
+            HttpMethod::POST,
+            "https://API.example.com/users",
+            &[],
+            &[],
+            &RequestBody::Raw(r#"{"name": "Ann"}"#.to_owned()),
+        );
+        entry.status = Some(404);
+        entry.response_body = "not found".to_owned();
+
+        assert!(HistoryFilter::default().matches(&entry));
+        assert!(matches(&entry, |f| f.method = Some(HttpMethod::POST)));
+        assert!(!matches(&entry, |f| f.method = Some(HttpMethod::GET)));
+        assert!(matches(&entry, |f| f.status = StatusFilter::ClientError));
+        assert!(!matches(&entry, |f| f.status = StatusFilter::Success));
+        assert!(!matches(&entry, |f| f.status = StatusFilter::Failed));
+        assert!(matches(&entry, |f| f.host = "Example".to_owned()));
+        assert!(!matches(&entry, |f| f.host = "other.org".to_owned()));
+        assert!(matches(&entry, |f| f.search = "/USERS".to_owned()));
+        assert!(matches(&entry, |f| f.search = "ann".to_owned()));
+        assert!(matches(&entry, |f| f.search = "NOT FOUND".to_owned()));
+        assert!(!matches(&entry, |f| f.search = "bob".to_owned()));
+
+        entry.status = None;
+        assert!(matches(&entry, |f| f.status = StatusFilter::Failed));
+        assert!(!matches(&entry, |f| f.status = StatusFilter::ClientError));
+    }
+
+    #[test]
+    fn restores_bodies_in_the_mode_they_were_sent_in() {
+        let parts = vec![
+            FormPart {
+                name: "name".to_owned(),
+                value: "ann".to_owned(),
+                ..Default::default()
+            },
+            FormPart {
+                name: "avatar".to_owned(),
+                kind: PartKind::File,
+                value: "/tmp/me.png".to_owned(),
+                ..Default::default()
+            },
+        ];
+        let multipart = RequestBody::Multipart(parts.clone());
+        let entry = HistoryEntry::new(HttpMethod::POST, "http://h/", &[], &[], &multipart);
+        assert_eq!(entry.body, "name=ann\navatar=@/tmp/me.png");
+        // through the history file and back
+        let json = serde_json::to_string(&History {
+            entries: vec![entry],
+        })
+        .unwrap();
+        let entry = serde_json::from_str::<History>(&json)
+            .unwrap()
+            .entries
+            .remove(0);
+        let mut request = SavedRequest {
+            body: "left alone".to_owned(),
+            ..Default::default()
+        };
+        entry.restore_body(&mut request);
+        assert_eq!(request.body_mode, BodyMode::Multipart);
+        assert_eq!(request.multipart, parts);
+        assert_eq!(request.body, "left alone");
+
+        let pairs = queries(&[("q", "a b"), ("page", "2")]);
+        let form = RequestBody::Form(pairs.clone());
+        let entry = HistoryEntry::new(HttpMethod::POST, "http://h/", &[], &[], &form);
+        assert_eq!(entry.body, "q=a+b&page=2");
+        let mut request = SavedRequest::default();
+        entry.restore_body(&mut request);
+        assert_eq!(request.body_mode, BodyMode::FormUrlEncoded);
+        assert_eq!(request.form, pairs);
+    }
+
+    #[test]
+    fn restores_entries_from_before_bodies_were_kept_as_raw_text() {
+        let history: History = serde_json::from_str(
+            r#"[{"timestamp": 1, "method": "POST", "url": "http://h/", "body": "a=1"}]"#,
+        )
+        .unwrap();
+        let mut request = SavedRequest {
+            body_mode: BodyMode::Graphql,
+            ..Default::default()
+        };
+        history.entries[0].restore_body(&mut request);
+        assert_eq!(request.body_mode, BodyMode::Raw);
+        assert_eq!(request.body, "a=1");
+    }
+
+    #[test]
+    fn appends_non_empty_queries_to_the_url() {
+        let pairs = queries(&[("q", "a b&c"), ("", "skipped"), ("page", "2")]);
+        assert_eq!(
+            url_with_queries("https://example.com/search?lang=en", &pairs),
+            "https://example.com/search?lang=en&q=a+b%26c&page=2"
+        );
+        assert_eq!(
+            url_with_queries("https://example.com/", &[]),
+            "https://example.com/"
+        );
+        // left alone when it isn't a url yet, e.g. still holding a placeholder
+        assert_eq!(
+            url_with_queries("{{base}}/search", &pairs),
+            "{{base}}/search"
+        );
+    }
+
+    #[test]
+    fn splits_queries_off_the_url() {
+        assert_eq!(
+            split_queries("https://example.com/search?q=a+b%26c&page=2"),
+            (
+                "https://example.com/search".to_owned(),
+                queries(&[("q", "a b&c"), ("page", "2")])
+            )
+        );
+        assert_eq!(
+            split_queries("https://example.com/"),
+            ("https://example.com/".to_owned(), Vec::new())
+        );
+        let url = "https://example.com/search?q=rust";
+        let (base, pairs) = split_queries(url);
+        assert_eq!(url_with_queries(&base, &pairs), url);
+    }
+
+    #[test]
+    fn writer_saves_the_newest_history_before_it_is_dropped() {
+        let dir = std::env::temp_dir().join(format!("snooze-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.json");
+        let writer = HistoryWriter::new(path.clone()).unwrap();
+        let mut history = History::default();
+        for i in 0..3 {
+            history.push(entry(&format!("http://h/{}", i), 10));
+            writer.save(&history);
+        }
+        drop(writer);
+        let saved = History::load(&path).unwrap();
+        assert_eq!(saved.entries.len(), 3);
+        assert_eq!(saved.entries[2].url, "http://h/2");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
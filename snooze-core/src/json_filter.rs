@@ -0,0 +1,978 @@
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// filters `input` with `expr`. expressions starting with `$` are jsonpath
+/// and give the array of everything they match; anything else is read as a
+/// jq subset (paths, `|`, `,`, `[...]`, comparisons, `and`/`or`, `map`,
+/// `select`, `keys`, `length`, `not`) whose single result is given as-is and
+/// several as an array
+pub fn apply(expr: &str, input: &Value) -> Result<Value, String> {
+    let tokens = tokenize(expr)?;
+    let jsonpath = tokens.first() == Some(&Token::Dollar);
+    let mut parser = Parser { tokens, pos: 0 };
+    let filter = if jsonpath {
+        parser.jsonpath()?
+    } else {
+        parser.pipe()?
+    };
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        return Err(format!("unexpected {}", token));
+    }
+    let mut results = eval(&filter, input, !jsonpath)?;
+    if !jsonpath && results.len() == 1 {
+        return Ok(results.remove(0));
+    }
+    Ok(Value::Array(results))
+}
+
+/// one step from a json value into a child
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `path` as jsonpath, e.g. `$.items[0]['first name']`
+pub fn to_jsonpath(path: &[PathSegment]) -> String {
+    let mut out = "$".to_owned();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) if is_identifier(key) => out.push_str(&format!(".{}", key)),
+            PathSegment::Key(key) => out.push_str(&format!(
+                "['{}']",
+                key.replace('\\', "\\\\").replace('\'', "\\'")
+            )),
+            PathSegment::Index(i) => out.push_str(&format!("[{}]", i)),
+        }
+    }
+    out
+}
+
+/// `path` as a jq path, e.g. `.items[0]["first name"]`, or `.` for the root
+pub fn to_jq(path: &[PathSegment]) -> String {
+    if path.is_empty() {
+        return ".".to_owned();
+    }
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) if is_identifier(key) => format!(".{}", key),
+            PathSegment::Key(key) => format!("[{}]", Value::from(key.as_str())),
+            PathSegment::Index(i) => format!("[{}]", i),
+        })
+        .collect()
+}
+
+/// `path` as a javascript accessor on the parsed body, e.g.
+/// `data.items[0]["first name"]` after `const data = await response.json()`
+pub fn to_js(path: &[PathSegment]) -> String {
+    match path {
+        [] => "data".to_owned(),
+        _ => format!("data{}", to_jq(path)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    DotDot,
+    /// `.name` with nothing between the dot and the name
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Dollar,
+    At,
+    Star,
+    Question,
+    Pipe,
+    Comma,
+    Colon,
+    Bang,
+    AndAnd,
+    OrOr,
+    Cmp(CmpOp),
+    Open(char),
+    Close(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Dot => write!(f, "`.`"),
+            Token::DotDot => write!(f, "`..`"),
+            Token::Field(name) => write!(f, "`.{}`", name),
+            Token::Ident(name) => write!(f, "`{}`", name),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Num(n) => write!(f, "`{}`", n),
+            Token::Dollar => write!(f, "`$`"),
+            Token::At => write!(f, "`@`"),
+            Token::Star => write!(f, "`*`"),
+            Token::Question => write!(f, "`?`"),
+            Token::Pipe => write!(f, "`|`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::Colon => write!(f, "`:`"),
+            Token::Bang => write!(f, "`!`"),
+            Token::AndAnd => write!(f, "`&&`"),
+            Token::OrOr => write!(f, "`||`"),
+            Token::Cmp(op) => write!(f, "`{}`", op.symbol()),
+            Token::Open(c) | Token::Close(c) => write!(f, "`{}`", c),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn symbol(&self) -> &'static str {
+        match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        }
+    }
+
+    fn test(&self, a: &Value, b: &Value) -> bool {
+        let ordering = compare(a, b);
+        match self {
+            CmpOp::Eq => ordering == Ordering::Equal,
+            CmpOp::Ne => ordering != Ordering::Equal,
+            CmpOp::Lt => ordering == Ordering::Less,
+            CmpOp::Le => ordering != Ordering::Greater,
+            CmpOp::Gt => ordering == Ordering::Greater,
+            CmpOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let is_ident_start = |c: char| c.is_ascii_alphabetic() || c == '_';
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let (token, len) = match (c, next) {
+            ('.', Some('.')) => (Token::DotDot, 2),
+            ('.', Some(n)) if is_ident_start(n) => {
+                let len = chars[i + 1..].iter().take_while(|c| is_ident(**c)).count();
+                let name = chars[i + 1..i + 1 + len].iter().collect();
+                (Token::Field(name), len + 1)
+            }
+            ('.', _) => (Token::Dot, 1),
+            ('"' | '\'', _) => {
+                let mut s = String::new();
+                let mut j = i + 1;
+                loop {
+                    match chars.get(j) {
+                        None => return Err("unterminated string".to_owned()),
+                        Some(q) if *q == c => break,
+                        Some('\\') => {
+                            j += 1;
+                            match chars.get(j) {
+                                Some('n') => s.push('\n'),
+                                Some('t') => s.push('\t'),
+                                Some('r') => s.push('\r'),
+                                Some(other) => s.push(*other),
+                                None => return Err("unterminated string".to_owned()),
+                            }
+                        }
+                        Some(other) => s.push(*other),
+                    }
+                    j += 1;
+                }
+                (Token::Str(s), j + 1 - i)
+            }
+            (c, _)
+                if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                let digits = |from: usize| {
+                    chars[from..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_digit())
+                        .count()
+                };
+                let mut len = 1 + digits(i + 1);
+                // `1.5` but not the `.` of `[1].a`
+                if chars.get(i + len) == Some(&'.')
+                    && chars.get(i + len + 1).is_some_and(|c| c.is_ascii_digit())
+                {
+                    len += 1 + digits(i + len + 1);
+                }
+                let text: String = chars[i..i + len].iter().collect();
+                let n = text
+                    .parse()
+                    .map_err(|_| format!("invalid number `{}`", text))?;
+                (Token::Num(n), len)
+            }
+            (c, _) if is_ident_start(c) => {
+                let len = chars[i..].iter().take_while(|c| is_ident(**c)).count();
+                (Token::Ident(chars[i..i + len].iter().collect()), len)
+            }
+            ('=', Some('=')) => (Token::Cmp(CmpOp::Eq), 2),
+            ('!', Some('=')) => (Token::Cmp(CmpOp::Ne), 2),
+            ('<', Some('=')) => (Token::Cmp(CmpOp::Le), 2),
+            ('>', Some('=')) => (Token::Cmp(CmpOp::Ge), 2),
+            ('<', _) => (Token::Cmp(CmpOp::Lt), 1),
+            ('>', _) => (Token::Cmp(CmpOp::Gt), 1),
+            ('&', Some('&')) => (Token::AndAnd, 2),
+            ('|', Some('|')) => (Token::OrOr, 2),
+            ('|', _) => (Token::Pipe, 1),
+            ('!', _) => (Token::Bang, 1),
+            ('$', _) => (Token::Dollar, 1),
+            ('@', _) => (Token::At, 1),
+            ('*', _) => (Token::Star, 1),
+            ('?', _) => (Token::Question, 1),
+            (',', _) => (Token::Comma, 1),
+            (':', _) => (Token::Colon, 1),
+            ('(' | '[', _) => (Token::Open(c), 1),
+            (')' | ']', _) => (Token::Close(c), 1),
+            (c, _) => return Err(format!("unexpected `{}`", c)),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Identity,
+    Field(String),
+    Index(i64),
+    /// `[from:to]`, giving the sub-array
+    Slice(Option<i64>, Option<i64>),
+    /// every element of an array or value of an object
+    Iterate,
+    /// the input and everything nested in it
+    Recurse,
+    Literal(Value),
+    /// `[f]`, every output of `f` gathered into one array
+    Collect(Box<Filter>),
+    Pipe(Box<Filter>, Box<Filter>),
+    Comma(Box<Filter>, Box<Filter>),
+    Compare(Box<Filter>, CmpOp, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Map(Box<Filter>),
+    Select(Box<Filter>),
+    Keys,
+    Length,
+}
+
+fn pipe(a: Filter, b: Filter) -> Filter {
+    match (a, b) {
+        (Filter::Identity, b) => b,
+        (a, Filter::Identity) => a,
+        (a, b) => Filter::Pipe(Box::new(a), Box::new(b)),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(found) if found == token => Ok(()),
+            Some(found) => Err(format!("expected {} but found {}", token, found)),
+            None => Err(format!("expected {}", token)),
+        }
+    }
+
+    // jq, loosest binding first
+
+    fn pipe(&mut self) -> Result<Filter, String> {
+        let mut filter = self.comma()?;
+        while self.eat(&Token::Pipe) {
+            filter = pipe(filter, self.comma()?);
+        }
+        Ok(filter)
+    }
+
+    fn comma(&mut self) -> Result<Filter, String> {
+        let mut filter = self.or()?;
+        while self.eat(&Token::Comma) {
+            filter = Filter::Comma(Box::new(filter), Box::new(self.or()?));
+        }
+        Ok(filter)
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        while self.eat(&Token::Ident("or".to_owned())) {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.comparison()?;
+        while self.eat(&Token::Ident("and".to_owned())) {
+            filter = Filter::And(Box::new(filter), Box::new(self.comparison()?));
+        }
+        Ok(filter)
+    }
+
+    fn comparison(&mut self) -> Result<Filter, String> {
+        let left = self.postfix()?;
+        if let Some(Token::Cmp(op)) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.postfix()?;
+            return Ok(Filter::Compare(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn postfix(&mut self) -> Result<Filter, String> {
+        let mut filter = self.term()?;
+        loop {
+            match self.peek() {
+                Some(Token::Field(name)) => {
+                    let name = name.clone();
+                    self.pos += 1;
+                    filter = pipe(filter, Filter::Field(name));
+                }
+                Some(Token::Dot)
+                    if matches!(self.tokens.get(self.pos + 1), Some(Token::Str(_))) =>
+                {
+                    self.pos += 1;
+                    let Some(Token::Str(name)) = self.next() else {
+                        unreachable!()
+                    };
+                    filter = pipe(filter, Filter::Field(name));
+                }
+                Some(Token::Dot) if self.tokens.get(self.pos + 1) == Some(&Token::Open('[')) => {
+                    self.pos += 1;
+                }
+                Some(Token::Open('[')) => {
+                    self.pos += 1;
+                    filter = pipe(filter, self.jq_brackets()?);
+                }
+                Some(Token::Question) => self.pos += 1,
+                _ => return Ok(filter),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Filter, String> {
+        match self.next() {
+            Some(Token::Dot) => match self.peek().cloned() {
+                Some(Token::Str(name)) => {
+                    self.pos += 1;
+                    Ok(Filter::Field(name))
+                }
+                _ => Ok(Filter::Identity),
+            },
+            Some(Token::DotDot) => Ok(Filter::Recurse),
+            Some(Token::Field(name)) => Ok(Filter::Field(name)),
+            Some(Token::Num(n)) => Ok(Filter::Literal(number(n))),
+            Some(Token::Str(s)) => Ok(Filter::Literal(Value::String(s))),
+            Some(Token::Open('(')) => {
+                let filter = self.pipe()?;
+                self.expect(Token::Close(')'))?;
+                Ok(filter)
+            }
+            Some(Token::Open('[')) => {
+                if self.eat(&Token::Close(']')) {
+                    return Ok(Filter::Literal(Value::Array(Vec::new())));
+                }
+                let filter = self.pipe()?;
+                self.expect(Token::Close(']'))?;
+                Ok(Filter::Collect(Box::new(filter)))
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Filter::Literal(Value::Bool(true))),
+                "false" => Ok(Filter::Literal(Value::Bool(false))),
+                "null" => Ok(Filter::Literal(Value::Null)),
+                "keys" => Ok(Filter::Keys),
+                "length" => Ok(Filter::Length),
+                "not" => Ok(Filter::Not(Box::new(Filter::Identity))),
+                "map" | "select" => {
+                    self.expect(Token::Open('('))?;
+                    let inner = Box::new(self.pipe()?);
+                    self.expect(Token::Close(')'))?;
+                    Ok(match name.as_str() {
+                        "map" => Filter::Map(inner),
+                        _ => Filter::Select(inner),
+                    })
+                }
+                _ => Err(format!("unknown function `{}`", name)),
+            },
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("unexpected end of filter".to_owned()),
+        }
+    }
+
+    /// what follows `[` after a path: `]`, an index, a key or a slice
+    fn jq_brackets(&mut self) -> Result<Filter, String> {
+        if self.eat(&Token::Close(']')) {
+            return Ok(Filter::Iterate);
+        }
+        let filter = match self.next() {
+            Some(Token::Str(name)) => Filter::Field(name),
+            Some(Token::Num(n)) if self.peek() != Some(&Token::Colon) => Filter::Index(n as i64),
+            Some(Token::Num(n)) => {
+                self.pos += 1;
+                Filter::Slice(Some(n as i64), self.slice_end()?)
+            }
+            Some(Token::Colon) => Filter::Slice(None, self.slice_end()?),
+            Some(token) => return Err(format!("unexpected {} in brackets", token)),
+            None => return Err("unexpected end of filter".to_owned()),
+        };
+        self.expect(Token::Close(']'))?;
+        Ok(filter)
+    }
+
+    fn slice_end(&mut self) -> Result<Option<i64>, String> {
+        match self.peek() {
+            Some(Token::Num(n)) => {
+                let n = *n as i64;
+                self.pos += 1;
+                Ok(Some(n))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // jsonpath
+
+    fn jsonpath(&mut self) -> Result<Filter, String> {
+        self.expect(Token::Dollar)?;
+        self.jsonpath_segments(Filter::Identity)
+    }
+
+    fn jsonpath_segments(&mut self, mut filter: Filter) -> Result<Filter, String> {
+        loop {
+            let segment = match self.peek() {
+                Some(Token::Field(name)) => {
+                    let name = name.clone();
+                    self.pos += 1;
+                    Filter::Field(name)
+                }
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Star) => Filter::Iterate,
+                        Some(token) => return Err(format!("unexpected {} after `.`", token)),
+                        None => return Err("expected a name after `.`".to_owned()),
+                    }
+                }
+                Some(Token::DotDot) => {
+                    self.pos += 1;
+                    let child = match self.peek() {
+                        Some(Token::Ident(name)) => {
+                            let name = name.clone();
+                            self.pos += 1;
+                            Filter::Field(name)
+                        }
+                        Some(Token::Star) => {
+                            self.pos += 1;
+                            Filter::Iterate
+                        }
+                        Some(Token::Open('[')) => {
+                            self.pos += 1;
+                            self.jsonpath_brackets()?
+                        }
+                        _ => return Err("expected a name after `..`".to_owned()),
+                    };
+                    pipe(Filter::Recurse, child)
+                }
+                Some(Token::Open('[')) => {
+                    self.pos += 1;
+                    self.jsonpath_brackets()?
+                }
+                _ => return Ok(filter),
+            };
+            filter = pipe(filter, segment);
+        }
+    }
+
+    /// what follows `[` in jsonpath: `*`, names, indexes, a slice or a `?()` filter
+    fn jsonpath_brackets(&mut self) -> Result<Filter, String> {
+        let filter = match self.peek() {
+            Some(Token::Star) => {
+                self.pos += 1;
+                Filter::Iterate
+            }
+            Some(Token::Question) => {
+                self.pos += 1;
+                self.expect(Token::Open('('))?;
+                let condition = self.condition_or()?;
+                self.expect(Token::Close(')'))?;
+                pipe(Filter::Iterate, Filter::Select(Box::new(condition)))
+            }
+            Some(Token::Colon) => {
+                self.pos += 1;
+                pipe(Filter::Slice(None, self.slice_end()?), Filter::Iterate)
+            }
+            Some(Token::Num(n)) if self.tokens.get(self.pos + 1) == Some(&Token::Colon) => {
+                let from = *n as i64;
+                self.pos += 2;
+                pipe(
+                    Filter::Slice(Some(from), self.slice_end()?),
+                    Filter::Iterate,
+                )
+            }
+            _ => {
+                let mut selectors = Vec::new();
+                loop {
+                    selectors.push(match self.next() {
+                        Some(Token::Str(name)) => Filter::Field(name),
+                        Some(Token::Num(n)) => Filter::Index(n as i64),
+                        Some(token) => return Err(format!("unexpected {} in brackets", token)),
+                        None => return Err("unexpected end of filter".to_owned()),
+                    });
+                    if !self.eat(&Token::Comma) {
+                        break;
+                    }
+                }
+                selectors
+                    .into_iter()
+                    .reduce(|a, b| Filter::Comma(Box::new(a), Box::new(b)))
+                    .unwrap_or(Filter::Identity)
+            }
+        };
+        self.expect(Token::Close(']'))?;
+        Ok(filter)
+    }
+
+    fn condition_or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.condition_and()?;
+        while self.eat(&Token::OrOr) {
+            filter = Filter::Or(Box::new(filter), Box::new(self.condition_and()?));
+        }
+        Ok(filter)
+    }
+
+    fn condition_and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.condition()?;
+        while self.eat(&Token::AndAnd) {
+            filter = Filter::And(Box::new(filter), Box::new(self.condition()?));
+        }
+        Ok(filter)
+    }
+
+    fn condition(&mut self) -> Result<Filter, String> {
+        if self.eat(&Token::Bang) {
+            return Ok(Filter::Not(Box::new(self.condition()?)));
+        }
+        if self.eat(&Token::Open('(')) {
+            let filter = self.condition_or()?;
+            self.expect(Token::Close(')'))?;
+            return Ok(filter);
+        }
+        let left = self.condition_operand()?;
+        if let Some(Token::Cmp(op)) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.condition_operand()?;
+            return Ok(Filter::Compare(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn condition_operand(&mut self) -> Result<Filter, String> {
+        match self.next() {
+            Some(Token::At) => self.jsonpath_segments(Filter::Identity),
+            Some(Token::Dollar) => Err("`$` inside a filter is not supported".to_owned()),
+            Some(Token::Num(n)) => Ok(Filter::Literal(number(n))),
+            Some(Token::Str(s)) => Ok(Filter::Literal(Value::String(s))),
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Filter::Literal(Value::Bool(true))),
+                "false" => Ok(Filter::Literal(Value::Bool(false))),
+                "null" => Ok(Filter::Literal(Value::Null)),
+                _ => Err(format!("unexpected `{}` in filter", name)),
+            },
+            Some(token) => Err(format!("unexpected {} in filter", token)),
+            None => Err("unexpected end of filter".to_owned()),
+        }
+    }
+}
+
+/// whole numbers stay integers so they print without a trailing `.0`
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+/// jq's ordering: null, false, true, numbers, strings, arrays, objects
+fn compare(a: &Value, b: &Value) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+    };
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(x, y))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        (Value::Object(_), Value::Object(_)) if a == b => Ordering::Equal,
+        (Value::Object(_), Value::Object(_)) => a.to_string().cmp(&b.to_string()),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// runs `filter` on `input`. jq is `strict`: indexing the wrong type is an
+/// error and missing keys give null. jsonpath just matches nothing there
+fn eval(filter: &Filter, input: &Value, strict: bool) -> Result<Vec<Value>, String> {
+    let mismatch = |what: String| {
+        if strict {
+            Err(format!("cannot {} {}", what, type_name(input)))
+        } else {
+            Ok(Vec::new())
+        }
+    };
+    let missing = || {
+        if strict {
+            vec![Value::Null]
+        } else {
+            Vec::new()
+        }
+    };
+    match filter {
+        Filter::Identity => Ok(vec![input.clone()]),
+        Filter::Field(name) => match input {
+            Value::Object(map) => Ok(map.get(name).cloned().map_or_else(missing, |v| vec![v])),
+            Value::Null => Ok(missing()),
+            _ => mismatch(format!("get field \"{}\" of", name)),
+        },
+        Filter::Index(index) => match input {
+            Value::Array(items) => {
+                let index = if *index < 0 {
+                    items.len() as i64 + index
+                } else {
+                    *index
+                };
+                let item = usize::try_from(index).ok().and_then(|i| items.get(i));
+                Ok(item.cloned().map_or_else(missing, |v| vec![v]))
+            }
+            Value::Null => Ok(missing()),
+            _ => mismatch(format!("get index {} of", index)),
+        },
+        Filter::Slice(from, to) => match input {
+            Value::Array(items) => {
+                let len = items.len() as i64;
+                let clamp = |i: i64| (if i < 0 { len + i } else { i }).clamp(0, len) as usize;
+                let from = clamp(from.unwrap_or(0));
+                let to = clamp(to.unwrap_or(len)).max(from);
+                Ok(vec![Value::Array(items[from..to].to_vec())])
+            }
+            Value::Null => Ok(missing()),
+            _ => mismatch("slice".to_owned()),
+        },
+        Filter::Iterate => match input {
+            Value::Array(items) => Ok(items.clone()),
+            Value::Object(map) => Ok(map.values().cloned().collect()),
+            _ => mismatch("iterate over".to_owned()),
+        },
+        Filter::Recurse => {
+            let mut out = Vec::new();
+            let mut stack = vec![input];
+            while let Some(value) = stack.pop() {
+                out.push(value.clone());
+                match value {
+                    Value::Array(items) => stack.extend(items.iter().rev()),
+                    Value::Object(map) => stack.extend(map.values().rev()),
+                    _ => {}
+                }
+            }
+            Ok(out)
+        }
+        Filter::Literal(value) => Ok(vec![value.clone()]),
+        Filter::Collect(inner) => Ok(vec![Value::Array(eval(inner, input, strict)?)]),
+        Filter::Pipe(a, b) => {
+            let mut out = Vec::new();
+            for value in eval(a, input, strict)? {
+                out.extend(eval(b, &value, strict)?);
+            }
+            Ok(out)
+        }
+        Filter::Comma(a, b) => {
+            let mut out = eval(a, input, strict)?;
+            out.extend(eval(b, input, strict)?);
+            Ok(out)
+        }
+        Filter::Compare(a, op, b) => {
+            let right = eval(b, input, strict)?;
+            let mut out = Vec::new();
+            for left in eval(a, input, strict)? {
+                out.extend(right.iter().map(|r| Value::Bool(op.test(&left, r))));
+            }
+            Ok(out)
+        }
+        Filter::And(a, b) | Filter::Or(a, b) => {
+            let is_and = matches!(filter, Filter::And(..));
+            let mut out = Vec::new();
+            for left in eval(a, input, strict)? {
+                // `false and x` and `true or x` don't need x
+                if truthy(&left) != is_and {
+                    out.push(Value::Bool(!is_and));
+                    continue;
+                }
+                for right in eval(b, input, strict)? {
+                    out.push(Value::Bool(truthy(&right)));
+                }
+            }
+            Ok(out)
+        }
+        Filter::Not(inner) => {
+            let values = eval(inner, input, strict)?;
+            // a jsonpath `!@.key` matches where the key is missing
+            if values.is_empty() {
+                return Ok(vec![Value::Bool(true)]);
+            }
+            Ok(values.iter().map(|v| Value::Bool(!truthy(v))).collect())
+        }
+        Filter::Map(inner) => {
+            let mut out = Vec::new();
+            for value in eval(&Filter::Iterate, input, true)? {
+                out.extend(eval(inner, &value, strict)?);
+            }
+            Ok(vec![Value::Array(out)])
+        }
+        Filter::Select(condition) => Ok(eval(condition, input, strict)?
+            .iter()
+            .filter(|v| truthy(v))
+            .map(|_| input.clone())
+            .collect()),
+        Filter::Keys => match input {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                Ok(vec![Value::from_iter(keys.into_iter().cloned())])
+            }
+            Value::Array(items) => Ok(vec![Value::from_iter(0..items.len() as u64)]),
+            _ => Err(format!("{} has no keys", type_name(input))),
+        },
+        Filter::Length => match input {
+            Value::Null => Ok(vec![Value::from(0)]),
+            Value::Bool(_) => Err("boolean has no length".to_owned()),
+            Value::Number(n) => Ok(vec![number(n.as_f64().unwrap_or(0.0).abs())]),
+            Value::String(s) => Ok(vec![Value::from(s.chars().count())]),
+            Value::Array(items) => Ok(vec![Value::from(items.len())]),
+            Value::Object(map) => Ok(vec![Value::from(map.len())]),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store() -> Value {
+        json!({
+            "store": {
+                "books": [
+                    {"title": "a", "price": 8, "tags": ["x"]},
+                    {"title": "b", "price": 12},
+                    {"title": "c", "price": 20, "tags": []}
+                ],
+                "owner": {"name": "ann"}
+            }
+        })
+    }
+
+    #[test]
+    fn jsonpath_paths_and_wildcards() {
+        assert_eq!(
+            apply("$.store.books[*].title", &store()).unwrap(),
+            json!(["a", "b", "c"])
+        );
+        assert_eq!(
+            apply("$['store']['owner'].name", &store()).unwrap(),
+            json!(["ann"])
+        );
+        assert_eq!(
+            apply("$.store.books[-1].price", &store()).unwrap(),
+            json!([20])
+        );
+        assert_eq!(apply("$.store.missing", &store()).unwrap(), json!([]));
+    }
+
+    #[test]
+    fn jsonpath_recursive_descent_and_slices() {
+        assert_eq!(apply("$..price", &store()).unwrap(), json!([8, 12, 20]));
+        assert_eq!(
+            apply("$.store.books[0:2].title", &store()).unwrap(),
+            json!(["a", "b"])
+        );
+        assert_eq!(apply("$..name", &store()).unwrap(), json!(["ann"]));
+    }
+
+    #[test]
+    fn jsonpath_filters() {
+        assert_eq!(
+            apply("$.store.books[?(@.price > 10)].title", &store()).unwrap(),
+            json!(["b", "c"])
+        );
+        assert_eq!(
+            apply("$.store.books[?(@.tags && @.price < 10)].title", &store()).unwrap(),
+            json!(["a"])
+        );
+        assert_eq!(
+            apply("$.store.books[?(!@.tags)].title", &store()).unwrap(),
+            json!(["b"])
+        );
+    }
+
+    #[test]
+    fn jq_paths_and_iteration() {
+        assert_eq!(apply(".store.owner.name", &store()).unwrap(), json!("ann"));
+        assert_eq!(apply(".", &json!(1)).unwrap(), json!(1));
+        assert_eq!(
+            apply(".store.books[].title", &store()).unwrap(),
+            json!(["a", "b", "c"])
+        );
+        assert_eq!(
+            apply(".store.books[1:].price", &store()).unwrap_err(),
+            "cannot get field \"price\" of array"
+        );
+        assert_eq!(
+            apply(".store[\"owner\"]", &store()).unwrap(),
+            json!({"name": "ann"})
+        );
+        assert_eq!(apply(".nothing", &store()).unwrap(), json!(null));
+    }
+
+    #[test]
+    fn jq_map_select_keys_length() {
+        assert_eq!(
+            apply(".store.books | map(.price)", &store()).unwrap(),
+            json!([8, 12, 20])
+        );
+        assert_eq!(
+            apply(
+                ".store.books[] | select(.price >= 12 and .title != \"c\") | .title",
+                &store()
+            )
+            .unwrap(),
+            json!("b")
+        );
+        assert_eq!(
+            apply(".store | keys", &store()).unwrap(),
+            json!(["books", "owner"])
+        );
+        assert_eq!(apply(".store.books | length", &store()).unwrap(), json!(3));
+        assert_eq!(
+            apply("[.store.books[] | select(.tags | not) | .title]", &store()).unwrap(),
+            json!(["b"])
+        );
+    }
+
+    #[test]
+    fn paths_quote_keys_that_need_it() {
+        let path = [
+            PathSegment::Key("items".to_owned()),
+            PathSegment::Index(0),
+            PathSegment::Key("first name".to_owned()),
+        ];
+        assert_eq!(to_jsonpath(&path), "$.items[0]['first name']");
+        assert_eq!(to_jq(&path), ".items[0][\"first name\"]");
+        assert_eq!(to_js(&path), "data.items[0][\"first name\"]");
+        assert_eq!(to_jq(&[]), ".");
+        assert_eq!(to_js(&[]), "data");
+        let data = json!({"items": [{"first name": "ann"}]});
+        assert_eq!(apply(&to_jsonpath(&path), &data).unwrap(), json!(["ann"]));
+        assert_eq!(apply(&to_jq(&path), &data).unwrap(), json!("ann"));
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        assert_eq!(
+            apply(".a |", &store()).unwrap_err(),
+            "unexpected end of filter"
+        );
+        assert_eq!(
+            apply("frobnicate", &store()).unwrap_err(),
+            "unknown function `frobnicate`"
+        );
+        assert_eq!(
+            apply("$.store.books[?(@.price > 1)", &store()).unwrap_err(),
+            "expected `]`"
+        );
+        assert_eq!(
+            apply(".store.owner.name[0]", &store()).unwrap_err(),
+            "cannot get index 0 of string"
+        );
+        assert_eq!(
+            apply("\"open", &store()).unwrap_err(),
+            "unterminated string"
+        );
+    }
+}
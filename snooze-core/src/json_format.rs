@@ -0,0 +1,144 @@
+use std::fmt;
+
+/// where and why a json text stops parsing
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    /// one-based
+    pub line: usize,
+    /// one-based, zero when the text ended early
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+pub fn validate(text: &str) -> Result<(), JsonError> {
+    serde_json::from_str::<serde::de::IgnoredAny>(text)
+        .map(|_| ())
+        .map_err(|e| {
+            let message = e.to_string();
+            // serde_json appends the position we report separately
+            let message = match message.rfind(" at line ") {
+                Some(i) => message[..i].to_owned(),
+                None => message,
+            };
+            JsonError {
+                line: e.line(),
+                column: e.column(),
+                message,
+            }
+        })
+}
+
+/// `text` reindented `indent` spaces per level. works on the text rather than
+/// a parsed value so key order and number formatting are kept
+pub fn pretty(text: &str, indent: usize) -> Result<String, JsonError> {
+    validate(text)?;
+    Ok(reformat(text, Some(indent)))
+}
+
+/// `text` with all whitespace outside strings removed
+pub fn minify(text: &str) -> Result<String, JsonError> {
+    validate(text)?;
+    Ok(reformat(text, None))
+}
+
+/// lays out valid json, on one line when `indent` is `None`
+fn reformat(text: &str, indent: Option<usize>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0;
+    let newline = |out: &mut String, depth: usize| {
+        if let Some(indent) = indent {
+            out.push('\n');
+            out.push_str(&" ".repeat(indent * depth));
+        }
+    };
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '[' => {
+                out.push(c);
+                while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    chars.next();
+                }
+                // keep empty objects and arrays as `{}` and `[]`
+                if matches!(chars.peek(), Some('}' | ']')) {
+                    out.extend(chars.next());
+                } else {
+                    depth += 1;
+                    newline(&mut out, depth);
+                }
+            }
+            '}' | ']' => {
+                depth -= 1;
+                newline(&mut out, depth);
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                newline(&mut out, depth);
+            }
+            ':' => {
+                out.push(c);
+                if indent.is_some() {
+                    out.push(' ');
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_keeps_order_and_numbers() {
+        let text = r#"{"z":1.50,"a":[1,{"b":"x, y: {z}"}],"e":{},"f":[ ]}"#;
+        assert_eq!(
+            pretty(text, 2).unwrap(),
+            "{\n  \"z\": 1.50,\n  \"a\": [\n    1,\n    {\n      \"b\": \"x, y: {z}\"\n    }\n  ],\n  \"e\": {},\n  \"f\": []\n}"
+        );
+        assert_eq!(
+            minify(&pretty(text, 4).unwrap()).unwrap(),
+            text.replace("[ ]", "[]")
+        );
+    }
+
+    #[test]
+    fn escaped_quotes_stay_inside_strings() {
+        let text = r#"{ "a" : "say \"hi\", \\" , "b" : null }"#;
+        assert_eq!(minify(text).unwrap(), r#"{"a":"say \"hi\", \\","b":null}"#);
+    }
+
+    #[test]
+    fn errors_give_line_and_column() {
+        let error = validate("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 7));
+        assert_eq!(error.message, "expected `:`");
+        assert_eq!(error.to_string(), "line 3, column 7: expected `:`");
+        assert!(pretty("[1,", 2).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+//! everything snooze does without a window: the request, body and auth
+//! models, saved collections and environments, sending requests, scripts,
+//! assertions and collection runs. the gui and `Snooze run` are built on it
+
+use serde::{Deserialize, Serialize};
+
+pub mod assertions;
+pub mod auth;
+pub mod body;
+pub mod codegen;
+pub mod collection;
+pub mod curl;
+pub mod environment;
+pub mod executor;
+pub mod graphql;
+pub mod history;
+pub mod json_filter;
+pub mod json_format;
+pub mod oauth;
+pub mod report;
+pub mod response;
+pub mod runner;
+pub mod script;
+pub mod settings;
+pub mod sigv4;
+
+pub use auth::Auth;
+pub use body::RequestBody;
+pub use collection::SavedRequest;
+pub use executor::{Executor, ResolvedRequest};
+pub use response::{HttpResponse, RequestError, RequestResult};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub enum HttpMethod {
+    #[default]
+    GET,
+    POST,
+    PUT,
+    DELETE,
+    PATCH,
+    HEAD,
+    OPTIONS,
+}
+
+impl HttpMethod {
+    pub const ALL: [HttpMethod; 7] = [
+        HttpMethod::GET,
+        HttpMethod::POST,
+        HttpMethod::PUT,
+        HttpMethod::DELETE,
+        HttpMethod::PATCH,
+        HttpMethod::HEAD,
+        HttpMethod::OPTIONS,
+    ];
+
+    /// whether the worker attaches the request body for this method
+    pub fn has_body(&self) -> bool {
+        matches!(self, HttpMethod::POST | HttpMethod::PUT | HttpMethod::PATCH)
+    }
+}
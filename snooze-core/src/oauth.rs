@@ -0,0 +1,606 @@
+use base64::Engine;
+use log::{error, info};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use url::Url;
+
+/// tokens this close to expiring are refreshed before use
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+/// how long to wait for the browser to come back with an authorization code
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(300);
+/// the oldest tokens are dropped once the cache holds more than this
+const MAX_CACHED_TOKENS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum GrantType {
+    #[default]
+    ClientCredentials,
+    Password,
+    AuthorizationCode,
+    RefreshToken,
+}
+
+impl GrantType {
+    pub const ALL: [GrantType; 4] = [
+        GrantType::ClientCredentials,
+        GrantType::Password,
+        GrantType::AuthorizationCode,
+        GrantType::RefreshToken,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GrantType::ClientCredentials => "client credentials",
+            GrantType::Password => "password",
+            GrantType::AuthorizationCode => "authorization code (pkce)",
+            GrantType::RefreshToken => "refresh token",
+        }
+    }
+}
+
+/// where the client id and secret go in token requests
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ClientAuthentication {
+    #[default]
+    Body,
+    BasicHeader,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OAuth2Config {
+    pub grant: GrantType,
+    pub token_url: String,
+    /// authorization code only
+    pub auth_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: String,
+    /// password grant only
+    pub username: String,
+    pub password: String,
+    /// refresh token grant only
+    pub refresh_token: String,
+    /// loopback port for the authorization code redirect, 0 picks a free one
+    pub redirect_port: u16,
+    pub client_authentication: ClientAuthentication,
+}
+
+impl OAuth2Config {
+    /// applies `f` to every text field, e.g. to substitute variables
+    pub fn map_fields(&self, mut f: impl FnMut(&str) -> String) -> OAuth2Config {
+        OAuth2Config {
+            grant: self.grant,
+            token_url: f(&self.token_url),
+            auth_url: f(&self.auth_url),
+            client_id: f(&self.client_id),
+            client_secret: f(&self.client_secret),
+            scope: f(&self.scope),
+            username: f(&self.username),
+            password: f(&self.password),
+            refresh_token: f(&self.refresh_token),
+            redirect_port: self.redirect_port,
+            client_authentication: self.client_authentication,
+        }
+    }
+
+    /// a digest of the whole configuration, so the cache never holds secrets
+    fn cache_key(&self) -> String {
+        let config = serde_json::to_string(self).unwrap_or_default();
+        format!("{:x}", Sha256::digest(config.as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<Instant>,
+}
+
+impl Token {
+    /// whether the token can still be used without refreshing it first
+    pub fn is_fresh(&self) -> bool {
+        self.expires_at
+            .is_none_or(|at| at > Instant::now() + REFRESH_MARGIN)
+    }
+
+    /// whether there's anything left to use, the token itself or a way to renew it
+    fn is_usable(&self) -> bool {
+        self.refresh_token.is_some() || self.expires_at.is_none_or(|at| at > Instant::now())
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenError {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// tokens by resolved configuration, shared by every request. tokens that
+/// can't be used or renewed are dropped, as are the oldest past `MAX_CACHED_TOKENS`
+#[derive(Default)]
+pub struct TokenCache {
+    /// each token with the order it was stored in
+    tokens: Mutex<HashMap<String, (u64, Token)>>,
+    inserts: AtomicU64,
+    /// held while a token is being fetched, so requests sharing a
+    /// configuration wait for one grant instead of each starting their own
+    fetching: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl TokenCache {
+    pub fn get(&self, config: &OAuth2Config) -> Option<Token> {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens
+            .get(&config.cache_key())
+            .map(|(_, token)| token.clone())
+    }
+
+    pub fn clear(&self, config: &OAuth2Config) {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.remove(&config.cache_key());
+    }
+
+    fn insert(&self, config: &OAuth2Config, token: Token) {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.retain(|_, (_, token)| token.is_usable());
+        let order = self.inserts.fetch_add(1, Ordering::Relaxed);
+        tokens.insert(config.cache_key(), (order, token));
+        while tokens.len() > MAX_CACHED_TOKENS {
+            let oldest = tokens
+                .iter()
+                .min_by_key(|(_, (order, _))| *order)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => tokens.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    /// an access token for `config`: the cached one while it's fresh,
+    /// otherwise a refreshed one, falling back to running the grant again
+    pub async fn access_token(
+        &self,
+        client: &reqwest::Client,
+        config: &OAuth2Config,
+    ) -> Result<String, String> {
+        if let Some(token) = self.get(config).filter(|t| t.is_fresh()) {
+            return Ok(token.access_token);
+        }
+        let key = config.cache_key();
+        let lock = {
+            let mut fetching = self.fetching.lock().unwrap_or_else(|e| e.into_inner());
+            fetching.entry(key).or_default().clone()
+        };
+        let result = {
+            let _guard = lock.lock().await;
+            self.fetch(client, config).await
+        };
+        drop(lock);
+        let mut fetching = self.fetching.lock().unwrap_or_else(|e| e.into_inner());
+        fetching.retain(|_, lock| Arc::strong_count(lock) > 1);
+        result
+    }
+
+    /// `access_token` once no other request is fetching for `config`, which
+    /// may have left a fresh token behind
+    async fn fetch(
+        &self,
+        client: &reqwest::Client,
+        config: &OAuth2Config,
+    ) -> Result<String, String> {
+        let cached = self.get(config);
+        if let Some(token) = cached.as_ref().filter(|t| t.is_fresh()) {
+            return Ok(token.access_token.clone());
+        }
+        let token = match cached.and_then(|t| t.refresh_token) {
+            Some(refresh_token) => match refresh(client, config, &refresh_token).await {
+                Ok(token) => token,
+                Err(e) => {
+                    info!("refreshing token failed, requesting a new one: {}", e);
+                    acquire(client, config).await?
+                }
+            },
+            None => acquire(client, config).await?,
+        };
+        self.insert(config, token.clone());
+        Ok(token.access_token)
+    }
+}
+
+async fn acquire(client: &reqwest::Client, config: &OAuth2Config) -> Result<Token, String> {
+    info!("requesting oauth2 token from {}", config.token_url);
+    match config.grant {
+        GrantType::ClientCredentials => {
+            let mut params = vec![("grant_type", "client_credentials".to_owned())];
+            push_scope(&mut params, config);
+            request_token(client, config, params).await
+        }
+        GrantType::Password => {
+            let mut params = vec![
+                ("grant_type", "password".to_owned()),
+                ("username", config.username.clone()),
+                ("password", config.password.clone()),
+            ];
+            push_scope(&mut params, config);
+            request_token(client, config, params).await
+        }
+        GrantType::AuthorizationCode => authorization_code(client, config).await,
+        GrantType::RefreshToken => refresh(client, config, &config.refresh_token).await,
+    }
+}
+
+async fn refresh(
+    client: &reqwest::Client,
+    config: &OAuth2Config,
+    refresh_token: &str,
+) -> Result<Token, String> {
+    let mut params = vec![
+        ("grant_type", "refresh_token".to_owned()),
+        ("refresh_token", refresh_token.to_owned()),
+    ];
+    push_scope(&mut params, config);
+    let mut token = request_token(client, config, params).await?;
+    // servers that don't rotate refresh tokens leave it out of the response
+    if token.refresh_token.is_none() {
+        token.refresh_token = Some(refresh_token.to_owned());
+    }
+    Ok(token)
+}
+
+fn push_scope(params: &mut Vec<(&str, String)>, config: &OAuth2Config) {
+    if !config.scope.trim().is_empty() {
+        params.push(("scope", config.scope.trim().to_owned()));
+    }
+}
+
+async fn request_token(
+    client: &reqwest::Client,
+    config: &OAuth2Config,
+    mut params: Vec<(&str, String)>,
+) -> Result<Token, String> {
+    let mut builder = client
+        .post(&config.token_url)
+        .header(reqwest::header::ACCEPT, "application/json");
+    match config.client_authentication {
+        ClientAuthentication::Body => {
+            params.push(("client_id", config.client_id.clone()));
+            if !config.client_secret.is_empty() {
+                params.push(("client_secret", config.client_secret.clone()));
+            }
+        }
+        ClientAuthentication::BasicHeader => {
+            builder = builder.basic_auth(&config.client_id, Some(&config.client_secret));
+        }
+    }
+    let resp = builder
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| format!("token request failed: {}", e))?;
+    let status = resp.status();
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| format!("error reading token response: {}", e))?;
+    if !status.is_success() {
+        return Err(match serde_json::from_str::<TokenError>(&body) {
+            Ok(e) => format!(
+                "token request failed ({}): {} {}",
+                status,
+                e.error,
+                e.error_description.unwrap_or_default()
+            ),
+            Err(_) => format!("token request failed ({}): {}", status, body),
+        });
+    }
+    let token: TokenResponse =
+        serde_json::from_str(&body).map_err(|e| format!("unexpected token response: {}", e))?;
+    Ok(Token {
+        access_token: token.access_token,
+        refresh_token: token.refresh_token,
+        expires_at: token
+            .expires_in
+            .map(|secs| Instant::now() + Duration::from_secs(secs)),
+    })
+}
+
+/// sends the user to the authorization page in their browser, waits for the
+/// redirect on a loopback listener and trades the code for a token
+async fn authorization_code(
+    client: &reqwest::Client,
+    config: &OAuth2Config,
+) -> Result<Token, String> {
+    let listener = TcpListener::bind(("127.0.0.1", config.redirect_port))
+        .await
+        .map_err(|e| format!("failed to listen for the redirect: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("failed to listen for the redirect: {}", e))?
+        .port();
+    let redirect_uri = format!("http://127.0.0.1:{}/callback", port);
+    let verifier = random_string(64);
+    let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(Sha256::digest(verifier.as_bytes()));
+    let state = random_string(24);
+
+    let mut url =
+        Url::parse(&config.auth_url).map_err(|e| format!("invalid authorization url: {}", e))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &redirect_uri)
+        .append_pair("code_challenge", &challenge)
+        .append_pair("code_challenge_method", "S256")
+        .append_pair("state", &state);
+    if !config.scope.trim().is_empty() {
+        url.query_pairs_mut()
+            .append_pair("scope", config.scope.trim());
+    }
+    info!("opening browser for authorization: {}", url);
+    open_browser(url.as_str());
+
+    let code = tokio::time::timeout(AUTHORIZATION_TIMEOUT, wait_for_code(&listener, &state))
+        .await
+        .map_err(|_| "timed out waiting for the authorization redirect".to_owned())??;
+    request_token(
+        client,
+        config,
+        vec![
+            ("grant_type", "authorization_code".to_owned()),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", verifier),
+        ],
+    )
+    .await
+}
+
+/// answers requests on `listener` until the redirect carrying the code arrives
+async fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String, String> {
+    loop {
+        let (mut socket, _) = listener
+            .accept()
+            .await
+            .map_err(|e| format!("failed to accept the redirect: {}", e))?;
+        let mut buf = vec![0; 8192];
+        let n = socket.read(&mut buf).await.unwrap_or(0);
+        let head = String::from_utf8_lossy(&buf[..n]);
+        let target = head.split_whitespace().nth(1).unwrap_or("/");
+        let Ok(url) = Url::parse(&format!("http://127.0.0.1{}", target)) else {
+            continue;
+        };
+        if url.path() != "/callback" {
+            // e.g. the browser asking for a favicon
+            let _ = socket
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await;
+            continue;
+        }
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let result = if let Some(error) = params.get("error") {
+            Err(format!(
+                "authorization failed: {} {}",
+                error,
+                params.get("error_description").cloned().unwrap_or_default()
+            ))
+        } else if params.get("state").map(String::as_str) != Some(state) {
+            Err("authorization redirect had the wrong state".to_owned())
+        } else {
+            params
+                .get("code")
+                .cloned()
+                .ok_or_else(|| "authorization redirect had no code".to_owned())
+        };
+        // plain text, since the error comes from the query and can't be trusted as markup
+        let message = match &result {
+            Ok(_) => {
+                "authorization complete, you can close this tab and return to snooze".to_owned()
+            }
+            Err(e) => e.clone(),
+        };
+        let _ = socket
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    message.len(),
+                    message
+                )
+                .as_bytes(),
+            )
+            .await;
+        return result;
+    }
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn open_browser(url: &str) {
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("open").arg(url).spawn();
+    #[cfg(target_os = "windows")]
+    let result = std::process::Command::new("rundll32")
+        .args(["url.dll,FileProtocolHandler", url])
+        .spawn();
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let result = std::process::Command::new("xdg-open").arg(url).spawn();
+    if let Err(e) = result {
+        error!("failed to open a browser, visit {} manually: {}", url, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpStream;
+
+    fn token(expires_in: Option<Duration>, refresh_token: Option<&str>) -> Token {
+        Token {
+            access_token: "access".to_owned(),
+            refresh_token: refresh_token.map(str::to_owned),
+            expires_at: expires_in.map(|d| Instant::now() + d),
+        }
+    }
+
+    fn config(client_id: &str) -> OAuth2Config {
+        OAuth2Config {
+            token_url: "https://auth.example.com/token".to_owned(),
+            client_id: client_id.to_owned(),
+            client_secret: "s3cret".to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tokens_near_expiry_are_not_fresh() {
+        assert!(token(None, None).is_fresh());
+        assert!(token(Some(REFRESH_MARGIN * 2), None).is_fresh());
+        assert!(!token(Some(REFRESH_MARGIN / 2), None).is_fresh());
+        assert!(token(Some(REFRESH_MARGIN / 2), None).is_usable());
+        assert!(!token(Some(Duration::ZERO), None).is_usable());
+        assert!(token(Some(Duration::ZERO), Some("refresh")).is_usable());
+    }
+
+    #[test]
+    fn cache_keys_leave_secrets_out() {
+        let config = config("app");
+        let key = config.cache_key();
+        assert!(!key.contains("s3cret"), "{}", key);
+        assert_eq!(key, config.clone().cache_key());
+        let mut other = config.clone();
+        other.client_secret = "rotated".to_owned();
+        assert_ne!(key, other.cache_key());
+    }
+
+    #[test]
+    fn drops_dead_and_oldest_tokens() {
+        let cache = TokenCache::default();
+        cache.insert(&config("expired"), token(Some(Duration::ZERO), None));
+        cache.insert(&config("renewable"), token(Some(Duration::ZERO), Some("r")));
+        assert!(cache.get(&config("expired")).is_none());
+        assert!(cache.get(&config("renewable")).is_some());
+
+        for i in 0..MAX_CACHED_TOKENS {
+            cache.insert(&config(&i.to_string()), token(None, None));
+        }
+        assert!(cache.get(&config("renewable")).is_none());
+        assert!(cache.get(&config("0")).is_some());
+        assert_eq!(cache.tokens.lock().unwrap().len(), MAX_CACHED_TOKENS);
+
+        cache.clear(&config("0"));
+        assert!(cache.get(&config("0")).is_none());
+    }
+
+    /// sends `target` to the redirect listener the way a browser would
+    async fn visit(port: u16, target: &str) -> String {
+        let mut socket = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        socket
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).as_bytes())
+            .await
+            .unwrap();
+        let mut page = String::new();
+        socket.read_to_string(&mut page).await.unwrap();
+        page
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_callback_and_checks_its_state() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let browser = tokio::spawn(async move {
+            let favicon = visit(port, "/favicon.ico").await;
+            let callback = visit(port, "/callback?code=abc&state=expected").await;
+            (favicon, callback)
+        });
+        assert_eq!(wait_for_code(&listener, "expected").await.unwrap(), "abc");
+        let (favicon, callback) = browser.await.unwrap();
+        assert!(favicon.starts_with("HTTP/1.1 404"), "{}", favicon);
+        assert!(callback.contains("authorization complete"), "{}", callback);
+
+        let browser = tokio::spawn(visit(port, "/callback?code=abc&state=forged"));
+        assert_eq!(
+            wait_for_code(&listener, "expected").await.unwrap_err(),
+            "authorization redirect had the wrong state"
+        );
+        assert!(browser.await.unwrap().contains("wrong state"));
+
+        let browser = tokio::spawn(visit(
+            port,
+            "/callback?error=access_denied&error_description=%3Cscript%3E&state=expected",
+        ));
+        assert_eq!(
+            wait_for_code(&listener, "expected").await.unwrap_err(),
+            "authorization failed: access_denied <script>"
+        );
+        let page = browser.await.unwrap();
+        assert!(page.contains("Content-Type: text/plain"), "{}", page);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_share_one_grant() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let grants = Arc::new(AtomicU64::new(0));
+        let counted = grants.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                counted.fetch_add(1, Ordering::SeqCst);
+                let mut buf = vec![0; 8192];
+                let _ = socket.read(&mut buf).await;
+                // slow enough for the other requests to arrive meanwhile
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let body = r#"{"access_token": "shared", "token_type": "bearer"}"#;
+                let _ = socket
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                        .as_bytes(),
+                    )
+                    .await;
+            }
+        });
+        let mut config = config("app");
+        config.token_url = format!("http://127.0.0.1:{}/token", port);
+        let client = reqwest::Client::new();
+        let cache = TokenCache::default();
+        let (a, b, c) = tokio::join!(
+            cache.access_token(&client, &config),
+            cache.access_token(&client, &config),
+            cache.access_token(&client, &config),
+        );
+        for token in [a, b, c] {
+            assert_eq!(token.unwrap(), "shared");
+        }
+        assert_eq!(grants.load(Ordering::SeqCst), 1);
+        assert!(cache.fetching.lock().unwrap().is_empty());
+    }
+}
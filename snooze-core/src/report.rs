@@ -0,0 +1,180 @@
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::runner::StepResult;
+
+/// a test case's name, numbered by iteration when the run had several
+fn case_name(result: &StepResult, iterations: usize) -> String {
+    if iterations > 1 {
+        format!("{} [{}]", result.name, result.iteration + 1)
+    } else {
+        result.name.clone()
+    }
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+/// a JUnit XML report with one test case per step. requests that got no
+/// response are errors, failed assertions are failures
+pub fn junit(suite: &str, results: &[StepResult], iterations: usize) -> String {
+    let errors = results.iter().filter(|r| r.error.is_some()).count();
+    let failures = results
+        .iter()
+        .filter(|r| r.error.is_none() && !r.passed())
+        .count();
+    let time = seconds(results.iter().map(|r| r.duration).sum());
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites name=\"snooze\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">\n",
+        results.len(),
+        failures,
+        errors,
+        time
+    ));
+    out.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">\n",
+        escape(suite),
+        results.len(),
+        failures,
+        errors,
+        time
+    ));
+    for result in results {
+        out.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+            escape(&case_name(result, iterations)),
+            escape(suite),
+            seconds(result.duration)
+        ));
+        let failed: Vec<String> = result
+            .assertions
+            .iter()
+            .filter(|a| !a.passed)
+            .map(|a| format!("{}: {}", a.description, a.message))
+            .collect();
+        if let Some(error) = &result.error {
+            out.push_str(&format!(
+                ">\n      <error message=\"{}\">{:?} {}</error>\n    </testcase>\n",
+                escape(error),
+                result.method,
+                escape(&result.url)
+            ));
+        } else if !failed.is_empty() {
+            out.push_str(&format!(
+                ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                escape(&format!("{} assertion(s) failed", failed.len())),
+                escape(&failed.join("\n"))
+            ));
+        } else {
+            out.push_str("/>\n");
+        }
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+/// every step with its status, timing and assertion results, plus totals
+pub fn json(suite: &str, results: &[StepResult], iterations: usize) -> Value {
+    let passed = results.iter().filter(|r| r.passed()).count();
+    let steps: Vec<Value> = results
+        .iter()
+        .map(|result| {
+            json!({
+                "iteration": result.iteration,
+                "name": result.name,
+                "method": format!("{:?}", result.method),
+                "url": result.url,
+                "status": result.status,
+                "error": result.error,
+                "duration_ms": result.duration.as_millis() as u64,
+                "passed": result.passed(),
+                "assertions": result.assertions,
+            })
+        })
+        .collect();
+    json!({
+        "suite": suite,
+        "iterations": iterations,
+        "total": results.len(),
+        "passed": passed,
+        "failed": results.len() - passed,
+        "duration_ms": results.iter().map(|r| r.duration).sum::<Duration>().as_millis() as u64,
+        "steps": steps,
+    })
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            // not allowed in xml 1.0 at all
+            c if (c as u32) < 0x20 && c != '\t' && c != '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assertions::AssertionResult;
+    use crate::HttpMethod;
+
+    fn step(name: &str, iteration: usize, error: Option<&str>, passed: bool) -> StepResult {
+        StepResult {
+            iteration,
+            name: name.to_owned(),
+            method: HttpMethod::GET,
+            url: "https://example.com/?a=1&b=2".to_owned(),
+            status: error.is_none().then_some(200),
+            error: error.map(str::to_owned),
+            duration: Duration::from_millis(1500),
+            assertions: vec![AssertionResult {
+                description: "status is 200".to_owned(),
+                passed,
+                message: "got <none>".to_owned(),
+            }],
+            console: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn junit_marks_failures_and_errors() {
+        let results = [
+            step("users/list", 0, None, true),
+            step("users/list", 1, None, false),
+            step("login", 1, Some("connection refused"), false),
+        ];
+        let xml = junit("api", &results, 2);
+        assert!(xml
+            .contains(r#"<testsuite name="api" tests="3" failures="1" errors="1" time="4.500">"#));
+        assert!(xml.contains(r#"<testcase name="users/list [1]" classname="api" time="1.500"/>"#));
+        assert!(xml.contains(
+            r#"<failure message="1 assertion(s) failed">status is 200: got &lt;none&gt;</failure>"#
+        ));
+        assert!(xml.contains(
+            r#"<error message="connection refused">GET https://example.com/?a=1&amp;b=2</error>"#
+        ));
+    }
+
+    #[test]
+    fn json_totals() {
+        let results = [step("a", 0, None, true), step("b", 0, None, false)];
+        let report = json("api", &results, 1);
+        assert_eq!(report["total"], 2);
+        assert_eq!(report["passed"], 1);
+        assert_eq!(report["failed"], 1);
+        assert_eq!(report["duration_ms"], 3000);
+        assert_eq!(report["steps"][1]["assertions"][0]["passed"], false);
+        assert_eq!(report["steps"][0]["method"], "GET");
+    }
+}
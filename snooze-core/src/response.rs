@@ -0,0 +1,226 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::assertions::AssertionResult;
+use crate::body::RequestBody;
+use crate::script::ScriptLog;
+use crate::sigv4;
+use crate::HttpMethod;
+
+/// everything the worker keeps from a completed response
+#[derive(Debug, Clone, Default)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub reason: String,
+    pub version: String,
+    /// where the response came from, after any redirects
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub content_length: Option<u64>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// status line and headers of `resp`, with the body left empty
+    pub fn from_head(resp: &reqwest::Response) -> Self {
+        let status = resp.status();
+        let headers = resp
+            .headers()
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.to_string(),
+                    String::from_utf8_lossy(v.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        Self {
+            status: status.as_u16(),
+            reason: status.canonical_reason().unwrap_or_default().to_owned(),
+            version: format!("{:?}", resp.version()),
+            url: resp.url().to_string(),
+            headers,
+            content_length: resp.content_length(),
+            body: Vec::new(),
+        }
+    }
+
+    /// the media type from the `Content-Type` header, lowercased and without
+    /// parameters like the charset
+    pub fn content_type(&self) -> Option<String> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+            .map(|(_, v)| {
+                v.split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_ascii_lowercase()
+            })
+    }
+
+    /// a file name for saving the body: the one `Content-Disposition` gives,
+    /// else the last segment of the url, else `response` with an extension
+    /// for the content type
+    pub fn suggested_filename(&self) -> String {
+        let from_header = self
+            .headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-disposition"))
+            .and_then(|(_, v)| disposition_filename(v));
+        let from_url = || {
+            let url = url::Url::parse(&self.url).ok()?;
+            let segment = url.path_segments()?.next_back()?;
+            Some(String::from_utf8_lossy(&sigv4::percent_decode(segment)).into_owned())
+        };
+        let name = from_header
+            .or_else(from_url)
+            .map(|name| {
+                // never let a server pick the directory
+                name.rsplit(['/', '\\'])
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_owned()
+            })
+            .filter(|name| !name.is_empty() && name != "." && name != "..")
+            .unwrap_or_else(|| "response".to_owned());
+        if name.contains('.') {
+            return name;
+        }
+        let extension = self
+            .content_type()
+            .and_then(|t| mime_guess::get_mime_extensions_str(&t))
+            .and_then(|extensions| extensions.first());
+        match extension {
+            Some(extension) => format!("{}.{}", name, extension),
+            None => name,
+        }
+    }
+
+    /// body decoded as utf-8, with invalid sequences replaced
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// the file name in a `Content-Disposition` value, preferring the rfc 5987
+/// `filename*` form when both are given
+fn disposition_filename(value: &str) -> Option<String> {
+    let params: Vec<(String, &str)> = value
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim()))
+        .collect();
+    let extended = params
+        .iter()
+        .find(|(k, _)| k == "filename*")
+        .and_then(|(_, v)| {
+            // charset'language'percent-encoded
+            let encoded = v.splitn(3, '\'').nth(2)?;
+            Some(String::from_utf8_lossy(&sigv4::percent_decode(encoded)).into_owned())
+        });
+    extended.or_else(|| {
+        params
+            .iter()
+            .find(|(k, _)| k == "filename")
+            .map(|(_, v)| v.trim_matches('"').to_owned())
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeoutKind {
+    Connect,
+    Total,
+    Read,
+}
+
+#[derive(Debug, Clone)]
+pub enum RequestError {
+    Timeout {
+        kind: TimeoutKind,
+        limit: Option<Duration>,
+    },
+    Failed(String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Timeout { kind, limit } => {
+                let kind = match kind {
+                    TimeoutKind::Connect => "connect",
+                    TimeoutKind::Total => "total",
+                    TimeoutKind::Read => "read",
+                };
+                match limit {
+                    Some(limit) => write!(f, "{} timeout after {} ms", kind, limit.as_millis()),
+                    None => write!(f, "{} timeout", kind),
+                }
+            }
+            RequestError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// a request as it went out, after scripts, auth and signing
+#[derive(Debug, Clone)]
+pub struct SentRequest {
+    pub method: HttpMethod,
+    /// url with the query parameters applied
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: RequestBody,
+}
+
+impl SentRequest {
+    pub fn new(method: HttpMethod, built: &reqwest::Request, body: &RequestBody) -> Self {
+        let headers = built
+            .headers()
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.to_string(),
+                    String::from_utf8_lossy(v.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        Self {
+            method,
+            url: built.url().to_string(),
+            headers,
+            body: body.clone(),
+        }
+    }
+}
+
+/// sent from the worker thread back to the ui once a request finishes
+pub struct RequestResult {
+    /// matches the id the ui handed out when sending, so stale results can be dropped
+    pub id: u64,
+    /// `None` when it failed before anything was sent
+    pub sent: Option<SentRequest>,
+    pub response: Result<HttpResponse, RequestError>,
+    pub duration: Duration,
+    /// what the request's scripts printed and the variables they set
+    pub script_log: ScriptLog,
+    /// how the request's enabled assertions went
+    pub assertions: Vec<AssertionResult>,
+}
+
+pub fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
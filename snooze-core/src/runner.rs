@@ -0,0 +1,426 @@
+use log::{error, info};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::{AbortHandle, JoinSet};
+
+use crate::assertions::{self, AssertionResult};
+use crate::body::UploadProgress;
+use crate::collection::{self, SavedRequest, Workspace};
+use crate::executor::{self, ResolvedRequest};
+use crate::oauth::TokenCache;
+use crate::response::RequestError;
+use crate::script::{ConsoleLine, ScriptLog};
+use crate::settings::Timeouts;
+use crate::HttpMethod;
+
+/// how a folder run goes about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOptions {
+    /// how many requests are in flight at once. variables set by a batch
+    /// are only seen by the batches after it
+    pub concurrency: usize,
+    pub stop_on_failure: bool,
+    /// csv or json rows to run the whole folder once for each, empty for a single pass
+    pub data_file: String,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            stop_on_failure: false,
+            data_file: String::new(),
+        }
+    }
+}
+
+/// one saved request of a run
+#[derive(Debug, Clone)]
+pub struct RunStep {
+    /// path within the folder being run, without the extension
+    pub name: String,
+    pub request: SavedRequest,
+}
+
+/// the requests under `folder` in tree order
+pub fn load_steps(folder: &Path) -> Result<Vec<RunStep>, String> {
+    let paths = Workspace::requests_in(folder)
+        .map_err(|e| format!("failed to read {}: {}", folder.display(), e))?;
+    paths
+        .into_iter()
+        .map(|path| {
+            let request = Workspace::load_request(&path)
+                .map_err(|e| format!("failed to load {}: {}", path.display(), e))?;
+            Ok(RunStep {
+                name: step_name(folder, &path),
+                request,
+            })
+        })
+        .collect()
+}
+
+fn step_name(folder: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(folder).unwrap_or(path);
+    let parent = relative.parent().filter(|p| !p.as_os_str().is_empty());
+    let name = collection::request_name(path);
+    match parent {
+        Some(parent) => format!("{}/{}", parent.to_string_lossy(), name),
+        None => name,
+    }
+}
+
+/// the rows of a data file, each a set of variables for one iteration. `.json`
+/// files hold an array of objects, anything else is csv with a header row
+pub fn load_data(path: &Path) -> Result<Vec<HashMap<String, String>>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    {
+        parse_json_rows(&contents)
+    } else {
+        parse_csv_rows(&contents)
+    }
+}
+
+fn parse_json_rows(text: &str) -> Result<Vec<HashMap<String, String>>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| format!("invalid json: {}", e))?;
+    let rows = value
+        .as_array()
+        .ok_or_else(|| "expected an array of objects".to_owned())?;
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let object = row
+                .as_object()
+                .ok_or_else(|| format!("row {} isn't an object", i + 1))?;
+            Ok(object
+                .iter()
+                .map(|(k, v)| {
+                    let value = match v {
+                        serde_json::Value::String(s) => s.clone(),
+                        serde_json::Value::Null => String::new(),
+                        other => other.to_string(),
+                    };
+                    (k.clone(), value)
+                })
+                .collect())
+        })
+        .collect()
+}
+
+fn parse_csv_rows(text: &str) -> Result<Vec<HashMap<String, String>>, String> {
+    let mut records = parse_csv(text).into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+    records
+        .enumerate()
+        .map(|(i, record)| {
+            if record.len() != header.len() {
+                return Err(format!(
+                    "row {} has {} fields, the header has {}",
+                    i + 2,
+                    record.len(),
+                    header.len()
+                ));
+            }
+            Ok(header.iter().cloned().zip(record).collect())
+        })
+        .collect()
+}
+
+/// records of comma separated fields. fields may be double quoted, with `""`
+/// for a quote and line breaks allowed inside. blank lines are skipped
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// how one request of one iteration went
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    /// zero-based row of the data file
+    pub iteration: usize,
+    pub name: String,
+    pub method: HttpMethod,
+    pub url: String,
+    pub status: Option<u16>,
+    /// why there is no response
+    pub error: Option<String>,
+    pub duration: Duration,
+    pub assertions: Vec<AssertionResult>,
+    pub console: Vec<ConsoleLine>,
+}
+
+impl StepResult {
+    /// a step fails when it got no response or an assertion failed; an
+    /// error status alone doesn't fail it
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.assertions.iter().all(|a| a.passed)
+    }
+}
+
+pub enum RunEvent {
+    Step(StepResult),
+    /// `stopped` when a failure ended the run early
+    Finished {
+        stopped: bool,
+    },
+}
+
+/// a step with the client and timeouts it is sent with
+pub struct PreparedStep {
+    pub step: RunStep,
+    pub client: Result<reqwest::Client, String>,
+    pub timeouts: Timeouts,
+}
+
+/// sends every step once per row of `data` (once if there are none). each
+/// iteration starts from `variables` plus its row, and variables set by
+/// scripts carry on to the later steps of that iteration only
+pub async fn run(
+    steps: Vec<PreparedStep>,
+    tokens: Arc<TokenCache>,
+    variables: HashMap<String, String>,
+    data: Vec<HashMap<String, String>>,
+    options: RunOptions,
+    tx: Sender<RunEvent>,
+) {
+    let rows = if data.is_empty() {
+        vec![HashMap::new()]
+    } else {
+        data
+    };
+    let steps = Arc::new(steps);
+    for (iteration, row) in rows.into_iter().enumerate() {
+        let mut vars = variables.clone();
+        vars.extend(row);
+        let indices: Vec<usize> = (0..steps.len()).collect();
+        for batch in indices.chunks(options.concurrency.max(1)) {
+            // the set aborts whatever is still running if the run is cancelled
+            let mut tasks = JoinSet::new();
+            for &index in batch {
+                let steps = steps.clone();
+                let tokens = tokens.clone();
+                let vars = vars.clone();
+                tasks.spawn(async move {
+                    let (result, set) = run_step(iteration, &steps[index], &tokens, &vars).await;
+                    (index, result, set)
+                });
+            }
+            let mut finished = Vec::new();
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok(done) => finished.push(done),
+                    Err(e) => error!("run step panicked: {}", e),
+                }
+            }
+            finished.sort_by_key(|(index, _, _)| *index);
+            for (_, result, set) in finished {
+                vars.extend(set);
+                let failed = !result.passed();
+                if tx.send(RunEvent::Step(result)).is_err() {
+                    return;
+                }
+                if failed && options.stop_on_failure {
+                    info!("stopping the run after a failure");
+                    let _ = tx.send(RunEvent::Finished { stopped: true });
+                    return;
+                }
+            }
+        }
+    }
+    let _ = tx.send(RunEvent::Finished { stopped: false });
+}
+
+/// sends one step and checks its assertions, returning the result and the
+/// variables its scripts set
+async fn run_step(
+    iteration: usize,
+    prepared: &PreparedStep,
+    tokens: &TokenCache,
+    vars: &HashMap<String, String>,
+) -> (StepResult, Vec<(String, String)>) {
+    let mut unresolved = Vec::new();
+    let request = ResolvedRequest::resolve(&prepared.step.request, vars, &mut unresolved);
+    let (method, url) = (request.method.clone(), request.url.clone());
+    let checks = request.assertions.clone();
+    let mut log = ScriptLog::default();
+    // a pre-request script may still set them, execute checks again after it
+    let (response, duration) = if !unresolved.is_empty() && !request.scripts.has_pre_request() {
+        let message = format!("unresolved variables: {}", unresolved.join(", "));
+        (Err(RequestError::Failed(message)), Duration::ZERO)
+    } else {
+        match &prepared.client {
+            Ok(client) => {
+                let progress = Arc::new(UploadProgress::default());
+                executor::execute(
+                    client,
+                    tokens,
+                    request,
+                    &prepared.timeouts,
+                    &progress,
+                    &mut log,
+                    &mut None,
+                )
+                .await
+            }
+            Err(e) => (Err(RequestError::Failed(e.clone())), Duration::ZERO),
+        }
+    };
+    let result = StepResult {
+        iteration,
+        name: prepared.step.name.clone(),
+        method,
+        url,
+        status: response.as_ref().ok().map(|r| r.status),
+        error: response.as_ref().err().map(|e| e.to_string()),
+        duration,
+        assertions: assertions::evaluate(&checks, &response, duration),
+        console: log.console,
+    };
+    (result, log.variables)
+}
+
+/// a run in progress or done, as the runner window shows it
+pub struct CollectionRun {
+    pub folder: PathBuf,
+    pub steps: usize,
+    pub iterations: usize,
+    pub results: Vec<StepResult>,
+    pub finished: bool,
+    /// ended early by a failure
+    pub stopped: bool,
+    abort: AbortHandle,
+    rx: Receiver<RunEvent>,
+}
+
+impl CollectionRun {
+    pub fn new(
+        folder: PathBuf,
+        steps: usize,
+        iterations: usize,
+        abort: AbortHandle,
+        rx: Receiver<RunEvent>,
+    ) -> Self {
+        Self {
+            folder,
+            steps,
+            iterations,
+            results: Vec::new(),
+            finished: false,
+            stopped: false,
+            abort,
+            rx,
+        }
+    }
+
+    /// takes in whatever the run reported since the last call
+    pub fn poll(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(RunEvent::Step(result)) => self.results.push(result),
+                Ok(RunEvent::Finished { stopped }) => {
+                    self.finished = true;
+                    self.stopped = stopped;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        info!("cancelling the run of {}", self.folder.display());
+        self.abort.abort();
+        self.finished = true;
+    }
+
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|r| r.passed()).count()
+    }
+
+    pub fn total_duration(&self) -> Duration {
+        self.results.iter().map(|r| r.duration).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_rows_with_quotes() {
+        let text = "user,note\r\nann,\"says \"\"hi\"\", twice\"\n\nbob,\"two\nlines\"\n";
+        let rows = parse_csv_rows(text).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["user"], "ann");
+        assert_eq!(rows[0]["note"], "says \"hi\", twice");
+        assert_eq!(rows[1]["note"], "two\nlines");
+        assert!(parse_csv_rows("a,b\n1\n").unwrap_err().contains("row 2"));
+        assert!(parse_csv_rows("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_rows_become_strings() {
+        let rows =
+            parse_json_rows(r#"[{"id": 3, "name": "ann", "admin": true, "x": null}]"#).unwrap();
+        assert_eq!(rows[0]["id"], "3");
+        assert_eq!(rows[0]["name"], "ann");
+        assert_eq!(rows[0]["admin"], "true");
+        assert_eq!(rows[0]["x"], "");
+        assert!(parse_json_rows(r#"{"id": 3}"#).is_err());
+    }
+
+    #[test]
+    fn step_names_are_relative() {
+        let folder = Path::new("/w/collections/api");
+        assert_eq!(step_name(folder, &folder.join("login.json")), "login");
+        assert_eq!(
+            step_name(folder, &folder.join("users/list.json")),
+            "users/list"
+        );
+    }
+}
@@ -0,0 +1,492 @@
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use rhai::{Array, Dynamic, Engine, Map, Scope};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::body::RequestBody;
+use crate::executor::ResolvedRequest;
+use crate::response::HttpResponse;
+use crate::HttpMethod;
+
+/// scripts are stopped after this many operations so a runaway loop can't
+/// hold a worker thread forever
+const MAX_OPERATIONS: u64 = 5_000_000;
+
+/// a request's rhai scripts, saved with it
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scripts {
+    /// runs in the worker before the request is built, and can change it
+    pub pre_request: String,
+    /// runs once the whole response has arrived
+    pub post_response: String,
+}
+
+impl Scripts {
+    pub fn has_pre_request(&self) -> bool {
+        !self.pre_request.trim().is_empty()
+    }
+
+    pub fn has_post_response(&self) -> bool {
+        !self.post_response.trim().is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    PreRequest,
+    PostResponse,
+}
+
+impl Stage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Stage::PreRequest => "pre-request",
+            Stage::PostResponse => "post-response",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsoleLine {
+    /// `None` for notes from the app rather than a script
+    pub stage: Option<Stage>,
+    pub text: String,
+    pub error: bool,
+}
+
+/// what a request's scripts printed and the variables they set, in order
+#[derive(Debug, Clone, Default)]
+pub struct ScriptLog {
+    pub console: Vec<ConsoleLine>,
+    pub variables: Vec<(String, String)>,
+}
+
+/// the request as the pre-request script sees it
+#[derive(Debug, Clone)]
+struct ScriptRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    queries: Vec<(String, String)>,
+    body: RequestBody,
+}
+
+/// the response as the post-response script sees it
+#[derive(Debug, Clone)]
+struct ScriptResponse {
+    status: u16,
+    reason: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+/// shared between the engine's callbacks while a script runs
+#[derive(Default)]
+struct State {
+    variables: HashMap<String, String>,
+    set: Vec<(String, String)>,
+    console: Vec<String>,
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// runs `script` on `request` before it's built. the script may change the
+/// method, url, headers, queries and body, and read and set `variables`.
+/// an error means the request shouldn't go out
+pub fn run_pre_request(
+    script: &str,
+    request: &mut ResolvedRequest,
+    variables: &mut HashMap<String, String>,
+    log: &mut ScriptLog,
+) -> Result<(), String> {
+    let mut scope = Scope::new();
+    scope.push(
+        "request",
+        ScriptRequest {
+            method: format!("{:?}", request.method),
+            url: request.url.clone(),
+            headers: request.headers.clone(),
+            queries: request.queries.clone(),
+            body: request.body.clone(),
+        },
+    );
+    run(Stage::PreRequest, script, &mut scope, variables, log)?;
+    let Some(changed) = scope.get_value::<ScriptRequest>("request") else {
+        return fail(Stage::PreRequest, "`request` was replaced", log);
+    };
+    let method = changed.method.to_ascii_uppercase();
+    let Some(method) = HttpMethod::ALL
+        .iter()
+        .find(|candidate| format!("{:?}", candidate) == method)
+        .cloned()
+    else {
+        return fail(
+            Stage::PreRequest,
+            &format!("unknown method {}", method),
+            log,
+        );
+    };
+    request.method = method;
+    request.url = changed.url;
+    request.headers = changed.headers;
+    request.queries = changed.queries;
+    request.body = changed.body;
+    Ok(())
+}
+
+/// runs `script` with the finished `response`, letting it read and set `variables`
+pub fn run_post_response(
+    script: &str,
+    response: &HttpResponse,
+    variables: &mut HashMap<String, String>,
+    log: &mut ScriptLog,
+) {
+    let mut scope = Scope::new();
+    scope.push(
+        "response",
+        ScriptResponse {
+            status: response.status,
+            reason: response.reason.clone(),
+            headers: response.headers.clone(),
+            body: response.text(),
+        },
+    );
+    // failures are already in the console and don't undo the response
+    let _ = run(Stage::PostResponse, script, &mut scope, variables, log);
+}
+
+fn fail(stage: Stage, message: &str, log: &mut ScriptLog) -> Result<(), String> {
+    log.console.push(ConsoleLine {
+        stage: Some(stage),
+        text: message.to_owned(),
+        error: true,
+    });
+    Err(format!("{} script: {}", stage.label(), message))
+}
+
+fn run(
+    stage: Stage,
+    script: &str,
+    scope: &mut Scope,
+    variables: &mut HashMap<String, String>,
+    log: &mut ScriptLog,
+) -> Result<(), String> {
+    let state = Arc::new(Mutex::new(State {
+        variables: std::mem::take(variables),
+        ..Default::default()
+    }));
+    let result = engine(&state).run_with_scope(scope, script);
+    let state = std::mem::take(&mut *lock(&state));
+    *variables = state.variables;
+    log.variables.extend(state.set);
+    log.console
+        .extend(state.console.into_iter().map(|text| ConsoleLine {
+            stage: Some(stage),
+            text,
+            error: false,
+        }));
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => fail(stage, &e.to_string(), log),
+    }
+}
+
+fn engine(state: &Arc<Mutex<State>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let console = state.clone();
+    engine.on_print(move |text| lock(&console).console.push(text.to_owned()));
+    let console = state.clone();
+    engine.on_debug(move |text, _, _| lock(&console).console.push(text.to_owned()));
+
+    let vars = state.clone();
+    engine.register_fn("variable", move |name: &str| {
+        lock(&vars)
+            .variables
+            .get(name)
+            .cloned()
+            .map_or(Dynamic::UNIT, Dynamic::from)
+    });
+    let vars = state.clone();
+    engine.register_fn("set_variable", move |name: &str, value: Dynamic| {
+        let value = value.to_string();
+        let mut state = lock(&vars);
+        state.variables.insert(name.to_owned(), value.clone());
+        state.set.push((name.to_owned(), value));
+    });
+
+    engine
+        .register_fn("timestamp", || now().as_secs() as i64)
+        .register_fn("timestamp_ms", || now().as_millis() as i64)
+        .register_fn("uuid", uuid_v4)
+        .register_fn("base64_encode", |s: &str| {
+            base64::engine::general_purpose::STANDARD.encode(s)
+        })
+        .register_fn(
+            "base64_decode",
+            |s: &str| -> Result<String, Box<rhai::EvalAltResult>> {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(s.trim())
+                    .map_err(|e| format!("invalid base64: {}", e))?;
+                Ok(String::from_utf8_lossy(&bytes).into_owned())
+            },
+        )
+        .register_fn("sha256", |s: &str| format!("{:x}", Sha256::digest(s)))
+        .register_fn("hmac_sha256", |key: &str, message: &str| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+                .expect("hmac takes keys of any length");
+            mac.update(message.as_bytes());
+            format!("{:x}", mac.finalize().into_bytes())
+        });
+
+    engine
+        .register_type_with_name::<ScriptRequest>("Request")
+        .register_get_set(
+            "method",
+            |r: &mut ScriptRequest| r.method.clone(),
+            |r: &mut ScriptRequest, method: String| r.method = method,
+        )
+        .register_get_set(
+            "url",
+            |r: &mut ScriptRequest| r.url.clone(),
+            |r: &mut ScriptRequest, url: String| r.url = url,
+        )
+        // anything but a raw body reads as its summary; setting one makes it raw
+        .register_get_set(
+            "body",
+            |r: &mut ScriptRequest| r.body.summary(),
+            |r: &mut ScriptRequest, body: String| r.body = RequestBody::Raw(body),
+        )
+        .register_get("headers", |r: &mut ScriptRequest| pairs_map(&r.headers))
+        .register_get("queries", |r: &mut ScriptRequest| pairs_map(&r.queries))
+        .register_fn("header", |r: &mut ScriptRequest, name: &str| {
+            find_pair(&r.headers, name, true)
+        })
+        .register_fn(
+            "set_header",
+            |r: &mut ScriptRequest, name: &str, value: Dynamic| {
+                set_pair(&mut r.headers, name, value.to_string(), true)
+            },
+        )
+        .register_fn("remove_header", |r: &mut ScriptRequest, name: &str| {
+            r.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name))
+        })
+        .register_fn("query", |r: &mut ScriptRequest, name: &str| {
+            find_pair(&r.queries, name, false)
+        })
+        .register_fn(
+            "set_query",
+            |r: &mut ScriptRequest, name: &str, value: Dynamic| {
+                set_pair(&mut r.queries, name, value.to_string(), false)
+            },
+        )
+        .register_fn("remove_query", |r: &mut ScriptRequest, name: &str| {
+            r.queries.retain(|(k, _)| k != name)
+        });
+
+    engine
+        .register_type_with_name::<ScriptResponse>("Response")
+        .register_get("status", |r: &mut ScriptResponse| r.status as i64)
+        .register_get("reason", |r: &mut ScriptResponse| r.reason.clone())
+        .register_get("body", |r: &mut ScriptResponse| r.body.clone())
+        .register_get("headers", |r: &mut ScriptResponse| pairs_map(&r.headers))
+        .register_fn("header", |r: &mut ScriptResponse, name: &str| {
+            find_pair(&r.headers, name, true)
+        })
+        .register_fn(
+            "json",
+            |r: &mut ScriptResponse| -> Result<Dynamic, Box<rhai::EvalAltResult>> {
+                let value: Value = serde_json::from_str(&r.body)
+                    .map_err(|e| format!("the response body isn't json: {}", e))?;
+                Ok(to_dynamic(value))
+            },
+        );
+    engine
+}
+
+fn now() -> std::time::Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// a random version 4 uuid
+fn uuid_v4() -> String {
+    let mut bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// header or query rows as a map; repeated names keep the last value
+fn pairs_map(pairs: &[(String, String)]) -> Map {
+    pairs
+        .iter()
+        .filter(|(k, _)| !k.is_empty())
+        .map(|(k, v)| (k.as_str().into(), Dynamic::from(v.clone())))
+        .collect()
+}
+
+/// the first value for `name`, or `()` when there is none
+fn find_pair(pairs: &[(String, String)], name: &str, ignore_case: bool) -> Dynamic {
+    pairs
+        .iter()
+        .find(|(k, _)| matches_name(k, name, ignore_case))
+        .map_or(Dynamic::UNIT, |(_, v)| Dynamic::from(v.clone()))
+}
+
+/// replaces the first row named `name` and drops any repeats, or adds a row
+fn set_pair(pairs: &mut Vec<(String, String)>, name: &str, value: String, ignore_case: bool) {
+    match pairs
+        .iter()
+        .position(|(k, _)| matches_name(k, name, ignore_case))
+    {
+        Some(first) => {
+            pairs[first].1 = value;
+            let mut index = 0;
+            pairs.retain(|(k, _)| {
+                index += 1;
+                index - 1 == first || !matches_name(k, name, ignore_case)
+            });
+        }
+        None => pairs.push((name.to_owned(), value)),
+    }
+}
+
+fn matches_name(key: &str, name: &str, ignore_case: bool) -> bool {
+    if ignore_case {
+        key.eq_ignore_ascii_case(name)
+    } else {
+        key == name
+    }
+}
+
+fn to_dynamic(value: Value) -> Dynamic {
+    match value {
+        Value::Null => Dynamic::UNIT,
+        Value::Bool(b) => Dynamic::from(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Dynamic::from(i),
+            None => Dynamic::from(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Dynamic::from(s),
+        Value::Array(items) => {
+            Dynamic::from_array(items.into_iter().map(to_dynamic).collect::<Array>())
+        }
+        Value::Object(map) => Dynamic::from_map(
+            map.into_iter()
+                .map(|(k, v)| (k.into(), to_dynamic(v)))
+                .collect::<Map>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Auth;
+
+    fn request() -> ResolvedRequest {
+        ResolvedRequest {
+            method: HttpMethod::GET,
+            url: "https://example.com/items".to_owned(),
+            headers: vec![("Accept".to_owned(), "text/plain".to_owned())],
+            queries: vec![("page".to_owned(), "1".to_owned())],
+            body: RequestBody::Raw(String::new()),
+            auth: Auth::default(),
+            scripts: Scripts::default(),
+            assertions: Vec::new(),
+            variables: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn pre_request_changes_the_request_and_variables() {
+        let mut request = request();
+        let mut variables = HashMap::from([("user".to_owned(), "ann".to_owned())]);
+        let mut log = ScriptLog::default();
+        let script = r#"
+            request.method = "post";
+            request.url += "/" + variable("user");
+            request.set_header("accept", "application/json");
+            request.set_query("page", 2);
+            request.body = `{"at":${timestamp()}}`;
+            set_variable("signature", hmac_sha256("key", "message"));
+            print("signed");
+        "#;
+        run_pre_request(script, &mut request, &mut variables, &mut log).unwrap();
+        assert_eq!(request.method, HttpMethod::POST);
+        assert_eq!(request.url, "https://example.com/items/ann");
+        assert_eq!(
+            request.headers,
+            vec![("Accept".to_owned(), "application/json".to_owned())]
+        );
+        assert_eq!(request.queries, vec![("page".to_owned(), "2".to_owned())]);
+        assert!(matches!(&request.body, RequestBody::Raw(b) if b.starts_with("{\"at\":")));
+        let signature = "6e9ef29b75fffc5b7abae527d58fdadb2fe42e7219011976917343065f58ed4a";
+        assert_eq!(variables["signature"], signature);
+        assert_eq!(
+            log.variables,
+            vec![("signature".to_owned(), signature.to_owned())]
+        );
+        assert_eq!(log.console.len(), 1);
+        assert_eq!(log.console[0].text, "signed");
+    }
+
+    #[test]
+    fn pre_request_errors_stop_the_request() {
+        let mut log = ScriptLog::default();
+        let error = run_pre_request(
+            "request.method = \"fetch\";",
+            &mut request(),
+            &mut HashMap::new(),
+            &mut log,
+        )
+        .unwrap_err();
+        assert_eq!(error, "pre-request script: unknown method FETCH");
+        assert!(run_pre_request("nope(", &mut request(), &mut HashMap::new(), &mut log).is_err());
+        assert!(log.console.iter().all(|line| line.error));
+        let error =
+            run_pre_request("loop {}", &mut request(), &mut HashMap::new(), &mut log).unwrap_err();
+        assert!(error.contains("Too many operations"), "{}", error);
+    }
+
+    #[test]
+    fn post_response_reads_the_response() {
+        let response = HttpResponse {
+            status: 201,
+            headers: vec![("X-Request-Id".to_owned(), "abc".to_owned())],
+            body: br#"{"token":"t0k","items":[1,2]}"#.to_vec(),
+            ..Default::default()
+        };
+        let mut variables = HashMap::new();
+        let mut log = ScriptLog::default();
+        let script = r#"
+            let body = response.json();
+            set_variable("token", body.token);
+            print(`${response.status} ${response.header("x-request-id")} ${body.items.len()}`);
+        "#;
+        run_post_response(script, &response, &mut variables, &mut log);
+        assert_eq!(variables["token"], "t0k");
+        assert_eq!(log.console[0].text, "201 abc 2");
+        assert!(uuid_v4().chars().nth(14) == Some('4'));
+    }
+}
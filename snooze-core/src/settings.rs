@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// request timeouts in milliseconds. in the global settings `None` means no
+/// limit; on a request it means "use the global value"
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeouts {
+    pub connect_ms: Option<u64>,
+    pub total_ms: Option<u64>,
+    /// longest wait for the next piece of the response
+    pub read_ms: Option<u64>,
+}
+
+impl Timeouts {
+    /// these timeouts with unset values taken from `fallback`
+    pub fn or(&self, fallback: &Timeouts) -> Timeouts {
+        Timeouts {
+            connect_ms: self.connect_ms.or(fallback.connect_ms),
+            total_ms: self.total_ms.or(fallback.total_ms),
+            read_ms: self.read_ms.or(fallback.read_ms),
+        }
+    }
+
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect_ms.map(Duration::from_millis)
+    }
+
+    pub fn total(&self) -> Option<Duration> {
+        self.total_ms.map(Duration::from_millis)
+    }
+
+    pub fn read(&self) -> Option<Duration> {
+        self.read_ms.map(Duration::from_millis)
+    }
+}
+
+/// app-wide settings stored in the workspace's `settings.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub timeouts: Timeouts,
+    /// spaces per level when formatting a json body
+    pub json_indent: usize,
+    /// refuse to send a body labelled as json that doesn't parse
+    pub check_json_body: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            timeouts: Timeouts {
+                connect_ms: Some(10_000),
+                total_ms: None,
+                read_ms: Some(30_000),
+            },
+            json_indent: 2,
+            check_json_body: true,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        contents.push('\n');
+        fs::write(path, contents)
+    }
+}
@@ -0,0 +1,525 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SigV4Config {
+    pub access_key: String,
+    pub secret_key: String,
+    /// temporary credentials only, sent as `x-amz-security-token`
+    pub session_token: String,
+    pub region: String,
+    pub service: String,
+    /// sign `UNSIGNED-PAYLOAD` instead of the body hash
+    pub unsigned_payload: bool,
+}
+
+impl SigV4Config {
+    /// applies `f` to every text field, e.g. to substitute variables
+    pub fn map_fields(&self, mut f: impl FnMut(&str) -> String) -> SigV4Config {
+        SigV4Config {
+            access_key: f(&self.access_key),
+            secret_key: f(&self.secret_key),
+            session_token: f(&self.session_token),
+            region: f(&self.region),
+            service: f(&self.service),
+            unsigned_payload: self.unsigned_payload,
+        }
+    }
+
+    /// s3 wants the payload hash sent along and its paths encoded only once
+    fn is_s3(&self) -> bool {
+        self.service == "s3"
+    }
+
+    /// headers added besides `authorization`, as they'll appear on the request
+    pub fn extra_header_names(&self) -> Vec<&'static str> {
+        let mut names = vec!["x-amz-date"];
+        if !self.session_token.is_empty() {
+            names.push("x-amz-security-token");
+        }
+        if self.is_s3() || self.unsigned_payload {
+            names.push("x-amz-content-sha256");
+        }
+        names
+    }
+}
+
+/// adds the `x-amz-*` and `authorization` headers to the fully built
+/// `request`, signing its method, url, headers and body as of `time`
+pub fn sign(
+    config: &SigV4Config,
+    request: &mut reqwest::Request,
+    time: SystemTime,
+) -> Result<(), String> {
+    let payload_hash = if config.unsigned_payload {
+        UNSIGNED_PAYLOAD.to_owned()
+    } else {
+        let body = match request.body() {
+            Some(body) => body
+                .as_bytes()
+                .ok_or("streamed bodies can only be signed with unsigned payload")?,
+            None => &[],
+        };
+        hex_sha256(body)
+    };
+    let amz_date = amz_date(time);
+
+    let mut added = vec![("x-amz-date", amz_date.clone())];
+    if !config.session_token.is_empty() {
+        added.push(("x-amz-security-token", config.session_token.clone()));
+    }
+    if config.is_s3() || config.unsigned_payload {
+        added.push(("x-amz-content-sha256", payload_hash.clone()));
+    }
+    for (name, value) in added {
+        let value = reqwest::header::HeaderValue::from_str(&value)
+            .map_err(|e| format!("invalid {} header: {}", name, e))?;
+        request.headers_mut().insert(name, value);
+    }
+
+    // hyper adds host when sending, but it has to be signed
+    let mut headers: Vec<(String, String)> = vec![("host".to_owned(), host(request.url()))];
+    for (name, value) in request.headers().iter() {
+        if name != reqwest::header::HOST {
+            headers.push((
+                name.as_str().to_owned(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            ));
+        }
+    }
+    let authorization = authorization(
+        config,
+        request.method().as_str(),
+        request.url(),
+        &headers,
+        &payload_hash,
+        &amz_date,
+    );
+    let value = reqwest::header::HeaderValue::from_str(&authorization)
+        .map_err(|e| format!("invalid authorization header: {}", e))?;
+    request
+        .headers_mut()
+        .insert(reqwest::header::AUTHORIZATION, value);
+    Ok(())
+}
+
+fn authorization(
+    config: &SigV4Config,
+    method: &str,
+    url: &Url,
+    headers: &[(String, String)],
+    payload_hash: &str,
+    amz_date: &str,
+) -> String {
+    let (signed_headers, canonical_headers) = canonical_headers(headers);
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri(url, config.is_s3()),
+        canonical_query(url),
+        canonical_headers,
+        signed_headers,
+        payload_hash
+    );
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, config.region, config.service);
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        ALGORITHM,
+        amz_date,
+        scope,
+        hex_sha256(canonical_request.as_bytes())
+    );
+    let key = signing_key(&config.secret_key, date, &config.region, &config.service);
+    let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+    format!(
+        "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+        ALGORITHM, config.access_key, scope, signed_headers, signature
+    )
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let k_region = hmac(&k_date, region.as_bytes());
+    let k_service = hmac(&k_region, service.as_bytes());
+    hmac(&k_service, b"aws4_request")
+}
+
+/// the path with each segment percent-encoded; everything but s3 encodes the
+/// already-encoded path a second time
+fn canonical_uri(url: &Url, is_s3: bool) -> String {
+    let path = url.path();
+    if path.is_empty() {
+        return "/".to_owned();
+    }
+    path.split('/')
+        .map(|segment| {
+            if is_s3 {
+                let decoded = percent_decode(segment);
+                uri_encode(&decoded)
+            } else {
+                uri_encode(segment.as_bytes())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// query pairs encoded and sorted by name, then value
+fn canonical_query(url: &Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(k.as_bytes()), uri_encode(v.as_bytes())))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// the `;`-joined signed header names, and the `name:value` lines with
+/// lowercased names, trimmed values and repeated headers joined by commas
+fn canonical_headers(headers: &[(String, String)]) -> (String, String) {
+    let mut merged: Vec<(String, String)> = Vec::new();
+    for (name, value) in headers {
+        let name = name.to_ascii_lowercase();
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        match merged.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => {
+                existing.push(',');
+                existing.push_str(&value);
+            }
+            None => merged.push((name, value)),
+        }
+    }
+    merged.sort();
+    let signed = merged
+        .iter()
+        .map(|(n, _)| n.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical = merged
+        .iter()
+        .map(|(n, v)| format!("{}:{}\n", n, v))
+        .collect();
+    (signed, canonical)
+}
+
+/// host with the port when it isn't the scheme's default, as sent in `Host`
+fn host(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    }
+}
+
+/// rfc 3986 encoding: everything but unreserved characters, uppercase hex
+fn uri_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for &b in bytes {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// undoes `%xx` escapes, leaving malformed ones as they are
+pub fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `YYYYMMDD'T'HHMMSS'Z'` in utc
+fn amz_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // days since the epoch to a civil date, after howard hinnant's algorithm
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 2015-08-30T12:36:00Z, the time used throughout the aws test suite
+    fn test_time() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_440_938_160)
+    }
+
+    fn test_config(service: &str) -> SigV4Config {
+        SigV4Config {
+            access_key: "AKIDEXAMPLE".to_owned(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_owned(),
+            region: "us-east-1".to_owned(),
+            service: service.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn signed(
+        config: &SigV4Config,
+        method: reqwest::Method,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> reqwest::Request {
+        let mut request = reqwest::Request::new(method, Url::parse(url).unwrap());
+        for (name, value) in headers {
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes()).unwrap();
+            request.headers_mut().insert(name, value.parse().unwrap());
+        }
+        if !body.is_empty() {
+            *request.body_mut() = Some(body.to_owned().into());
+        }
+        sign(config, &mut request, test_time()).unwrap();
+        request
+    }
+
+    fn authorization_header(request: &reqwest::Request) -> &str {
+        request.headers()["authorization"].to_str().unwrap()
+    }
+
+    #[test]
+    fn formats_amz_date() {
+        assert_eq!(amz_date(test_time()), "20150830T123600Z");
+        assert_eq!(amz_date(UNIX_EPOCH), "19700101T000000Z");
+        assert_eq!(
+            amz_date(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "20000229T000000Z"
+        );
+    }
+
+    #[test]
+    fn derives_signing_key() {
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20150830",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9"
+        );
+    }
+
+    #[test]
+    fn get_vanilla() {
+        let request = signed(
+            &test_config("service"),
+            reqwest::Method::GET,
+            "https://example.amazonaws.com/",
+            &[],
+            "",
+        );
+        assert_eq!(
+            authorization_header(&request),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+        assert_eq!(request.headers()["x-amz-date"], "20150830T123600Z");
+        assert!(request.headers().get("x-amz-content-sha256").is_none());
+    }
+
+    #[test]
+    fn get_vanilla_query_order_key_case() {
+        let request = signed(
+            &test_config("service"),
+            reqwest::Method::GET,
+            "https://example.amazonaws.com/?Param2=value2&Param1=value1",
+            &[],
+            "",
+        );
+        assert!(authorization_header(&request).ends_with(
+            "Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        ));
+    }
+
+    #[test]
+    fn post_vanilla() {
+        let request = signed(
+            &test_config("service"),
+            reqwest::Method::POST,
+            "https://example.amazonaws.com/",
+            &[],
+            "",
+        );
+        assert!(authorization_header(&request).ends_with(
+            "Signature=5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        ));
+    }
+
+    #[test]
+    fn post_x_www_form_urlencoded() {
+        let request = signed(
+            &test_config("service"),
+            reqwest::Method::POST,
+            "https://example.amazonaws.com/",
+            &[("content-type", "application/x-www-form-urlencoded")],
+            "Param1=value1",
+        );
+        assert_eq!(
+            authorization_header(&request),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a"
+        );
+    }
+
+    #[test]
+    fn iam_list_users() {
+        let request = signed(
+            &test_config("iam"),
+            reqwest::Method::GET,
+            "https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08",
+            &[(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )],
+            "",
+        );
+        assert_eq!(
+            authorization_header(&request),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn session_token_is_sent_and_signed() {
+        let config = SigV4Config {
+            session_token: "session-token".to_owned(),
+            ..test_config("service")
+        };
+        let request = signed(
+            &config,
+            reqwest::Method::GET,
+            "https://example.amazonaws.com/",
+            &[],
+            "",
+        );
+        assert_eq!(request.headers()["x-amz-security-token"], "session-token");
+        assert!(authorization_header(&request)
+            .contains("SignedHeaders=host;x-amz-date;x-amz-security-token,"));
+    }
+
+    #[test]
+    fn unsigned_payload_is_declared_and_signed() {
+        let config = SigV4Config {
+            unsigned_payload: true,
+            ..test_config("s3")
+        };
+        let request = signed(
+            &config,
+            reqwest::Method::PUT,
+            "https://bucket.s3.amazonaws.com/key",
+            &[],
+            "data",
+        );
+        assert_eq!(request.headers()["x-amz-content-sha256"], UNSIGNED_PAYLOAD);
+        assert!(authorization_header(&request)
+            .contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date,"));
+    }
+
+    #[test]
+    fn s3_sends_payload_hash() {
+        let request = signed(
+            &test_config("s3"),
+            reqwest::Method::PUT,
+            "https://bucket.s3.amazonaws.com/key",
+            &[],
+            "",
+        );
+        assert_eq!(
+            request.headers()["x-amz-content-sha256"],
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn canonical_uri_encoding() {
+        let url = Url::parse("https://example.com/a b/c%2Fd").unwrap();
+        assert_eq!(canonical_uri(&url, false), "/a%2520b/c%252Fd");
+        assert_eq!(canonical_uri(&url, true), "/a%20b/c%2Fd");
+    }
+
+    #[test]
+    fn canonical_headers_trim_and_merge() {
+        let (signed, canonical) = canonical_headers(&[
+            ("My-Header1".to_owned(), "  value1  ".to_owned()),
+            ("my-header1".to_owned(), "a   b   c".to_owned()),
+            ("host".to_owned(), "example.com".to_owned()),
+        ]);
+        assert_eq!(signed, "host;my-header1");
+        assert_eq!(canonical, "host:example.com\nmy-header1:value1,a b c\n");
+    }
+}
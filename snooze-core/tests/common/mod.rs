@@ -0,0 +1,192 @@
+//! a tiny http/1.1 server on a random local port for the integration tests.
+//! every connection is closed after one response
+//!
+//! - `/json` answers `{"id": 7, "name": "ann"}`
+//! - `/echo…` answers with the request's method, path, headers and body as json
+//! - `/status/<code>` answers with that status and no body
+//! - `/slow` waits two seconds before answering
+//! - `/upload` waits a second before reading the body, so big ones stall the
+//!   sender, then answers with how many bytes came in
+//! - `/redirect` redirects to `/json`
+//! - `/digest…` wants digest auth as `ann` / `hunter2`, answering a wrong or
+//!   missing `Authorization` with a challenge and a right one with `{"user": "ann"}`
+//! - `/token` is an oauth2 token endpoint for client `app` / `s3cret`, taking
+//!   them in the body or a basic header. client credentials grants get
+//!   `cc-<body|basic>` expiring in 30 seconds with refresh token `r1`, which
+//!   refreshes to `refreshed-<body|basic>`
+
+#![allow(dead_code)]
+
+use base64::Engine;
+use md5::{Digest, Md5};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+/// starts the server and returns its base url, e.g. `http://127.0.0.1:41234`
+pub fn serve() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind the test server");
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            thread::spawn(move || {
+                let _ = handle(stream);
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn handle(stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let path = parts.next().unwrap_or_default().to_owned();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+        }
+    }
+    let length = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if path == "/upload" {
+        thread::sleep(Duration::from_secs(1));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let (status, extra, content): (u16, &str, String) = if path == "/json" {
+        (200, "", r#"{"id": 7, "name": "ann"}"#.to_owned())
+    } else if path.starts_with("/echo") {
+        let echo = serde_json::json!({
+            "method": method,
+            "path": path,
+            "headers": headers,
+            "body": String::from_utf8_lossy(&body),
+        });
+        (200, "", echo.to_string())
+    } else if let Some(code) = path.strip_prefix("/status/") {
+        (code.parse().unwrap_or(400), "", String::new())
+    } else if path == "/slow" {
+        thread::sleep(Duration::from_secs(2));
+        (200, "", "finally".to_owned())
+    } else if path == "/upload" {
+        (200, "", format!(r#"{{"received": {}}}"#, body.len()))
+    } else if path == "/redirect" {
+        (302, "Location: /json\r\n", String::new())
+    } else if path.starts_with("/digest") {
+        match headers.get("authorization") {
+            Some(header) if digest_matches(header, &method, &path) => {
+                (200, "", r#"{"user": "ann"}"#.to_owned())
+            }
+            _ => (401, DIGEST_CHALLENGE, String::new()),
+        }
+    } else if path == "/token" {
+        token(&headers, &body)
+    } else {
+        (404, "", String::new())
+    };
+    let content_type = if content.starts_with('{') {
+        "Content-Type: application/json\r\n"
+    } else {
+        ""
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} Test\r\n{}{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        extra,
+        content.len(),
+        content
+    )?;
+    stream.flush()
+}
+
+const DIGEST_CHALLENGE: &str =
+    "WWW-Authenticate: Digest realm=\"snooze\", qop=\"auth\", nonce=\"5a7f3e\", opaque=\"0c1d\"\r\n";
+
+/// checks a digest `Authorization` header against the challenge above, the
+/// way a server would
+fn digest_matches(header: &str, method: &str, path: &str) -> bool {
+    let Some(params) = header.strip_prefix("Digest ") else {
+        return false;
+    };
+    let params: HashMap<&str, &str> = params
+        .split(", ")
+        .filter_map(|param| param.split_once('='))
+        .map(|(k, v)| (k, v.trim_matches('"')))
+        .collect();
+    let get = |name: &str| params.get(name).copied().unwrap_or_default();
+    let h = |data: String| format!("{:x}", Md5::digest(data.as_bytes()));
+    let ha1 = h("ann:snooze:hunter2".to_owned());
+    let ha2 = h(format!("{}:{}", method, path));
+    let expected = h(format!(
+        "{}:5a7f3e:{}:{}:auth:{}",
+        ha1,
+        get("nc"),
+        get("cnonce"),
+        ha2
+    ));
+    get("username") == "ann"
+        && get("uri") == path
+        && get("opaque") == "0c1d"
+        && !get("cnonce").is_empty()
+        && get("response") == expected
+}
+
+fn token(headers: &HashMap<String, String>, body: &[u8]) -> (u16, &'static str, String) {
+    let form: HashMap<String, String> = url::form_urlencoded::parse(body).into_owned().collect();
+    let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+    let basic = format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode("app:s3cret")
+    );
+    let client = if headers.get("authorization") == Some(&basic) {
+        "basic"
+    } else if field("client_id") == "app" && field("client_secret") == "s3cret" {
+        "body"
+    } else {
+        return (
+            401,
+            "",
+            r#"{"error": "invalid_client", "error_description": "bad client credentials"}"#
+                .to_owned(),
+        );
+    };
+    let token = match (field("grant_type"), field("refresh_token")) {
+        ("client_credentials", _) => serde_json::json!({
+            "access_token": format!("cc-{}", client),
+            "expires_in": 30,
+            "refresh_token": "r1",
+        }),
+        ("refresh_token", "r1") => serde_json::json!({
+            "access_token": format!("refreshed-{}", client),
+            "expires_in": 3600,
+        }),
+        _ => {
+            return (
+                400,
+                "",
+                r#"{"error": "invalid_grant", "error_description": "unknown refresh token"}"#
+                    .to_owned(),
+            )
+        }
+    };
+    (200, "", token.to_string())
+}
@@ -0,0 +1,387 @@
+mod common;
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::time::Duration;
+
+use snooze_core::assertions::{Assertion, AssertionKind};
+use snooze_core::auth::AuthKind;
+use snooze_core::body::{BinaryFile, BodyMode, FormPart, PartKind, UploadProgress};
+use snooze_core::collection::Workspace;
+use snooze_core::response::TimeoutKind;
+use snooze_core::settings::Timeouts;
+use snooze_core::{
+    Executor, HttpMethod, RequestError, RequestResult, ResolvedRequest, SavedRequest,
+};
+
+/// resolves `saved` against `vars` and sends it, waiting for the result
+fn send(saved: &SavedRequest, vars: &[(&str, &str)]) -> RequestResult {
+    send_tracked(saved, vars, Arc::new(UploadProgress::default()))
+}
+
+/// `send`, reporting file uploads to `progress`
+fn send_tracked(
+    saved: &SavedRequest,
+    vars: &[(&str, &str)],
+    progress: Arc<UploadProgress>,
+) -> RequestResult {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let mut unresolved = Vec::new();
+    let request = ResolvedRequest::resolve(saved, &vars, &mut unresolved);
+    assert!(unresolved.is_empty(), "unresolved: {:?}", unresolved);
+    let executor = Executor::new().unwrap();
+    let (tx, rx) = channel();
+    executor.spawn(1, request, saved.timeouts.clone(), progress, tx);
+    rx.recv_timeout(Duration::from_secs(10))
+        .expect("no result from the executor")
+}
+
+fn get(url: &str) -> SavedRequest {
+    SavedRequest {
+        url: url.to_owned(),
+        ..Default::default()
+    }
+}
+
+fn body_json(result: &RequestResult) -> Value {
+    let response = result.response.as_ref().expect("request failed");
+    serde_json::from_slice(&response.body).expect("body isn't json")
+}
+
+#[test]
+fn gets_a_json_response_and_checks_assertions() {
+    let base = common::serve();
+    let mut saved = get(&format!("{}/json", base));
+    saved.assertions = vec![
+        Assertion {
+            kind: AssertionKind::StatusEquals,
+            expected: "200".to_owned(),
+            ..Default::default()
+        },
+        Assertion {
+            kind: AssertionKind::JsonEquals,
+            target: "$.name".to_owned(),
+            expected: "ann".to_owned(),
+            ..Default::default()
+        },
+        Assertion {
+            kind: AssertionKind::HeaderMatches,
+            target: "content-type".to_owned(),
+            expected: "json".to_owned(),
+            ..Default::default()
+        },
+    ];
+    let result = send(&saved, &[]);
+    let response = result.response.as_ref().unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(body_json(&result)["id"], 7);
+    assert_eq!(result.assertions.len(), 3);
+    assert!(
+        result.assertions.iter().all(|a| a.passed),
+        "{:?}",
+        result.assertions
+    );
+}
+
+#[test]
+fn substitutes_variables_everywhere() {
+    let base = common::serve();
+    let saved = SavedRequest {
+        method: HttpMethod::POST,
+        url: "{{base}}/echo".to_owned(),
+        queries: vec![("q".to_owned(), "{{term}}".to_owned())],
+        headers: vec![
+            ("X-Token".to_owned(), "{{token}}".to_owned()),
+            ("Content-Type".to_owned(), "application/json".to_owned()),
+        ],
+        body: r#"{"user": "{{user}}"}"#.to_owned(),
+        ..Default::default()
+    };
+    let result = send(
+        &saved,
+        &[
+            ("base", &base),
+            ("term", "rust"),
+            ("token", "secret"),
+            ("user", "ann"),
+        ],
+    );
+    let echo = body_json(&result);
+    assert_eq!(echo["method"], "POST");
+    assert_eq!(echo["path"], "/echo?q=rust");
+    assert_eq!(echo["headers"]["x-token"], "secret");
+    assert_eq!(echo["body"], r#"{"user": "ann"}"#);
+}
+
+#[test]
+fn sends_a_form_urlencoded_body() {
+    let base = common::serve();
+    let saved = SavedRequest {
+        method: HttpMethod::POST,
+        url: format!("{}/echo", base),
+        body_mode: BodyMode::FormUrlEncoded,
+        form: vec![
+            ("q".to_owned(), "{{term}} & more".to_owned()),
+            (String::new(), "skipped".to_owned()),
+            ("name".to_owned(), "zoë".to_owned()),
+        ],
+        ..Default::default()
+    };
+    let echo = body_json(&send(&saved, &[("term", "rust")]));
+    assert_eq!(
+        echo["headers"]["content-type"],
+        "application/x-www-form-urlencoded"
+    );
+    assert_eq!(echo["body"], "q=rust+%26+more&name=zo%C3%AB");
+}
+
+#[test]
+fn sends_a_multipart_body() {
+    let base = common::serve();
+    let dir = std::env::temp_dir().join(format!("snooze-multipart-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("notes.txt");
+    std::fs::write(&path, "from disk").unwrap();
+    let saved = SavedRequest {
+        method: HttpMethod::POST,
+        url: format!("{}/echo", base),
+        // a typed content type can't name the boundary, so it's replaced
+        headers: vec![("Content-Type".to_owned(), "multipart/form-data".to_owned())],
+        body_mode: BodyMode::Multipart,
+        multipart: vec![
+            FormPart {
+                name: "user".to_owned(),
+                value: "{{user}}".to_owned(),
+                ..Default::default()
+            },
+            FormPart {
+                name: "notes".to_owned(),
+                kind: PartKind::File,
+                value: path.to_string_lossy().into_owned(),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    let echo = body_json(&send(&saved, &[("user", "ann")]));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let content_type = echo["headers"]["content-type"].as_str().unwrap();
+    let boundary = content_type
+        .strip_prefix("multipart/form-data; boundary=")
+        .unwrap_or_else(|| panic!("unexpected content type {}", content_type));
+    assert_eq!(
+        echo["body"],
+        format!(
+            "--{b}\r\n\
+             Content-Disposition: form-data; name=\"user\"\r\n\r\nann\r\n\
+             --{b}\r\n\
+             Content-Disposition: form-data; name=\"notes\"; filename=\"notes.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\nfrom disk\r\n\
+             --{b}--\r\n",
+            b = boundary
+        )
+    );
+}
+
+#[test]
+fn streams_a_file_body() {
+    let base = common::serve();
+    let dir = std::env::temp_dir().join(format!("snooze-file-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("data.json");
+    // several reads' worth, so progress is counted in steps
+    let contents = format!("[{}0]", "1234567,".repeat(40_000));
+    std::fs::write(&path, &contents).unwrap();
+    let mut saved = SavedRequest {
+        method: HttpMethod::PUT,
+        url: format!("{}/echo", base),
+        body_mode: BodyMode::Binary,
+        binary: BinaryFile {
+            path: path.to_string_lossy().into_owned(),
+            content_type: String::new(),
+        },
+        ..Default::default()
+    };
+
+    let progress = Arc::new(UploadProgress::default());
+    let echo = body_json(&send_tracked(&saved, &[], progress.clone()));
+    assert_eq!(echo["body"], contents);
+    assert_eq!(
+        echo["headers"]["content-length"],
+        contents.len().to_string()
+    );
+    assert_eq!(echo["headers"]["content-type"], "application/json");
+    assert!(echo["headers"].get("transfer-encoding").is_none());
+    let total = contents.len() as u64;
+    assert_eq!(progress.get(), Some((total, total)));
+
+    saved.binary.content_type = "text/csv".to_owned();
+    let echo = body_json(&send(&saved, &[]));
+    assert_eq!(echo["headers"]["content-type"], "text/csv");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    saved.binary.path = dir.join("gone.bin").to_string_lossy().into_owned();
+    match send(&saved, &[]).response {
+        Err(RequestError::Failed(message)) => {
+            assert!(message.starts_with("failed to read "), "{}", message)
+        }
+        other => panic!("expected a failure, got {:?}", other),
+    }
+}
+
+#[test]
+fn sends_basic_auth() {
+    let base = common::serve();
+    let mut saved = get(&format!("{}/echo", base));
+    saved.auth.kind = AuthKind::Basic;
+    saved.auth.username = "ann".to_owned();
+    saved.auth.password = "hunter2".to_owned();
+    let echo = body_json(&send(&saved, &[]));
+    assert_eq!(echo["headers"]["authorization"], "Basic YW5uOmh1bnRlcjI=");
+}
+
+#[test]
+fn answers_a_digest_challenge() {
+    let base = common::serve();
+    let mut saved = get(&format!("{}/digest/files?name=a", base));
+    saved.auth.kind = AuthKind::Digest;
+    saved.auth.username = "ann".to_owned();
+    saved.auth.password = "hunter2".to_owned();
+    let result = send(&saved, &[]);
+    assert_eq!(result.response.as_ref().unwrap().status, 200);
+    assert_eq!(body_json(&result)["user"], "ann");
+
+    saved.auth.password = "wrong".to_owned();
+    let result = send(&saved, &[]);
+    assert_eq!(result.response.as_ref().unwrap().status, 401);
+}
+
+#[test]
+fn scripts_change_the_request_and_set_variables() {
+    let base = common::serve();
+    let mut saved = get(&format!("{}/echo", base));
+    saved.scripts.pre_request = r#"request.set_header("X-User", variable("user"));"#.to_owned();
+    saved.scripts.post_response =
+        r#"set_variable("seen", response.json().headers["x-user"]);"#.to_owned();
+    let result = send(&saved, &[("user", "ann")]);
+    assert_eq!(body_json(&result)["headers"]["x-user"], "ann");
+    assert_eq!(
+        result.script_log.variables,
+        vec![("seen".to_owned(), "ann".to_owned())]
+    );
+}
+
+#[test]
+fn reports_the_request_as_it_went_out() {
+    let base = common::serve();
+    let mut saved = get(&format!("{}/echo", base));
+    saved.auth.kind = AuthKind::Basic;
+    saved.auth.username = "ann".to_owned();
+    saved.auth.password = "hunter2".to_owned();
+    saved.scripts.pre_request = r#"
+        set_variable("page", "2");
+        request.set_query("page", "{{page}}");
+    "#
+    .to_owned();
+    let result = send(&saved, &[]);
+    let sent = result.sent.as_ref().expect("nothing reported as sent");
+    assert_eq!(sent.url, format!("{}/echo?page=2", base));
+    assert!(
+        sent.headers
+            .iter()
+            .any(|(k, v)| k == "authorization" && v == "Basic YW5uOmh1bnRlcjI="),
+        "{:?}",
+        sent.headers
+    );
+
+    // nothing goes out when the script fails
+    saved.scripts.pre_request = "throw \"no\";".to_owned();
+    assert!(send(&saved, &[]).sent.is_none());
+}
+
+#[test]
+fn follows_redirects() {
+    let base = common::serve();
+    let result = send(&get(&format!("{}/redirect", base)), &[]);
+    let response = result.response.as_ref().unwrap();
+    assert_eq!(response.status, 200);
+    assert!(response.url.ends_with("/json"), "{}", response.url);
+}
+
+#[test]
+fn reports_a_total_timeout() {
+    let base = common::serve();
+    let mut saved = get(&format!("{}/slow", base));
+    saved.timeouts = Timeouts {
+        total_ms: Some(200),
+        ..Default::default()
+    };
+    saved.assertions = vec![Assertion::default()];
+    let result = send(&saved, &[]);
+    match &result.response {
+        Err(RequestError::Timeout { kind, .. }) => assert_eq!(*kind, TimeoutKind::Total),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+    assert!(!result.assertions[0].passed);
+}
+
+#[test]
+fn reports_a_read_timeout() {
+    let base = common::serve();
+    let mut saved = get(&format!("{}/slow", base));
+    saved.timeouts = Timeouts {
+        read_ms: Some(200),
+        ..Default::default()
+    };
+    match &send(&saved, &[]).response {
+        Err(RequestError::Timeout { kind, .. }) => assert_eq!(*kind, TimeoutKind::Read),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
+
+#[test]
+fn slow_uploads_dont_hit_the_read_timeout() {
+    let base = common::serve();
+    // far more than the socket buffers hold, so sending waits on the server
+    let size = 32 * 1024 * 1024;
+    let saved = SavedRequest {
+        method: HttpMethod::POST,
+        url: format!("{}/upload", base),
+        body: "x".repeat(size),
+        timeouts: Timeouts {
+            read_ms: Some(500),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let result = send(&saved, &[]);
+    assert!(
+        result.duration >= Duration::from_secs(1),
+        "{:?}",
+        result.duration
+    );
+    assert_eq!(body_json(&result)["received"], size);
+}
+
+#[test]
+fn saved_requests_round_trip() {
+    let dir = std::env::temp_dir().join(format!("snooze-core-test-{}", std::process::id()));
+    let path = Workspace::request_path(&dir, "users: list");
+    let mut saved = get("{{base}}/users");
+    saved.assertions = vec![Assertion {
+        kind: AssertionKind::StatusInRange,
+        expected: "2xx".to_owned(),
+        ..Default::default()
+    }];
+    saved.scripts.post_response = "print(response.status);".to_owned();
+    Workspace::save_request(&path, &saved).unwrap();
+    assert_eq!(path.file_name().unwrap(), "users_ list.json");
+    assert_eq!(Workspace::load_request(&path).unwrap(), saved);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use snooze_core::oauth::{ClientAuthentication, GrantType, OAuth2Config, TokenCache};
+
+fn config(base: &str, grant: GrantType) -> OAuth2Config {
+    OAuth2Config {
+        grant,
+        token_url: format!("{}/token", base),
+        client_id: "app".to_owned(),
+        client_secret: "s3cret".to_owned(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn gets_a_client_credentials_token_and_refreshes_it() {
+    let base = common::serve();
+    let client = reqwest::Client::new();
+    let cache = TokenCache::default();
+    let config = config(&base, GrantType::ClientCredentials);
+
+    assert_eq!(
+        cache.access_token(&client, &config).await.unwrap(),
+        "cc-body"
+    );
+    let cached = cache.get(&config).unwrap();
+    assert_eq!(cached.refresh_token.as_deref(), Some("r1"));
+    // it expires inside the refresh margin, so the next use refreshes it
+    assert!(!cached.is_fresh());
+    assert_eq!(
+        cache.access_token(&client, &config).await.unwrap(),
+        "refreshed-body"
+    );
+    let refreshed = cache.get(&config).unwrap();
+    assert!(refreshed.is_fresh());
+    // the server didn't rotate it, so the old one is kept
+    assert_eq!(refreshed.refresh_token.as_deref(), Some("r1"));
+    assert_eq!(
+        cache.access_token(&client, &config).await.unwrap(),
+        "refreshed-body"
+    );
+}
+
+#[tokio::test]
+async fn sends_client_credentials_in_a_basic_header() {
+    let base = common::serve();
+    let client = reqwest::Client::new();
+    let mut config = config(&base, GrantType::ClientCredentials);
+    config.client_authentication = ClientAuthentication::BasicHeader;
+    let token = TokenCache::default().access_token(&client, &config).await;
+    assert_eq!(token.unwrap(), "cc-basic");
+}
+
+#[tokio::test]
+async fn uses_a_configured_refresh_token() {
+    let base = common::serve();
+    let client = reqwest::Client::new();
+    let mut config = config(&base, GrantType::RefreshToken);
+    config.refresh_token = "r1".to_owned();
+    let cache = TokenCache::default();
+    assert_eq!(
+        cache.access_token(&client, &config).await.unwrap(),
+        "refreshed-body"
+    );
+
+    config.refresh_token = "revoked".to_owned();
+    assert_eq!(
+        cache.access_token(&client, &config).await.unwrap_err(),
+        "token request failed (400 Bad Request): invalid_grant unknown refresh token"
+    );
+    assert!(cache.get(&config).is_none());
+}
+
+#[tokio::test]
+async fn reports_rejected_client_credentials() {
+    let base = common::serve();
+    let client = reqwest::Client::new();
+    let mut config = config(&base, GrantType::ClientCredentials);
+    config.client_secret = "wrong".to_owned();
+    assert_eq!(
+        TokenCache::default()
+            .access_token(&client, &config)
+            .await
+            .unwrap_err(),
+        "token request failed (401 Unauthorized): invalid_client bad client credentials"
+    );
+}
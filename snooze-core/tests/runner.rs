@@ -0,0 +1,148 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use snooze_core::assertions::{Assertion, AssertionKind};
+use snooze_core::runner::{RunEvent, RunOptions, RunStep, StepResult};
+use snooze_core::settings::Timeouts;
+use snooze_core::{Executor, SavedRequest};
+
+fn step(name: &str, url: &str, assertions: Vec<Assertion>) -> RunStep {
+    RunStep {
+        name: name.to_owned(),
+        request: SavedRequest {
+            url: url.to_owned(),
+            assertions,
+            ..Default::default()
+        },
+    }
+}
+
+fn assertion(kind: AssertionKind, target: &str, expected: &str) -> Assertion {
+    Assertion {
+        kind,
+        target: target.to_owned(),
+        expected: expected.to_owned(),
+        ..Default::default()
+    }
+}
+
+/// runs `steps` to the end, returning every result and whether a failure stopped it
+fn run(
+    steps: Vec<RunStep>,
+    base: &str,
+    data: Vec<HashMap<String, String>>,
+    options: RunOptions,
+) -> (Vec<StepResult>, bool) {
+    let executor = Executor::new().unwrap();
+    let (tx, rx) = channel();
+    let variables = HashMap::from([("base".to_owned(), base.to_owned())]);
+    let _abort = executor.run(steps, variables, data, options, &Timeouts::default(), tx);
+    let mut results = Vec::new();
+    loop {
+        match rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("run stalled")
+        {
+            RunEvent::Step(result) => results.push(result),
+            RunEvent::Finished { stopped } => return (results, stopped),
+        }
+    }
+}
+
+#[test]
+fn variables_flow_from_one_step_to_the_next() {
+    let base = common::serve();
+    let mut login = step(
+        "login",
+        "{{base}}/json",
+        vec![assertion(AssertionKind::StatusEquals, "", "200")],
+    );
+    login.request.scripts.post_response =
+        r#"set_variable("name", response.json().name);"#.to_owned();
+    let profile = step(
+        "profile",
+        "{{base}}/echo/{{name}}",
+        vec![assertion(AssertionKind::JsonEquals, "$.path", "/echo/ann")],
+    );
+    let (results, stopped) = run(
+        vec![login, profile],
+        &base,
+        Vec::new(),
+        RunOptions::default(),
+    );
+    assert!(!stopped);
+    let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, ["login", "profile"]);
+    assert!(results.iter().all(|r| r.passed()), "{:?}", results);
+}
+
+#[test]
+fn iterates_data_rows_and_stops_on_failure() {
+    let base = common::serve();
+    let steps = vec![step(
+        "status",
+        "{{base}}/status/{{code}}",
+        vec![assertion(AssertionKind::StatusInRange, "", "2xx")],
+    )];
+    let rows = ["204", "500", "200"]
+        .iter()
+        .map(|code| HashMap::from([("code".to_owned(), code.to_string())]))
+        .collect::<Vec<_>>();
+
+    let (results, stopped) = run(steps.clone(), &base, rows.clone(), RunOptions::default());
+    assert!(!stopped);
+    let passed: Vec<bool> = results.iter().map(|r| r.passed()).collect();
+    assert_eq!(passed, [true, false, true]);
+    assert_eq!(results[1].status, Some(500));
+    assert_eq!(results[1].iteration, 1);
+
+    let options = RunOptions {
+        stop_on_failure: true,
+        ..Default::default()
+    };
+    let (results, stopped) = run(steps, &base, rows, options);
+    assert!(stopped);
+    assert_eq!(results.len(), 2);
+}
+
+#[test]
+fn concurrent_batches_keep_step_order() {
+    let base = common::serve();
+    let steps = (0..6)
+        .map(|i| {
+            step(
+                &format!("step {}", i),
+                &format!("{{{{base}}}}/echo/{}", i),
+                vec![assertion(
+                    AssertionKind::JsonEquals,
+                    "$.path",
+                    &format!("/echo/{}", i),
+                )],
+            )
+        })
+        .collect();
+    let options = RunOptions {
+        concurrency: 3,
+        ..Default::default()
+    };
+    let (results, _) = run(steps, &base, Vec::new(), options);
+    let names: Vec<String> = results.iter().map(|r| r.name.clone()).collect();
+    let expected: Vec<String> = (0..6).map(|i| format!("step {}", i)).collect();
+    assert_eq!(names, expected);
+    assert!(results.iter().all(|r| r.passed()));
+}
+
+#[test]
+fn unreachable_hosts_fail_the_step() {
+    let steps = vec![step(
+        "down",
+        "http://127.0.0.1:9/",
+        vec![assertion(AssertionKind::StatusEquals, "", "200")],
+    )];
+    let (results, _) = run(steps, "", Vec::new(), RunOptions::default());
+    assert!(results[0].error.is_some());
+    assert!(!results[0].passed());
+}
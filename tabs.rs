@@ -0,0 +1,201 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::viewer::{ViewCache, Viewer};
+use crate::ResponseTab;
+use snooze_core::assertions::AssertionResult;
+use snooze_core::body::FormPart;
+use snooze_core::collection::{self, SavedRequest};
+use snooze_core::graphql::Schema;
+use snooze_core::response::{HttpResponse, RequestError, RequestResult};
+use snooze_core::script::ConsoleLine;
+
+/// how long a tab title may get before it's cut short
+const MAX_TITLE_CHARS: usize = 28;
+
+/// one open request: the editor fields, the file they came from, and
+/// whatever came back the last time it was sent
+pub struct RequestTab {
+    /// stays the same when tabs are reordered; keys widget state and pending requests
+    pub id: u64,
+    pub request: SavedRequest,
+    /// `request` as it was when last loaded or saved, for the unsaved marker
+    pub baseline: SavedRequest,
+    pub path: Option<PathBuf>,
+    /// the request this tab is waiting on, if any
+    pub in_flight: Option<u64>,
+    pub response_text: String,
+    pub last_response: Option<HttpResponse>,
+    pub response_tab: ResponseTab,
+    pub parsed_json: Option<Value>,
+    pub last_duration: Option<Duration>,
+    pub last_error: Option<RequestError>,
+    pub search_input: String,
+    /// the body viewer picked by hand, `None` to go by content type
+    pub viewer: Option<Viewer>,
+    pub view_cache: ViewCache,
+    /// where the response was last saved to, or why that failed
+    pub save_status: String,
+    /// what the last request's scripts printed
+    pub console: Vec<ConsoleLine>,
+    /// how the last request's assertions went
+    pub assertion_results: Vec<AssertionResult>,
+    /// the introspected graphql schema, for the browser and completion
+    pub schema: Option<Schema>,
+    pub schema_status: String,
+    /// the introspection request this tab is waiting on, if any
+    pub introspecting: Option<u64>,
+    /// shown under the url until the next send, e.g. what a history restore cleared
+    pub notice: String,
+}
+
+impl RequestTab {
+    pub fn new(id: u64) -> Self {
+        Self::from_saved(id, blank_request(), None)
+    }
+
+    pub fn from_saved(id: u64, request: SavedRequest, path: Option<PathBuf>) -> Self {
+        let baseline = if path.is_some() {
+            request.clone()
+        } else {
+            blank_request()
+        };
+        Self {
+            id,
+            request,
+            baseline,
+            path,
+            in_flight: None,
+            response_text: String::new(),
+            last_response: None,
+            response_tab: ResponseTab::Body,
+            parsed_json: None,
+            last_duration: None,
+            last_error: None,
+            search_input: String::new(),
+            viewer: None,
+            view_cache: ViewCache::default(),
+            save_status: String::new(),
+            console: Vec::new(),
+            assertion_results: Vec::new(),
+            schema: None,
+            schema_status: String::new(),
+            introspecting: None,
+            notice: String::new(),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        if let Some(path) = &self.path {
+            return collection::request_name(path);
+        }
+        let url = self.request.url.trim();
+        let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+        if url.is_empty() {
+            return "untitled".to_owned();
+        }
+        if url.chars().count() > MAX_TITLE_CHARS {
+            let short: String = url.chars().take(MAX_TITLE_CHARS - 1).collect();
+            format!("{}…", short)
+        } else {
+            url.to_owned()
+        }
+    }
+
+    /// whether the editor differs from the file it was loaded from, or from a
+    /// blank request when there is no file. the selected editor tab doesn't count
+    pub fn is_dirty(&self) -> bool {
+        let mut baseline = self.baseline.clone();
+        baseline.tab = self.request.tab.clone();
+        baseline != self.request
+    }
+
+    /// nothing typed, loaded or received yet, so it can be reused for the next load
+    pub fn is_blank(&self) -> bool {
+        self.path.is_none()
+            && !self.is_dirty()
+            && self.in_flight.is_none()
+            && self.response_text.is_empty()
+    }
+
+    pub fn mark_saved(&mut self, path: &Path) {
+        self.baseline = self.request.clone();
+        self.path = Some(path.to_path_buf());
+    }
+
+    /// forgets the file this tab was loaded from, e.g. when its contents were
+    /// replaced or the file was deleted
+    pub fn detach(&mut self) {
+        self.path = None;
+        self.baseline = blank_request();
+    }
+
+    pub fn clear_response(&mut self) {
+        self.parsed_json = None;
+        self.last_response = None;
+        self.last_error = None;
+        self.last_duration = None;
+        self.view_cache = ViewCache::default();
+        self.save_status.clear();
+        self.console.clear();
+        self.assertion_results.clear();
+    }
+
+    pub fn show_result(&mut self, result: RequestResult) {
+        self.in_flight = None;
+        match result.response {
+            Ok(resp) => {
+                self.response_text = resp.text();
+                self.last_response = Some(resp);
+                self.last_error = None;
+            }
+            Err(e) => {
+                self.response_text = e.to_string();
+                self.last_response = None;
+                self.last_error = Some(e);
+            }
+        }
+        self.last_duration = Some(result.duration);
+        self.parsed_json = serde_json::from_str(&self.response_text).ok();
+        self.view_cache = ViewCache::default();
+        self.save_status.clear();
+        self.console = result.script_log.console;
+        self.assertion_results = result.assertions;
+        let hidden = match self.response_tab {
+            ResponseTab::Console => self.console.is_empty(),
+            ResponseTab::Tests => self.assertion_results.is_empty(),
+            _ => false,
+        };
+        if hidden {
+            self.response_tab = ResponseTab::Body;
+        }
+    }
+
+    pub fn show_schema(&mut self, result: RequestResult) {
+        self.introspecting = None;
+        let parsed = match result.response {
+            Ok(resp) if resp.status < 400 => Schema::parse(&resp.text()),
+            Ok(resp) => Err(format!("{} {}", resp.status, resp.reason)),
+            Err(e) => Err(e.to_string()),
+        };
+        match parsed {
+            Ok(schema) => {
+                self.schema_status = format!("{} types", schema.user_types().count());
+                self.schema = Some(schema);
+            }
+            Err(e) => self.schema_status = format!("introspection failed: {}", e),
+        }
+    }
+}
+
+/// an empty request with one blank row in each table ready to type into
+pub fn blank_request() -> SavedRequest {
+    SavedRequest {
+        headers: vec![("".to_owned(), "".to_owned())],
+        queries: vec![("".to_owned(), "".to_owned())],
+        form: vec![("".to_owned(), "".to_owned())],
+        multipart: vec![FormPart::default()],
+        ..Default::default()
+    }
+}
@@ -0,0 +1,489 @@
+use egui::text::LayoutJob;
+use egui::{ColorImage, TextureHandle, TextureOptions};
+use serde_json::Value;
+
+use crate::highlight;
+use snooze_core::json_filter;
+use snooze_core::response::{self, HttpResponse};
+
+/// hex dumps stop after this many bytes, the rest is only counted
+const MAX_HEX_BYTES: usize = 64 * 1024;
+/// svgs are rasterized no larger than this on their longest side
+const MAX_SVG_SIDE: f32 = 2048.0;
+
+/// elements html never closes
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+/// elements whose contents are kept as-is rather than reindented
+const RAW_ELEMENTS: [&str; 4] = ["script", "style", "pre", "textarea"];
+
+/// the ways a response body can be shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Viewer {
+    Json,
+    Markup,
+    Image,
+    Hex,
+    Text,
+}
+
+impl Viewer {
+    pub const ALL: [Viewer; 5] = [
+        Viewer::Json,
+        Viewer::Markup,
+        Viewer::Image,
+        Viewer::Hex,
+        Viewer::Text,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Viewer::Json => "json",
+            Viewer::Markup => "xml / html",
+            Viewer::Image => "image",
+            Viewer::Hex => "hex",
+            Viewer::Text => "raw text",
+        }
+    }
+
+    /// picks a viewer from the content type, falling back to sniffing the
+    /// body when there is none or it's too generic to go by
+    pub fn detect(resp: &HttpResponse, is_json: bool) -> Viewer {
+        let content_type = resp.content_type().unwrap_or_default();
+        match content_type.as_str() {
+            "image/svg+xml" | "image/png" | "image/jpeg" | "image/gif" | "image/webp" => {
+                return Viewer::Image
+            }
+            t if t.contains("json") => return Viewer::Json,
+            t if t.contains("html") || t.contains("xml") => return Viewer::Markup,
+            _ => {}
+        }
+        if is_json {
+            return Viewer::Json;
+        }
+        if image::guess_format(&resp.body).is_ok() {
+            return Viewer::Image;
+        }
+        match std::str::from_utf8(&resp.body) {
+            Ok(text) if looks_like_markup(text) => Viewer::Markup,
+            Ok(_) => Viewer::Text,
+            Err(_) => Viewer::Hex,
+        }
+    }
+}
+
+fn looks_like_markup(text: &str) -> bool {
+    let start = text.trim_start().to_ascii_lowercase();
+    ["<?xml", "<!doctype", "<html", "<svg"]
+        .iter()
+        .any(|prefix| start.starts_with(prefix))
+}
+
+fn is_svg(resp: &HttpResponse) -> bool {
+    resp.content_type().as_deref() == Some("image/svg+xml")
+        || (image::guess_format(&resp.body).is_err()
+            && std::str::from_utf8(&resp.body).is_ok_and(|t| t.contains("<svg")))
+}
+
+/// the slow parts of showing a body, worked out on first view and kept until
+/// the next response
+#[derive(Default)]
+pub struct ViewCache {
+    markup: Option<LayoutJob>,
+    hex: Option<String>,
+    image: Option<Result<TextureHandle, String>>,
+    /// the last filter expression and what it gave
+    filtered: Option<(String, Result<Value, String>)>,
+}
+
+impl ViewCache {
+    pub fn markup(&mut self, ui: &egui::Ui, resp: &HttpResponse) -> &LayoutJob {
+        self.markup.get_or_insert_with(|| {
+            let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+            let html = resp.content_type().is_some_and(|t| t.contains("html"))
+                || resp
+                    .text()
+                    .trim_start()
+                    .to_ascii_lowercase()
+                    .starts_with("<!doctype html");
+            let pretty = pretty_markup(&resp.text(), html);
+            highlight::markup_layout_job(&pretty, font_id, ui.visuals().text_color())
+        })
+    }
+
+    pub fn hex(&mut self, resp: &HttpResponse) -> &str {
+        self.hex.get_or_insert_with(|| hex_dump(&resp.body))
+    }
+
+    /// `json` filtered by `expr`, evaluated again only when `expr` changes
+    pub fn filtered(&mut self, expr: &str, json: &Value) -> &Result<Value, String> {
+        if self.filtered.as_ref().is_none_or(|(last, _)| last != expr) {
+            self.filtered = Some((expr.to_owned(), json_filter::apply(expr, json)));
+        }
+        &self.filtered.as_ref().expect("just filled").1
+    }
+
+    /// the decoded image, uploaded as a texture named after `id`
+    pub fn image(
+        &mut self,
+        ctx: &egui::Context,
+        id: u64,
+        resp: &HttpResponse,
+    ) -> &Result<TextureHandle, String> {
+        self.image.get_or_insert_with(|| {
+            decode_image(&resp.body, is_svg(resp)).map(|image| {
+                ctx.load_texture(
+                    format!("response_image_{}", id),
+                    image,
+                    TextureOptions::LINEAR,
+                )
+            })
+        })
+    }
+}
+
+/// decodes a png, jpeg, gif or webp, or rasterizes an svg. animated images
+/// show their first frame
+fn decode_image(bytes: &[u8], svg: bool) -> Result<ColorImage, String> {
+    if !svg {
+        let image = image::load_from_memory(bytes)
+            .map_err(|e| e.to_string())?
+            .to_rgba8();
+        let size = [image.width() as usize, image.height() as usize];
+        return Ok(ColorImage::from_rgba_unmultiplied(size, image.as_raw()));
+    }
+    let tree = resvg::usvg::Tree::from_data(bytes, &resvg::usvg::Options::default())
+        .map_err(|e| e.to_string())?;
+    let size = tree.size();
+    let scale = (MAX_SVG_SIDE / size.width().max(size.height())).min(1.0);
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| "the svg has no size".to_owned())?;
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    Ok(ColorImage::from_rgba_premultiplied(
+        [width as usize, height as usize],
+        pixmap.data(),
+    ))
+}
+
+/// offset, sixteen bytes in hex and their printable characters per line
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let shown = &bytes[..bytes.len().min(MAX_HEX_BYTES)];
+    let mut out = String::with_capacity(shown.len() * 4 + 64);
+    for (i, line) in shown.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x}  ", i * 16));
+        for column in 0..16 {
+            match line.get(column) {
+                Some(byte) => out.push_str(&format!("{:02x} ", byte)),
+                None => out.push_str("   "),
+            }
+            if column == 7 {
+                out.push(' ');
+            }
+        }
+        out.push_str(" |");
+        out.extend(line.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push_str("|\n");
+    }
+    if bytes.len() > shown.len() {
+        out.push_str(&format!(
+            "… showing the first {} of {}\n",
+            response::format_size(shown.len()),
+            response::format_size(bytes.len())
+        ));
+    }
+    out
+}
+
+#[derive(Debug, PartialEq)]
+enum MarkupToken<'a> {
+    Open {
+        name: String,
+        raw: &'a str,
+    },
+    Close {
+        name: String,
+        raw: &'a str,
+    },
+    /// self-closing tags, comments, doctypes, processing instructions and cdata
+    Single(&'a str),
+    Text(&'a str),
+}
+
+/// splits markup into tags and text without checking that it's well formed
+fn markup_tokens(src: &str) -> Vec<MarkupToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = src;
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            tokens.push(MarkupToken::Text(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+        let end = if let Some(body) = rest.strip_prefix("<!--") {
+            body.find("-->").map(|i| i + 4 + 3)
+        } else if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            body.find("]]>").map(|i| i + 9 + 3)
+        } else {
+            tag_end(rest)
+        };
+        let end = end.unwrap_or(rest.len());
+        let raw = &rest[..end];
+        rest = &rest[end..];
+        let name: String = raw
+            .trim_start_matches(['<', '/'])
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if raw.starts_with("</") {
+            tokens.push(MarkupToken::Close { name, raw });
+        } else if raw.starts_with("<!") || raw.starts_with("<?") || raw.ends_with("/>") {
+            tokens.push(MarkupToken::Single(raw));
+        } else {
+            // scripts and styles may contain `<`, so skip to their closing tag
+            let content_end = RAW_ELEMENTS
+                .contains(&name.as_str())
+                .then(|| rest.to_ascii_lowercase().find(&format!("</{}", name)))
+                .flatten();
+            tokens.push(MarkupToken::Open { name, raw });
+            if let Some(end) = content_end {
+                tokens.push(MarkupToken::Text(&rest[..end]));
+                rest = &rest[end..];
+            }
+        }
+    }
+    tokens
+}
+
+/// the length of the tag at the start of `src`, skipping `>` inside quotes
+fn tag_end(src: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in src.char_indices().skip(1) {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('>', None) => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// reindents xml or html two spaces per level. elements holding nothing but
+/// short text stay on one line, and script, style and pre contents are left
+/// alone. malformed input is indented as best it can be rather than rejected
+pub fn pretty_markup(src: &str, html: bool) -> String {
+    let tokens = markup_tokens(src);
+    let mut out = String::with_capacity(src.len() + src.len() / 4);
+    let mut depth = 0usize;
+    let mut i = 0;
+    let push_line = |out: &mut String, depth: usize, text: &str| {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(text);
+        out.push('\n');
+    };
+    while i < tokens.len() {
+        match &tokens[i] {
+            MarkupToken::Open { name, raw } => {
+                if html && VOID_ELEMENTS.contains(&name.as_str()) {
+                    push_line(&mut out, depth, raw);
+                    i += 1;
+                    continue;
+                }
+                // raw elements are copied through to their closing tag
+                if html && RAW_ELEMENTS.contains(&name.as_str()) {
+                    let mut line = raw.to_string();
+                    i += 1;
+                    while i < tokens.len() {
+                        match &tokens[i] {
+                            MarkupToken::Close { name: close, raw } if close == name => {
+                                line.push_str(raw);
+                                break;
+                            }
+                            MarkupToken::Open { raw, .. }
+                            | MarkupToken::Close { raw, .. }
+                            | MarkupToken::Single(raw)
+                            | MarkupToken::Text(raw) => line.push_str(raw),
+                        }
+                        i += 1;
+                    }
+                    push_line(&mut out, depth, &line);
+                    i += 1;
+                    continue;
+                }
+                // <name>short text</name> on one line
+                if let (
+                    Some(MarkupToken::Text(text)),
+                    Some(MarkupToken::Close {
+                        name: close,
+                        raw: end,
+                    }),
+                ) = (tokens.get(i + 1), tokens.get(i + 2))
+                {
+                    let text = text.trim();
+                    if close == name && !text.contains('\n') && text.len() <= 80 {
+                        push_line(&mut out, depth, &format!("{}{}{}", raw, text, end));
+                        i += 3;
+                        continue;
+                    }
+                }
+                push_line(&mut out, depth, raw);
+                depth += 1;
+            }
+            MarkupToken::Close { raw, .. } => {
+                depth = depth.saturating_sub(1);
+                push_line(&mut out, depth, raw);
+            }
+            MarkupToken::Single(raw) => push_line(&mut out, depth, raw),
+            MarkupToken::Text(text) => {
+                for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                    push_line(&mut out, depth, line);
+                }
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_markup_into_tags_and_text() {
+        let src = r#"<?xml version="1.0"?><a x="1>2"><b/><!-- c <d> --><![CDATA[<e>]]>text</A>"#;
+        assert_eq!(
+            markup_tokens(src),
+            [
+                MarkupToken::Single(r#"<?xml version="1.0"?>"#),
+                MarkupToken::Open {
+                    name: "a".to_owned(),
+                    raw: r#"<a x="1>2">"#,
+                },
+                MarkupToken::Single("<b/>"),
+                MarkupToken::Single("<!-- c <d> -->"),
+                MarkupToken::Single("<![CDATA[<e>]]>"),
+                MarkupToken::Text("text"),
+                MarkupToken::Close {
+                    name: "a".to_owned(),
+                    raw: "</A>",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_script_contents_as_one_text_run() {
+        assert_eq!(
+            markup_tokens("<script>if (a<b) {}</SCRIPT> after <!-- open"),
+            [
+                MarkupToken::Open {
+                    name: "script".to_owned(),
+                    raw: "<script>",
+                },
+                MarkupToken::Text("if (a<b) {}"),
+                MarkupToken::Close {
+                    name: "script".to_owned(),
+                    raw: "</SCRIPT>",
+                },
+                MarkupToken::Text(" after "),
+                // an unterminated comment runs to the end
+                MarkupToken::Single("<!-- open"),
+            ]
+        );
+    }
+
+    #[test]
+    fn indents_nested_xml() {
+        let src = r#"<root><item id="1"><name> ann </name><tags><tag/></tags></item><!-- end --><![CDATA[x]]></root>"#;
+        assert_eq!(
+            pretty_markup(src, false),
+            "<root>\n\
+             \x20 <item id=\"1\">\n\
+             \x20   <name>ann</name>\n\
+             \x20   <tags>\n\
+             \x20     <tag/>\n\
+             \x20   </tags>\n\
+             \x20 </item>\n\
+             \x20 <!-- end -->\n\
+             \x20 <![CDATA[x]]>\n\
+             </root>\n"
+        );
+    }
+
+    #[test]
+    fn puts_multi_line_text_on_its_own_lines() {
+        assert_eq!(
+            pretty_markup("<p>\n   first line\n\n   second\n</p>", false),
+            "<p>\n  first line\n  second\n</p>\n"
+        );
+    }
+
+    #[test]
+    fn leaves_void_and_raw_html_elements_alone() {
+        let src = "<div><br><img src=\"a.png\"><pre>  keep\n  this</pre><script>if (a < b) {}</script></div>";
+        assert_eq!(
+            pretty_markup(src, true),
+            "<div>\n\
+             \x20 <br>\n\
+             \x20 <img src=\"a.png\">\n\
+             \x20 <pre>  keep\n  this</pre>\n\
+             \x20 <script>if (a < b) {}</script>\n\
+             </div>\n"
+        );
+        // xml has no void elements, so <br> opens a level there
+        assert_eq!(pretty_markup("<br><x/>", false), "<br>\n  <x/>\n");
+    }
+
+    #[test]
+    fn indents_malformed_markup_as_best_it_can() {
+        assert_eq!(
+            pretty_markup("</x><a><b></a>", false),
+            "</x>\n<a>\n  <b>\n  </a>\n"
+        );
+        assert_eq!(pretty_markup("<a x='>", false), "<a x='>\n");
+    }
+
+    #[test]
+    fn dumps_full_and_partial_hex_rows() {
+        let bytes = b"Hello, world!\x00\x01\x7f\xffabc";
+        assert_eq!(
+            hex_dump(bytes),
+            format!(
+                "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 00 01 7f  |Hello, world!...|\n\
+                 00000010  ff 61 62 63 {} |.abc|\n",
+                " ".repeat(12 * 3 + 1)
+            )
+        );
+        assert_eq!(hex_dump(&[]), "");
+    }
+
+    #[test]
+    fn stops_long_hex_dumps() {
+        let dump = hex_dump(&vec![0; MAX_HEX_BYTES + 10]);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), MAX_HEX_BYTES / 16 + 1);
+        assert!(lines[lines.len() - 2].starts_with("0000fff0  00 00"));
+        assert!(
+            lines[lines.len() - 1].starts_with("… showing the first"),
+            "{}",
+            lines[lines.len() - 1]
+        );
+    }
+}